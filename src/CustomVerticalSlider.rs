@@ -46,6 +46,10 @@ pub struct ParamSlider<'a, P: Param> {
     background_set_color: Color32,
     bar_set_color: Color32,
     use_padding: bool,
+    // Snap to this normalized step while dragging, Alt overrides it for fine control
+    snap_step: Option<f32>,
+    // Normalized value a double click jumps to instead of the param's default
+    double_click_value: Option<f32>,
 
     /// Will be set in the `ui()` function so we can request keyboard input focus on Alt+click.
     keyboard_focus_id: Option<egui::Id>,
@@ -68,6 +72,8 @@ impl<'a, P: Param> ParamSlider<'a, P> {
             background_set_color: Color32::TEMPORARY_COLOR,
             bar_set_color: Color32::TEMPORARY_COLOR,
             use_padding: false,
+            snap_step: None,
+            double_click_value: None,
 
             // I removed this because it was causing errors on plugin load somehow in FL
             keyboard_focus_id: None,
@@ -112,6 +118,20 @@ impl<'a, P: Param> ParamSlider<'a, P> {
         self
     }
 
+    /// Snap the value to a grid of this many normalized units while dragging. For linear ranges
+    /// like the band gains this is a fixed step in the plain value. Holding Alt skips snapping.
+    pub fn with_snap(mut self, snap_step: Option<f32>) -> Self {
+        self.snap_step = snap_step.filter(|step| *step > 0.0);
+        self
+    }
+
+    /// Double clicking sets this plain value as its own gesture instead of resetting to the
+    /// default. Ctrl+Click still resets.
+    pub fn with_double_click_value(mut self, value: P::Plain) -> Self {
+        self.double_click_value = Some(self.param.preview_normalized(value));
+        self
+    }
+
     fn plain_value(&self) -> P::Plain {
         self.param.modulated_plain_value()
     }
@@ -188,9 +208,10 @@ impl<'a, P: Param> ParamSlider<'a, P> {
         let total_drag_distance = drag_delta.x + Self::get_drag_amount_memory(ui);
         Self::set_drag_amount_memory(ui, total_drag_distance);
 
-        self.set_normalized_value(
+        self.set_normalized_value(self.snapped(
+            ui,
             (start_value + (total_drag_distance * GRANULAR_DRAG_MULTIPLIER)).clamp(0.0, 1.0),
-        );
+        ));
     }
 
     fn end_drag(&self) {
@@ -220,6 +241,14 @@ impl<'a, P: Param> ParamSlider<'a, P> {
         ui.memory_mut(|i| i.data.insert_temp(*DRAG_AMOUNT_MEMORY_ID, amount));
     }
 
+    /// Applies the snap grid to a normalized value unless Alt is held
+    fn snapped(&self, ui: &Ui, normalized: f32) -> f32 {
+        match self.snap_step {
+            Some(step) if !ui.input(|i| i.modifiers.alt) => snap_normalized(normalized, step),
+            _ => normalized,
+        }
+    }
+
     fn slider_ui(&self, ui: &mut Ui, response: &mut Response) {
         // Handle user input
         // TODO: Optionally (since it can be annoying) add scrolling behind a builder option
@@ -243,13 +272,20 @@ impl<'a, P: Param> ParamSlider<'a, P> {
                 let proportion =
                     egui::emath::remap_clamp(click_pos.y, response.rect.y_range(), 0.0..=1.0)
                         as f64;
-                self.set_normalized_value(1.0 - proportion as f32);
+                self.set_normalized_value(self.snapped(ui, 1.0 - proportion as f32));
                 response.mark_changed();
                 Self::set_drag_amount_memory(ui, 0.0);
             }
         }
         if response.double_clicked() {
-            self.reset_param();
+            match self.double_click_value {
+                Some(normalized) => {
+                    self.begin_drag();
+                    self.set_normalized_value(normalized);
+                    self.end_drag();
+                }
+                None => self.reset_param(),
+            }
             response.mark_changed();
         }
         if response.drag_released() {
@@ -443,3 +479,11 @@ impl<P: Param> Widget for ParamSlider<'_, P> {
         .inner
     }
 }
+
+/// Round a normalized value to the nearest multiple of `step`, staying within `[0, 1]`
+pub fn snap_normalized(normalized: f32, step: f32) -> f32 {
+    if step <= 0.0 {
+        return normalized;
+    }
+    ((normalized / step).round() * step).clamp(0.0, 1.0)
+}
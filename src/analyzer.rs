@@ -0,0 +1,277 @@
+// analyzer.rs - Ardura 2023
+// Spectrum analyzer: a ring buffer of samples, a Hann window and a plain radix-2 FFT
+// Everything is allocated up front so pushing samples and computing frames never allocates
+// The plugin's analyzer only fills the ring in process and hands full frames to a background task
+// for the FFT, see `AnalyzerTask`.
+
+/// FFT length, has to be a power of two
+pub(crate) const FFT_SIZE: usize = 2048;
+pub(crate) const NUM_BINS: usize = FFT_SIZE / 2;
+/// A new frame is computed every this many samples
+const HOP_SIZE: usize = FFT_SIZE / 2;
+/// Bottom of the analyzer's dB scale, the top is 0 dBFS
+pub(crate) const ANALYZER_MIN_DB: f32 = -96.0;
+/// How far the displayed bins can fall per frame so the spectrum doesn't flicker
+const FALLOFF_DB: f32 = 3.0;
+
+// What the analyzer shows: one summed spectrum, or left and right, or mid and side overlaid, or
+// the input with how the output differs from it
+pub(crate) const ANALYZER_SUM: u32 = 0;
+pub(crate) const ANALYZER_LR: u32 = 1;
+pub(crate) const ANALYZER_MS: u32 = 2;
+pub(crate) const ANALYZER_DIFF: u32 = 3;
+/// The difference is averaged over this much of an octave either side of each bin, and each frame
+/// only moves it this far towards the new one. Transients make single frames very noisy.
+const DIFFERENCE_OCTAVES: f32 = 1.0 / 12.0;
+const DIFFERENCE_SMOOTHING: f32 = 0.2;
+
+/// Background work sent from process
+pub(crate) enum AnalyzerTask {
+    /// A new frame of samples is waiting in the shared frame buffer. Split frames carry a second
+    /// channel after the first for L/R and M/S, or the output for the difference. The hold and
+    /// spectrogram keep the first channel's frames.
+    ComputeFrame { split: bool, difference: bool, hold: bool, spectrogram: bool },
+    /// A frame for the resonance suppressor is waiting in its own buffer, find the strongest
+    /// resonance between the bounds
+    DetectResonance { low: f32, high: f32 },
+    /// A pair of Match EQ frames is waiting in its own buffer, add it to the average. The first
+    /// frame of a capture starts the average over.
+    MatchFrame { first: bool },
+    /// The Match EQ capture has heard enough, hand the average over to the GUI
+    MatchFinish,
+}
+
+pub(crate) struct Analyzer {
+    ring: Vec<f32>,
+    write_pos: usize,
+    samples_since_frame: usize,
+    window: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    // Latest frame straight from the FFT and the smoothed copy for display, both in dBFS
+    frame_db: Vec<f32>,
+    display_db: Vec<f32>,
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos()
+            })
+            .collect();
+        Analyzer {
+            ring: vec![0.0; FFT_SIZE],
+            write_pos: 0,
+            samples_since_frame: 0,
+            window,
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            frame_db: vec![ANALYZER_MIN_DB; NUM_BINS],
+            display_db: vec![ANALYZER_MIN_DB; NUM_BINS],
+        }
+    }
+
+    /// Add a sample, returns true when enough have come in for a new frame
+    pub fn push(&mut self, sample: f32) -> bool {
+        self.ring[self.write_pos] = sample;
+        self.write_pos = (self.write_pos + 1) % FFT_SIZE;
+        self.samples_since_frame += 1;
+        if self.samples_since_frame >= HOP_SIZE {
+            self.samples_since_frame = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Copy the ring buffer out oldest to newest, `frame` has to be FFT_SIZE long
+    pub fn copy_frame(&self, frame: &mut [f32]) {
+        let (newest, oldest) = self.ring.split_at(self.write_pos);
+        frame[..oldest.len()].copy_from_slice(oldest);
+        frame[oldest.len()..].copy_from_slice(newest);
+    }
+
+    /// Window a frame from `copy_frame`, run the FFT and update the bins
+    pub fn compute_frame(&mut self, frame: &[f32]) {
+        for ((re, im), (sample, window)) in self
+            .re
+            .iter_mut()
+            .zip(self.im.iter_mut())
+            .zip(frame.iter().zip(self.window.iter()))
+        {
+            *re = sample * window;
+            *im = 0.0;
+        }
+        self.transform();
+    }
+
+    // FFT the windowed samples in `re` and update both sets of bins
+    fn transform(&mut self) {
+        fft(&mut self.re, &mut self.im);
+
+        // A full scale sine reads 0 dBFS: the Hann window's coherent gain is 1/2 and we only
+        // keep the positive half of the spectrum, so scale by 4 / N
+        let scale = 4.0 / FFT_SIZE as f32;
+        for bin in 0..NUM_BINS {
+            let magnitude = (self.re[bin] * self.re[bin] + self.im[bin] * self.im[bin]).sqrt() * scale;
+            let db = (20.0 * magnitude.max(1e-9).log10()).max(ANALYZER_MIN_DB);
+            self.frame_db[bin] = db;
+            self.display_db[bin] = db.max(self.display_db[bin] - FALLOFF_DB);
+        }
+    }
+
+    /// Latest unsmoothed frame in dBFS
+    pub fn frame(&self) -> &[f32] {
+        &self.frame_db
+    }
+
+    /// Smoothed bins for drawing in dBFS
+    pub fn display(&self) -> &[f32] {
+        &self.display_db
+    }
+}
+
+/// In place iterative radix-2 FFT, both slices must be the same power of two length
+pub(crate) fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    // Bit reversal reordering
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    // Butterflies
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * std::f32::consts::PI / len as f32;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let mut cur_re = 1.0;
+            let mut cur_im = 0.0;
+            for k in 0..len / 2 {
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                let next_re = cur_re * w_re - cur_im * w_im;
+                cur_im = cur_re * w_im + cur_im * w_re;
+                cur_re = next_re;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Center frequency of a bin
+pub(crate) fn bin_to_freq(bin: f32, sample_rate: f32) -> f32 {
+    bin * sample_rate / FFT_SIZE as f32
+}
+
+/// The loudest bin's frequency with parabolic interpolation between its neighbours for sub-bin
+/// accuracy. Returns None when the loudest bin is under `gate_db` so silence and noise floor
+/// don't make the readout jump around.
+pub(crate) fn peak_frequency(bins_db: &[f32], sample_rate: f32, gate_db: f32) -> Option<f32> {
+    // Skip DC
+    let (peak_bin, peak_db) = bins_db
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold((0, f32::MIN), |best, (bin, db)| if *db > best.1 { (bin, *db) } else { best });
+    if peak_bin == 0 || peak_db < gate_db {
+        return None;
+    }
+
+    let offset = if peak_bin + 1 < bins_db.len() {
+        let before = bins_db[peak_bin - 1];
+        let after = bins_db[peak_bin + 1];
+        let denominator = before - 2.0 * peak_db + after;
+        if denominator.abs() > f32::EPSILON {
+            (0.5 * (before - after) / denominator).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+    Some(bin_to_freq(peak_bin as f32 + offset, sample_rate))
+}
+
+/// Average in dB of the bins within `octaves` either side of a frequency, what the drawn spectrum
+/// sits at around there
+pub(crate) fn level_at(bins_db: &[f32], sample_rate: f32, freq: f32, octaves: f32) -> f32 {
+    let bin_of = |freq: f32| ((freq * FFT_SIZE as f32 / sample_rate).round() as usize).clamp(1, bins_db.len() - 1);
+    let spread = 2.0_f32.powf(octaves);
+    let bins = &bins_db[bin_of(freq / spread)..=bin_of(freq * spread)];
+    bins.iter().sum::<f32>() / bins.len() as f32
+}
+
+/// How far the bin at a frequency stands above the average of the bins up to an octave either
+/// side. A resonance sticks out of its neighbours where the broad tonal balance doesn't.
+pub(crate) fn prominence_db(bins_db: &[f32], sample_rate: f32, freq: f32) -> f32 {
+    let bin_of = |freq: f32| ((freq * FFT_SIZE as f32 / sample_rate).round() as usize).clamp(1, bins_db.len() - 1);
+    let center = bin_of(freq);
+    // The bins right next to the center are part of the same peak
+    let neighbours: Vec<f32> = (bin_of(freq / 2.0)..=bin_of(freq * 2.0))
+        .filter(|bin| bin.abs_diff(center) > 1)
+        .map(|bin| bins_db[bin])
+        .collect();
+    if neighbours.is_empty() {
+        return 0.0;
+    }
+    bins_db[center] - neighbours.iter().sum::<f32>() / neighbours.len() as f32
+}
+
+/// The frequency between the bounds that stands out most from its neighbours and by how much, see
+/// `prominence_db`. None when nothing in the region pokes above them, bins under `gate_db` are
+/// skipped so the noise floor's ripple isn't taken for resonances.
+pub(crate) fn strongest_resonance(
+    bins_db: &[f32],
+    sample_rate: f32,
+    low: f32,
+    high: f32,
+    gate_db: f32,
+) -> Option<(f32, f32)> {
+    let first = ((low * FFT_SIZE as f32 / sample_rate).round() as usize).max(1);
+    let last = ((high * FFT_SIZE as f32 / sample_rate).round() as usize).min(bins_db.len() - 1);
+    (first..=last)
+        .filter(|bin| bins_db[*bin] >= gate_db)
+        .map(|bin| {
+            let freq = bin_to_freq(bin as f32, sample_rate);
+            (freq, prominence_db(bins_db, sample_rate, freq))
+        })
+        .filter(|(_, prominence)| *prominence > 0.0)
+        .fold(None, |best: Option<(f32, f32)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+}
+
+/// Move `difference` towards how far the output's bins sit above the input's. Only bins where the
+/// input is over `gate_db` count, anything under that is mostly noise floor and reads as nothing.
+/// See `DIFFERENCE_OCTAVES` and `DIFFERENCE_SMOOTHING` for the smoothing.
+pub(crate) fn smooth_difference(difference: &mut [f32], pre_db: &[f32], post_db: &[f32], gate_db: f32) {
+    let spread = 2.0_f32.powf(DIFFERENCE_OCTAVES);
+    for bin in 1..difference.len() {
+        let first = ((bin as f32 / spread).floor() as usize).max(1);
+        let last = ((bin as f32 * spread).ceil() as usize).min(difference.len() - 1);
+        let (sum, count) = (first..=last)
+            .filter(|bin| pre_db[*bin] >= gate_db)
+            .fold((0.0, 0), |(sum, count), bin| (sum + post_db[bin] - pre_db[bin], count + 1));
+        let target = if count > 0 { sum / count as f32 } else { 0.0 };
+        difference[bin] += (target - difference[bin]) * DIFFERENCE_SMOOTHING;
+    }
+}
@@ -0,0 +1,102 @@
+// balance_meter.rs - Ardura 2023
+// A left/right leaning indicator for the spectral balance (tilt) of a signal
+// Draws from the center out: left means darker, right means brighter
+
+use nih_plug_egui::egui::{
+    vec2, Color32, NumExt, Pos2, Rect, Response, Sense, Shape, Stroke, TextStyle, Ui, Vec2,
+    Widget, WidgetText,
+};
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct BalanceMeter {
+    balance: f32,
+    text: Option<WidgetText>,
+    border_color: Color32,
+    bar_color: Color32,
+    background_color: Color32,
+}
+
+impl BalanceMeter {
+    /// Balance in the `[-1, 1]` range, where `-1` is fully dark and `1` is fully bright.
+    pub fn new(balance: f32) -> Self {
+        Self {
+            balance: balance.clamp(-1.0, 1.0),
+            text: None,
+            border_color: Color32::BLACK,
+            bar_color: Color32::GREEN,
+            background_color: Color32::GRAY,
+        }
+    }
+
+    /// A custom text to display on the meter.
+    pub fn text(mut self, text: impl Into<WidgetText>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Set the color of the outline, center mark and text
+    pub fn set_border_color(&mut self, new_color: Color32) {
+        self.border_color = new_color;
+    }
+
+    /// Set the indicator color for the meter
+    pub fn set_bar_color(&mut self, new_color: Color32) {
+        self.bar_color = new_color;
+    }
+
+    /// Set the background color
+    pub fn set_background_color(&mut self, new_color: Color32) {
+        self.background_color = new_color;
+    }
+}
+
+impl Widget for BalanceMeter {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let desired_width = ui.available_size_before_wrap().x.at_least(96.0);
+        let height = ui.spacing().interact_size.y;
+        let (outer_rect, response) =
+            ui.allocate_exact_size(vec2(desired_width, height), Sense::hover());
+
+        if ui.is_rect_visible(response.rect) {
+            let visuals = ui.style().visuals.clone();
+            ui.painter().rect(
+                outer_rect,
+                0.0,
+                self.background_color,
+                Stroke::new(1.0, self.border_color),
+            );
+
+            // Indicator leans from the center towards the dark or bright side
+            let center_x = outer_rect.center().x;
+            let lean_x = center_x + (outer_rect.width() / 2.0) * self.balance;
+            let inner_rect = Rect::from_x_y_ranges(
+                center_x.min(lean_x)..=center_x.max(lean_x),
+                outer_rect.y_range(),
+            );
+            ui.painter()
+                .rect(inner_rect, 0.0, self.bar_color, Stroke::NONE);
+
+            // Center marker so "balanced" is easy to read at a glance
+            let points: Vec<Pos2> = vec![
+                Pos2::new(center_x, outer_rect.top()),
+                Pos2::new(center_x, outer_rect.bottom()),
+            ];
+            ui.painter()
+                .add(Shape::line(points, Stroke::new(1.0, self.border_color)));
+
+            if let Some(text) = self.text {
+                let galley = text.into_galley(ui, Some(false), f32::INFINITY, TextStyle::Button);
+                let text_pos = outer_rect.left_center() - Vec2::new(0.0, galley.size().y / 2.0)
+                    + vec2(ui.spacing().item_spacing.x, 0.0);
+                let text_color = visuals.override_text_color.unwrap_or(self.border_color);
+                galley.paint_with_fallback_color(
+                    &ui.painter().with_clip_rect(outer_rect),
+                    text_pos,
+                    text_color,
+                );
+            }
+        }
+
+        response
+    }
+}
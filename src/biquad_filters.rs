@@ -3,11 +3,68 @@
 // Adapted to rust by Ardura
 
 use nih_plug::params::enums::Enum;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 // This is for my sanity
 const LEFT: usize = 0;
 const RIGHT: usize = 1;
 
+/// Highest center frequency we design for as a fraction of the sample rate (90% of Nyquist).
+/// The bilinear transform squashes everything up there so a 20 kHz shelf at 44.1 kHz would be
+/// mostly cramped against Nyquist, anything above this is held here instead.
+pub(crate) const MAX_FREQ_RATIO: f32 = 0.45;
+
+/// a0 smaller than this is treated as degenerate and the biquad passes through instead
+const DEGENERATE_A0: f32 = 1e-6;
+
+/// Q range every filter is designed inside. Wider than the Res knobs since Q Scale and the
+/// editor's own filters go past them, anything outside is held at the edge.
+pub(crate) const MIN_Q: f32 = 0.0025;
+pub(crate) const MAX_Q: f32 = 40.0;
+
+/// Gains are held inside this either way, far past the knobs but a stray value can't run the
+/// output off to infinity
+const MAX_GAIN_DB: f32 = 48.0;
+
+/// How many times a biquad has gone degenerate and started passing through. Process can't log
+/// so the editor reports it when this moves.
+pub(crate) static DEGENERATE_BIQUADS: AtomicU32 = AtomicU32::new(0);
+
+/// Soft Q starts easing resonant bands off above this fraction of Nyquist
+const SOFT_Q_START: f32 = 0.5;
+/// How much of the Q is taken away by the time a band reaches the MAX_FREQ_RATIO limit
+const SOFT_Q_DEPTH: f32 = 0.5;
+/// Soft Q never takes a band below this
+const SOFT_Q_FLOOR: f32 = 0.707;
+
+// Angular frequency of the center with the Nyquist limit applied
+fn design_omega(center_freq: f32, sample_rate: f32) -> f32 {
+    2.0 * std::f32::consts::PI * center_freq.min(sample_rate * MAX_FREQ_RATIO) / sample_rate
+}
+
+// Near Nyquist the bilinear transform squeezes resonances into thin shrill spikes, soft Q eases
+// Q down over the top of the range so high bands stay musical
+fn design_q(omega: f32, q_factor: f32, soft_q: bool) -> f32 {
+    let q_factor = q_factor.clamp(MIN_Q, MAX_Q);
+    if soft_q && q_factor > SOFT_Q_FLOOR {
+        let nyquist_fraction = omega / std::f32::consts::PI;
+        let t = ((nyquist_fraction - SOFT_Q_START) / (MAX_FREQ_RATIO * 2.0 - SOFT_Q_START)).clamp(0.0, 1.0);
+        (q_factor * (1.0 - SOFT_Q_DEPTH * t * t)).max(SOFT_Q_FLOOR)
+    } else {
+        q_factor
+    }
+}
+
+// Bandwidth term from the Q
+fn design_alpha(omega: f32, q_factor: f32, soft_q: bool) -> f32 {
+    omega.sin() / (2.0 * design_q(omega, q_factor, soft_q))
+}
+
+/// The Q a band is really designed with at a frequency once soft Q has had its say
+pub(crate) fn effective_q(center_freq: f32, sample_rate: f32, q_factor: f32, soft_q: bool) -> f32 {
+    design_q(design_omega(center_freq, sample_rate), q_factor, soft_q)
+}
+
 // These are the filter types implemented
 #[derive(Clone, Copy, Enum, PartialEq)]
 pub(crate) enum FilterType {
@@ -21,6 +78,58 @@ pub(crate) enum FilterType {
     HighShelf,
 }
 
+impl FilterType {
+    // Only these types do anything with the gain param
+    pub fn uses_gain(&self) -> bool {
+        matches!(self, FilterType::Peak | FilterType::LowShelf | FilterType::HighShelf)
+    }
+
+    // Hover help for the editor, says what the type does and what each knob means for it
+    pub fn description(&self) -> &'static str {
+        match self {
+            FilterType::Off => "Off: the band does nothing",
+            FilterType::LowPass => "Low Pass: removes everything above the frequency. Res sets the resonance at the cutoff, gain is unused",
+            FilterType::HighPass => "High Pass: removes everything below the frequency. Res sets the resonance at the cutoff, gain is unused",
+            FilterType::BandPass => "Band Pass: keeps only a band around the frequency. Res narrows the band, gain is unused",
+            FilterType::Notch => "Notch: cuts a narrow band at the frequency. Res narrows the cut, gain is unused",
+            FilterType::Peak => "Peak: boosts or cuts around the frequency by the gain. Res narrows the bell",
+            FilterType::LowShelf => "Low Shelf: boosts or cuts everything below the frequency by the gain. Res sets the slope",
+            FilterType::HighShelf => "High Shelf: boosts or cuts everything above the frequency by the gain. Res sets the slope",
+        }
+    }
+}
+
+/// Bandwidth in octaves between the -3 dB points for a Q, straight from RBJ's cookbook
+pub(crate) fn q_to_octaves(q_factor: f32) -> f32 {
+    2.0 / std::f32::consts::LN_2 * (1.0 / (2.0 * q_factor)).asinh()
+}
+
+/// Bandwidth in Hz between the -3 dB points of a band pass at a center and Q. The cookbook
+/// filters are the analog ones through the bilinear transform prewarped at the center, so the
+/// points sit where the analog ones do on the warped tan(omega / 2) axis. f / Q is only the
+/// analog answer and reads too wide towards Nyquist.
+pub(crate) fn q_to_bandwidth_hz(center_freq: f32, q_factor: f32, sample_rate: f32) -> f32 {
+    let warped_center = (design_omega(center_freq, sample_rate) / 2.0).tan();
+    let half_width = 1.0 / (2.0 * q_factor);
+    let spread = (1.0 + half_width * half_width).sqrt();
+    let edge = |warped: f32| sample_rate / std::f32::consts::PI * warped.atan();
+    edge(warped_center * (spread + half_width)) - edge(warped_center * (spread - half_width))
+}
+
+/// Group delay in samples of c0 + c1 z^-1 + c2 z^-2 at omega, Re(sum k c_k z^-k / sum c_k z^-k).
+/// A filter's is its numerator's minus its denominator's.
+pub(crate) fn polynomial_delay(coeffs: [f32; 3], omega: f32) -> f32 {
+    let (mut re, mut im, mut weighted_re, mut weighted_im) = (0.0, 0.0, 0.0, 0.0);
+    for (k, coeff) in coeffs.iter().enumerate() {
+        let (sin, cos) = (k as f32 * omega).sin_cos();
+        re += coeff * cos;
+        im -= coeff * sin;
+        weighted_re += k as f32 * coeff * cos;
+        weighted_im -= k as f32 * coeff * sin;
+    }
+    (weighted_re * re + weighted_im * im) / (re * re + im * im).max(1e-12)
+}
+
 // I wanted these separate from the main struct for readability
 #[derive(Clone, Copy)]
 struct BiquadCoefficients {
@@ -42,6 +151,7 @@ impl BiquadCoefficients {
         let a0: f32;
         let a1: f32;
         let a2: f32;
+        let peak_gain = peak_gain.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
         let cos_omega = omega.cos();
         let sin_omega = omega.sin();
         match biquad_type {
@@ -124,6 +234,29 @@ impl BiquadCoefficients {
             a2: a2,
         }
     }
+
+    // Everything gets divided by a0 so a tiny one (or anything non finite) blows up the output,
+    // and poles on or outside the unit circle (a negative or zero frequency) run away on their own
+    fn is_degenerate(&self) -> bool {
+        let all = [self.b0, self.b1, self.b2, self.a0, self.a1, self.a2];
+        if self.a0.abs() < DEGENERATE_A0 || all.iter().any(|coeff| !coeff.is_finite()) {
+            return true;
+        }
+        let a1 = self.a1 / self.a0;
+        let a2 = self.a2 / self.a0;
+        a2.abs() >= 1.0 || a1.abs() >= 1.0 + a2
+    }
+
+    fn pass_through() -> Self {
+        BiquadCoefficients {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a0: 1.0,
+            a1: 0.0,
+            a2: 0.0,
+        }
+    }
 }
 
 // This is the main Biquad struct, once more trying to make things clearer
@@ -135,6 +268,9 @@ pub(crate) struct Biquad {
     center_freq: f32,
     gain_db: f32,
     q_factor: f32,
+    soft_q: bool,
+    // Passing through because the settings gave unusable coefficients
+    degenerate: bool,
     // Tracks previous outputs
     input_history: [[f32; 2]; 2],
     output_history: [[f32; 2]; 2],
@@ -148,24 +284,30 @@ pub(crate) struct Biquad {
 pub(crate) struct InterleavedBiquad {
     interleaves: usize,
     current_index: usize,
+    // Which biquad the rotation starts from after a reset or interleave change
+    start_offset: usize,
     biquad_array: [Biquad; 10],
 }
 
 impl Biquad {
     pub fn new(sample_rate: f32, center_freq: f32, gain_db: f32, q_factor: f32, biquad_type: FilterType) -> Self {
-        let omega = 2.0 * std::f32::consts::PI * center_freq / sample_rate;
-        let alpha = (omega.sin()) / (2.0 * q_factor);
+        let omega = design_omega(center_freq, sample_rate);
+        let alpha = design_alpha(omega, q_factor, false);
 
-        Biquad {
+        let mut biquad = Biquad {
             biquad_type: biquad_type,
             sample_rate,
             center_freq,
             gain_db,
             q_factor,
+            soft_q: false,
+            degenerate: false,
             input_history: [[0.0, 0.0]; 2],
             output_history: [[0.0, 0.0]; 2],
-            coeffs: BiquadCoefficients::new(biquad_type, alpha, omega, gain_db),
-        }
+            coeffs: BiquadCoefficients::pass_through(),
+        };
+        biquad.set_coeffs(BiquadCoefficients::new(biquad_type, alpha, omega, gain_db));
+        biquad
     }
 
     // This is meant to only recalculate when there's an actual update as this method runs often
@@ -188,20 +330,84 @@ impl Biquad {
             recalc = true;
         }
         if recalc {
-            // Calculate our intermediate variables from our new info and create new coefficients
-            let omega = 2.0 * std::f32::consts::PI * center_freq / sample_rate;
-            let alpha = (omega.sin()) / (2.0 * q_factor);
-            self.coeffs = BiquadCoefficients::new(self.biquad_type, alpha, omega, self.gain_db);
+            self.recalculate();
+        }
+    }
+
+    // Soft Q eases off the Q of bands close to Nyquist, off by default
+    pub fn set_soft_q(&mut self, soft_q: bool) {
+        if self.soft_q != soft_q {
+            self.soft_q = soft_q;
+            self.recalculate();
+        }
+    }
+
+    // Calculate our intermediate variables from our current info and create new coefficients
+    fn recalculate(&mut self) {
+        let omega = design_omega(self.center_freq, self.sample_rate);
+        let alpha = design_alpha(omega, self.q_factor, self.soft_q);
+        self.set_coeffs(BiquadCoefficients::new(self.biquad_type, alpha, omega, self.gain_db));
+    }
+
+    // Degenerate coefficients pass through instead, only counted when the biquad first goes that
+    // way so a band stuck there doesn't count every update. Off is all zeroes on purpose and never
+    // gets run.
+    fn set_coeffs(&mut self, coeffs: BiquadCoefficients) {
+        let degenerate = self.biquad_type != FilterType::Off && coeffs.is_degenerate();
+        if degenerate && !self.degenerate {
+            DEGENERATE_BIQUADS.fetch_add(1, Ordering::Relaxed);
+        }
+        self.degenerate = degenerate;
+        self.coeffs = if degenerate { BiquadCoefficients::pass_through() } else { coeffs };
+    }
+
+    // Numerator and denominator of the transfer function on the unit circle at a frequency, as
+    // (num_re, num_im, den_re, den_im)
+    fn transfer(&self, freq: f32) -> (f32, f32, f32, f32) {
+        let omega = 2.0 * std::f32::consts::PI * freq / self.sample_rate;
+        let (sin_1, cos_1) = omega.sin_cos();
+        let (sin_2, cos_2) = (2.0 * omega).sin_cos();
+        (
+            self.coeffs.b0 + self.coeffs.b1 * cos_1 + self.coeffs.b2 * cos_2,
+            -(self.coeffs.b1 * sin_1 + self.coeffs.b2 * sin_2),
+            self.coeffs.a0 + self.coeffs.a1 * cos_1 + self.coeffs.a2 * cos_2,
+            -(self.coeffs.a1 * sin_1 + self.coeffs.a2 * sin_2),
+        )
+    }
+
+    // Magnitude in dB at a frequency, straight from evaluating the transfer function on the unit circle
+    pub fn frequency_response(&self, freq: f32) -> f32 {
+        if self.biquad_type == FilterType::Off {
+            return 0.0;
+        }
+        let (num_re, num_im, den_re, den_im) = self.transfer(freq);
+        let magnitude = ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt();
+        20.0 * magnitude.max(1e-6).log10()
+    }
+
+    // Phase shift in radians at a frequency, not wrapped
+    pub fn phase_response(&self, freq: f32) -> f32 {
+        if self.biquad_type == FilterType::Off {
+            return 0.0;
         }
+        let (num_re, num_im, den_re, den_im) = self.transfer(freq);
+        num_im.atan2(num_re) - den_im.atan2(den_re)
+    }
+
+    // Group delay in samples at a frequency
+    pub fn group_delay(&self, freq: f32) -> f32 {
+        if self.biquad_type == FilterType::Off {
+            return 0.0;
+        }
+        let omega = 2.0 * std::f32::consts::PI * freq / self.sample_rate;
+        polynomial_delay([self.coeffs.b0, self.coeffs.b1, self.coeffs.b2], omega)
+            - polynomial_delay([self.coeffs.a0, self.coeffs.a1, self.coeffs.a2], omega)
     }
 
     pub fn set_type(&mut self, biquad_type: FilterType) {
         if self.biquad_type != biquad_type {
             self.biquad_type = biquad_type;
-            // Calculate our intermediate variables from our new info and create new coefficients
-            let omega = 2.0 * std::f32::consts::PI * self.center_freq / self.sample_rate;
-            let alpha = (omega.sin()) / (2.0 * self.q_factor);
-            self.coeffs = BiquadCoefficients::new(self.biquad_type, alpha, omega, self.gain_db);
+            self.recalculate();
         }
     }
 
@@ -246,6 +452,7 @@ impl InterleavedBiquad {
         InterleavedBiquad {
             interleaves: new_interleave,
             current_index: 0,
+            start_offset: 0,
             biquad_array: [Biquad::new(sample_rate, center_freq, gain_db, q_factor, biquad_type); 10],
         }
     }
@@ -262,8 +469,32 @@ impl InterleavedBiquad {
         }
     }
 
+    pub fn set_soft_q(&mut self, soft_q: bool) {
+        for biquad in self.biquad_array.iter_mut() {
+            biquad.set_soft_q(soft_q);
+        }
+    }
+
     pub fn set_interleave(&mut self, new_interleave: usize) {
-        self.interleaves = new_interleave.clamp(2, 10);
+        let new_interleave = new_interleave.clamp(2, 10);
+        if self.interleaves != new_interleave {
+            self.interleaves = new_interleave;
+            self.reset_index();
+        }
+    }
+
+    // All the biquads share coefficients so on a steady signal any offset sounds the same, what it
+    // changes is which biquad's history picks the signal back up after a reset or an interleave
+    // change, and with that the transient you hear at that moment
+    pub fn set_start_offset(&mut self, new_offset: usize) {
+        if self.start_offset != new_offset {
+            self.start_offset = new_offset;
+            self.reset_index();
+        }
+    }
+
+    pub fn reset_index(&mut self) {
+        self.current_index = self.start_offset % self.interleaves;
     }
 
     pub fn increment_index(&mut self) {
@@ -283,4 +514,195 @@ impl InterleavedBiquad {
         // Return
         (output_l, output_r)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Lower and upper frequencies where a response falls 3 dB under its peak, found on a fine
+    /// log grid across the audible range and interpolated between grid points
+    pub(crate) fn minus_3db_points(response: impl Fn(&[f32]) -> Vec<f32>) -> (f32, f32) {
+        let freqs: Vec<f32> = (0..=8000).map(|i| 20.0 * 1000.0_f32.powf(i as f32 / 8000.0)).collect();
+        let db = response(&freqs);
+        let peak_index = (0..db.len()).fold(0, |best, i| if db[i] > db[best] { i } else { best });
+        let edge = db[peak_index] - 3.0;
+        let interpolate = |a: usize, b: usize| {
+            let t = (edge - db[a]) / (db[b] - db[a]);
+            freqs[a] * (freqs[b] / freqs[a]).powf(t)
+        };
+        let lower = (1..=peak_index)
+            .rev()
+            .find(|&i| db[i - 1] < edge)
+            .map(|i| interpolate(i - 1, i))
+            .expect("no lower -3 dB point");
+        let upper = (peak_index..freqs.len() - 1)
+            .find(|&i| db[i + 1] < edge)
+            .map(|i| interpolate(i, i + 1))
+            .expect("no upper -3 dB point");
+        (lower, upper)
+    }
+
+    #[test]
+    fn bad_settings_keep_the_output_finite() {
+        let freqs = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -1000.0, 0.0, 1e-9, 1e9];
+        let gains = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -1000.0, 1000.0];
+        let qs = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -1.0, 0.0, 1e-9, 1e9];
+        let types = [
+            FilterType::LowPass,
+            FilterType::HighPass,
+            FilterType::BandPass,
+            FilterType::Notch,
+            FilterType::Peak,
+            FilterType::LowShelf,
+            FilterType::HighShelf,
+        ];
+        for filter_type in types {
+            for &freq in freqs.iter().chain([1000.0].iter()) {
+                for &gain in gains.iter().chain([6.0].iter()) {
+                    for &q in qs.iter().chain([0.707].iter()) {
+                        let mut biquad = Biquad::new(SAMPLE_RATE, 1000.0, 0.0, 0.707, filter_type);
+                        biquad.update(SAMPLE_RATE, freq, gain, q);
+                        for i in 0..4096 {
+                            let input = (i as f32 * 0.1).sin();
+                            let (left, right) = biquad.process_sample(input, -input);
+                            assert!(
+                                left.is_finite() && right.is_finite(),
+                                "freq {freq}, gain {gain}, q {q} went non finite"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bandwidth_matches_the_measured_minus_3db_points() {
+        for (center, q) in [(200.0, 0.5), (1000.0, 0.707), (8000.0, 0.707), (8000.0, 4.0), (15000.0, 2.0)] {
+            let biquad = Biquad::new(SAMPLE_RATE, center, 0.0, q, FilterType::BandPass);
+            let (lower, upper) = minus_3db_points(|freqs| {
+                freqs.iter().map(|freq| biquad.frequency_response(*freq)).collect()
+            });
+            let measured = upper - lower;
+            let bandwidth = q_to_bandwidth_hz(center, q, SAMPLE_RATE);
+            assert!(
+                (bandwidth / measured - 1.0).abs() < 0.005,
+                "{center} Hz at Q {q}: {bandwidth} Hz against {measured} Hz measured"
+            );
+        }
+    }
+
+    #[test]
+    fn degenerate_settings_are_counted_once() {
+        let mut biquad = Biquad::new(SAMPLE_RATE, 1000.0, 0.0, 0.707, FilterType::Peak);
+        let before = DEGENERATE_BIQUADS.load(Ordering::Relaxed);
+        for _ in 0..100 {
+            biquad.update(SAMPLE_RATE, 1000.0, f32::NAN, 0.707);
+        }
+        assert!(biquad.degenerate);
+        assert!(DEGENERATE_BIQUADS.load(Ordering::Relaxed) > before);
+
+        // Back to something usable and it runs the filter again
+        biquad.update(SAMPLE_RATE, 1000.0, 6.0, 0.707);
+        assert!(!biquad.degenerate);
+        let fresh = Biquad::new(SAMPLE_RATE, 1000.0, 6.0, 0.707, FilterType::Peak);
+        assert!(biquad.frequency_response(1000.0) > 0.0);
+        assert_eq!(biquad.frequency_response(1000.0), fresh.frequency_response(1000.0));
+    }
+
+    #[test]
+    fn shelf_near_nyquist_stays_sane() {
+        for gain in [-12.0, 6.0, 12.0] {
+            let shelf = Biquad::new(SAMPLE_RATE, 20000.0, gain, 0.707, FilterType::HighShelf);
+            // Nothing happens down low and the top of the band heads to the shelf's gain without
+            // overshooting it or flipping the wrong way
+            assert!(shelf.frequency_response(1000.0).abs() < 0.1, "{gain} dB shelf at 1 kHz");
+            let mut last = 0.0;
+            for freq in [5000.0, 10000.0, 15000.0, 20000.0, 22000.0, 23900.0] {
+                let db = shelf.frequency_response(freq);
+                assert!(db * gain >= 0.0 && db.abs() <= gain.abs() + 0.1, "{gain} dB shelf at {freq} Hz: {db} dB");
+                assert!(db.abs() >= last - 1e-3, "{gain} dB shelf at {freq} Hz: {db} dB after {last} dB");
+                last = db.abs();
+            }
+            assert!(last > gain.abs() * 0.25, "{gain} dB shelf reaches {last} dB");
+
+            // A full scale impulse dies away instead of ringing on
+            let mut running = shelf;
+            let mut tail: f32 = 0.0;
+            for i in 0..4800 {
+                let (left, _) = running.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0);
+                if i >= 2400 {
+                    tail = tail.max(left.abs());
+                }
+            }
+            assert!(tail < 1e-6, "{gain} dB shelf still rings at {tail}");
+        }
+
+        // Past the design limit a shelf is held there rather than squashed into Nyquist
+        let limit = SAMPLE_RATE * MAX_FREQ_RATIO;
+        let held = Biquad::new(SAMPLE_RATE, 23000.0, 6.0, 0.707, FilterType::HighShelf);
+        let at_limit = Biquad::new(SAMPLE_RATE, limit, 6.0, 0.707, FilterType::HighShelf);
+        assert_eq!(held.frequency_response(18000.0), at_limit.frequency_response(18000.0));
+    }
+
+    #[test]
+    fn start_offsets_change_the_transient_but_settle_the_same() {
+        // Interleaved by 4 the 1 kHz band shows up at 250 Hz and again either side of 12 kHz, a
+        // tone on that image changes a lot from one biquad's turn to the next
+        let tone = |i: usize| 0.5 * (2.0 * std::f32::consts::PI * 11750.0 * i as f32 / SAMPLE_RATE).sin();
+        let mut filters = [1, 3].map(|_| InterleavedBiquad::new(SAMPLE_RATE, 1000.0, 12.0, 2.0, FilterType::Peak, 4));
+        // Same history in both, then each picks back up from a different biquad
+        for i in 0..4800 {
+            for filter in filters.iter_mut() {
+                filter.process_sample(tone(i), tone(i));
+                filter.increment_index();
+            }
+        }
+        filters[0].set_start_offset(1);
+        filters[1].set_start_offset(3);
+
+        let mut transient: f32 = 0.0;
+        let mut settled: f32 = 0.0;
+        for i in 4800..14400 {
+            let [(first, _), (second, _)] = filters.each_mut().map(|filter| {
+                let output = filter.process_sample(tone(i), tone(i));
+                filter.increment_index();
+                output
+            });
+            assert!(first.abs() < 4.0 && second.abs() < 4.0, "sample {i}: {first}, {second}");
+            if i < 4800 + 480 {
+                transient = transient.max((first - second).abs());
+            } else if i >= 14400 - 480 {
+                settled = settled.max((first - second).abs());
+            }
+        }
+        assert!(transient > 0.1, "offsets started the same: {transient}");
+        assert!(settled < 1e-4, "offsets never settled: {settled}");
+    }
+
+    #[test]
+    fn soft_q_widens_resonances_near_nyquist_only() {
+        let bandwidth = |center: f32, soft_q: bool| {
+            let mut biquad = Biquad::new(SAMPLE_RATE, center, 0.0, 8.0, FilterType::BandPass);
+            biquad.set_soft_q(soft_q);
+            let (lower, upper) = minus_3db_points(|freqs| {
+                freqs.iter().map(|freq| biquad.frequency_response(*freq)).collect()
+            });
+            upper - lower
+        };
+
+        // Well under Nyquist nothing changes
+        assert_eq!(bandwidth(2000.0, true), bandwidth(2000.0, false));
+
+        // Up high the resonance is wider and less shrill, by as much as the eased Q says
+        let raw = bandwidth(17000.0, false);
+        let soft = bandwidth(17000.0, true);
+        let eased = effective_q(17000.0, SAMPLE_RATE, 8.0, true);
+        assert!(eased < 8.0 * 0.9, "Q eased to {eased}");
+        assert!(soft > raw * 1.1, "{soft} Hz against {raw} Hz");
+        assert!((soft / raw - 8.0 / eased).abs() < 0.05, "{soft} Hz against {raw} Hz at Q {eased}");
+    }
+}
@@ -0,0 +1,139 @@
+// crossover.rs - Ardura 2023
+// Linkwitz-Riley crossovers built from the same RBJ biquads as the EQ
+// An LR4 section is just two Butterworth (Q = 0.707) biquads in a row, and the low and high
+// outputs of one sum back to an allpass so recombining the regions keeps a flat magnitude
+
+use crate::biquad_filters::{Biquad, FilterType};
+
+/// Most regions the multiband split can make
+pub(crate) const MAX_REGIONS: usize = 3;
+
+const BUTTERWORTH_Q: f32 = 0.707;
+
+// One LR4 split point
+#[derive(Clone, Copy)]
+pub(crate) struct Crossover {
+    low_pass: [Biquad; 2],
+    high_pass: [Biquad; 2],
+}
+
+impl Crossover {
+    pub fn new(sample_rate: f32, freq: f32) -> Self {
+        Crossover {
+            low_pass: [Biquad::new(sample_rate, freq, 0.0, BUTTERWORTH_Q, FilterType::LowPass); 2],
+            high_pass: [Biquad::new(sample_rate, freq, 0.0, BUTTERWORTH_Q, FilterType::HighPass); 2],
+        }
+    }
+
+    pub fn update(&mut self, sample_rate: f32, freq: f32) {
+        for biquad in self.low_pass.iter_mut().chain(self.high_pass.iter_mut()) {
+            biquad.update(sample_rate, freq, 0.0, BUTTERWORTH_Q);
+        }
+    }
+
+    /// Returns the (low, high) stereo pairs
+    pub fn split(&mut self, input_l: f32, input_r: f32) -> ((f32, f32), (f32, f32)) {
+        let mut low = (input_l, input_r);
+        for biquad in self.low_pass.iter_mut() {
+            low = biquad.process_sample(low.0, low.1);
+        }
+        let mut high = (input_l, input_r);
+        for biquad in self.high_pass.iter_mut() {
+            high = biquad.process_sample(high.0, high.1);
+        }
+        (low, high)
+    }
+}
+
+// Splits into two or three regions, the three region version runs the low region through the
+// upper crossover's allpass as well so all regions share the same phase before recombining
+#[derive(Clone, Copy)]
+pub(crate) struct MultibandSplit {
+    low_mid: Crossover,
+    mid_high: Crossover,
+    low_allpass: Crossover,
+}
+
+impl MultibandSplit {
+    pub fn new(sample_rate: f32, low_freq: f32, high_freq: f32) -> Self {
+        MultibandSplit {
+            low_mid: Crossover::new(sample_rate, low_freq),
+            mid_high: Crossover::new(sample_rate, high_freq),
+            low_allpass: Crossover::new(sample_rate, high_freq),
+        }
+    }
+
+    pub fn update(&mut self, sample_rate: f32, low_freq: f32, high_freq: f32) {
+        // Swap rather than let the crossovers cross over each other
+        let (low_freq, high_freq) = if low_freq <= high_freq {
+            (low_freq, high_freq)
+        } else {
+            (high_freq, low_freq)
+        };
+        self.low_mid.update(sample_rate, low_freq);
+        self.mid_high.update(sample_rate, high_freq);
+        self.low_allpass.update(sample_rate, high_freq);
+    }
+
+    /// Splits a sample into `regions` (2 or 3) stereo pairs from low to high, unused slots are silent
+    pub fn split(&mut self, input_l: f32, input_r: f32, regions: usize) -> [(f32, f32); MAX_REGIONS] {
+        let (low, high) = self.low_mid.split(input_l, input_r);
+        if regions < 3 {
+            return [low, high, (0.0, 0.0)];
+        }
+        let (mid, high) = self.mid_high.split(high.0, high.1);
+        let (low_lows, low_highs) = self.low_allpass.split(low.0, low.1);
+        [
+            (low_lows.0 + low_highs.0, low_lows.1 + low_highs.1),
+            mid,
+            high,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    // Level in dB of the recombined regions relative to a full scale sine once the filters have
+    // settled, RMS against RMS over the same stretch so it doesn't depend on where the samples land
+    fn summed_level(split: &mut MultibandSplit, regions: usize, freq: f32) -> f32 {
+        let mut input_power = 0.0;
+        let mut output_power = 0.0;
+        for index in 0..SAMPLE_RATE as usize {
+            let input = (2.0 * std::f32::consts::PI * freq * index as f32 / SAMPLE_RATE).sin();
+            let outputs = split.split(input, -input, regions);
+            let (left, right) = outputs.iter().fold((0.0, 0.0), |sum, region| (sum.0 + region.0, sum.1 + region.1));
+            assert!((left + right).abs() < 1e-5);
+            if index > SAMPLE_RATE as usize / 2 {
+                input_power += input * input;
+                output_power += left * left;
+            }
+        }
+        10.0 * (output_power / input_power).log10()
+    }
+
+    #[test]
+    fn regions_sum_back_to_a_flat_response() {
+        for regions in [2, 3] {
+            for freq in [30.0, 120.0, 250.0, 1000.0, 2500.0, 6000.0, 15000.0] {
+                let mut split = MultibandSplit::new(SAMPLE_RATE, 250.0, 2500.0);
+                let level = summed_level(&mut split, regions, freq);
+                // Within 0.05 dB, the crossovers only move the phase
+                assert!(level.abs() < 0.05, "{regions} regions at {freq} Hz: {level} dB");
+            }
+        }
+    }
+
+    #[test]
+    fn crossed_frequencies_still_sum_flat() {
+        let mut split = MultibandSplit::new(SAMPLE_RATE, 250.0, 2500.0);
+        split.update(SAMPLE_RATE, 4000.0, 500.0);
+        for freq in [100.0, 500.0, 1500.0, 4000.0, 10000.0] {
+            let level = summed_level(&mut split, 3, freq);
+            assert!(level.abs() < 0.05, "{freq} Hz: {level} dB");
+        }
+    }
+}
@@ -16,7 +16,10 @@ enum DBMeterText {
 #[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
 pub struct DBMeter {
     level: f32,
+    peak_hold: Option<f32>,
+    vertical: bool,
     desired_width: Option<f32>,
+    desired_height: Option<f32>,
     text: Option<DBMeterText>,
     animate: bool,
     border_color: Color32,
@@ -30,7 +33,10 @@ impl DBMeter {
     pub fn new(level: f32) -> Self {
         Self {
             level: level.clamp(0.0, 1.0),
+            peak_hold: None,
+            vertical: false,
             desired_width: None,
+            desired_height: None,
             text: None,
             animate: false,
             border_color: Color32::BLACK,
@@ -45,6 +51,25 @@ impl DBMeter {
         self
     }
 
+    /// The desired height of the bar. Horizontal meters default to the interact height and
+    /// vertical meters will use all vertical space if not set.
+    pub fn desired_height(mut self, desired_height: f32) -> Self {
+        self.desired_height = Some(desired_height);
+        self
+    }
+
+    /// Draw the meter filling bottom to top instead of left to right
+    pub fn vertical(mut self, vertical: bool) -> Self {
+        self.vertical = vertical;
+        self
+    }
+
+    /// Held peak in the same `[0, 1]` range as the level, drawn as a line on the meter
+    pub fn peak_hold(mut self, peak_hold: f32) -> Self {
+        self.peak_hold = Some(peak_hold.clamp(0.0, 1.0));
+        self
+    }
+
     /// A custom text to display on the progress bar.
     pub fn text(mut self, text: impl Into<WidgetText>) -> Self {
         self.text = Some(DBMeterText::Custom(text.into()));
@@ -72,7 +97,10 @@ impl Widget for DBMeter {
     fn ui(self, ui: &mut Ui) -> Response {
         let DBMeter {
             level,
+            peak_hold,
+            vertical,
             desired_width,
+            desired_height,
             text,
             animate,
             border_color,
@@ -82,11 +110,18 @@ impl Widget for DBMeter {
 
         let animate = animate && level < 1.0;
 
-        let desired_width =
-            desired_width.unwrap_or_else(|| ui.available_size_before_wrap().x.at_least(96.0));
-        let height = ui.spacing().interact_size.y;
-        let (outer_rect, response) =
-            ui.allocate_exact_size(vec2(desired_width, height), Sense::hover());
+        let size = if vertical {
+            vec2(
+                desired_width.unwrap_or_else(|| ui.spacing().interact_size.y),
+                desired_height.unwrap_or_else(|| ui.available_size_before_wrap().y.at_least(96.0)),
+            )
+        } else {
+            vec2(
+                desired_width.unwrap_or_else(|| ui.available_size_before_wrap().x.at_least(96.0)),
+                desired_height.unwrap_or_else(|| ui.spacing().interact_size.y),
+            )
+        };
+        let (outer_rect, response) = ui.allocate_exact_size(size, Sense::hover());
 
         if ui.is_rect_visible(response.rect) {
             if animate {
@@ -100,22 +135,34 @@ impl Widget for DBMeter {
             ui.painter().rect(
                 outer_rect,
                 rounding,
-                self.background_color,
-                Stroke::new(1.0, self.border_color),
-            );
-            let inner_rect = Rect::from_min_size(
-                outer_rect.min,
-                vec2(
-                    (outer_rect.width() * level).at_least(outer_rect.height()),
-                    outer_rect.height(),
-                ),
+                background_color,
+                Stroke::new(1.0, border_color),
             );
+            let inner_rect = if vertical {
+                // Fill up from the bottom
+                Rect::from_min_max(
+                    Pos2::new(
+                        outer_rect.left(),
+                        outer_rect.bottom()
+                            - (outer_rect.height() * level).at_least(outer_rect.width()),
+                    ),
+                    outer_rect.max,
+                )
+            } else {
+                Rect::from_min_size(
+                    outer_rect.min,
+                    vec2(
+                        (outer_rect.width() * level).at_least(outer_rect.height()),
+                        outer_rect.height(),
+                    ),
+                )
+            };
 
             ui.painter().rect(
                 inner_rect,
                 rounding,
-                if self.level < 1.0 {
-                    self.bar_color
+                if level < 1.0 {
+                    bar_color
                 } else {
                     Color32::RED
                 },
@@ -138,21 +185,58 @@ impl Widget for DBMeter {
                     })
                     .collect();
                 ui.painter()
-                    .add(Shape::line(points, Stroke::new(2.0, self.border_color)));
+                    .add(Shape::line(points, Stroke::new(2.0, border_color)));
             }
 
             // Markers
-            let marker_spacing = outer_rect.width() / 12.0;
-            let points_x = (outer_rect.left_bottom().x as i32..=outer_rect.right_bottom().x as i32)
-                .step_by(marker_spacing as usize);
-
-            for x in points_x {
-                let points: Vec<Pos2> = vec![
-                    Pos2::new(x as f32, outer_rect.left_bottom().y),
-                    Pos2::new(x as f32, outer_rect.left_bottom().y - 10.0),
-                ];
-                ui.painter()
-                    .add(Shape::line(points, Stroke::new(1.0, self.border_color)));
+            if vertical {
+                let marker_spacing = outer_rect.height() / 12.0;
+                let points_y = (outer_rect.top() as i32..=outer_rect.bottom() as i32)
+                    .step_by(marker_spacing as usize);
+
+                for y in points_y {
+                    let points: Vec<Pos2> = vec![
+                        Pos2::new(outer_rect.left(), y as f32),
+                        Pos2::new(outer_rect.left() + 10.0, y as f32),
+                    ];
+                    ui.painter()
+                        .add(Shape::line(points, Stroke::new(1.0, border_color)));
+                }
+            } else {
+                let marker_spacing = outer_rect.width() / 12.0;
+                let points_x = (outer_rect.left_bottom().x as i32
+                    ..=outer_rect.right_bottom().x as i32)
+                    .step_by(marker_spacing as usize);
+
+                for x in points_x {
+                    let points: Vec<Pos2> = vec![
+                        Pos2::new(x as f32, outer_rect.left_bottom().y),
+                        Pos2::new(x as f32, outer_rect.left_bottom().y - 10.0),
+                    ];
+                    ui.painter()
+                        .add(Shape::line(points, Stroke::new(1.0, border_color)));
+                }
+            }
+
+            // Peak hold line
+            if let Some(peak) = peak_hold {
+                let points: Vec<Pos2> = if vertical {
+                    let y = outer_rect.bottom() - outer_rect.height() * peak;
+                    vec![
+                        Pos2::new(outer_rect.left(), y),
+                        Pos2::new(outer_rect.right(), y),
+                    ]
+                } else {
+                    let x = outer_rect.left() + outer_rect.width() * peak;
+                    vec![
+                        Pos2::new(x, outer_rect.top()),
+                        Pos2::new(x, outer_rect.bottom()),
+                    ]
+                };
+                ui.painter().add(Shape::line(
+                    points,
+                    Stroke::new(2.0, if peak < 1.0 { border_color } else { Color32::RED }),
+                ));
             }
 
             if let Some(text_kind) = text {
@@ -160,10 +244,14 @@ impl Widget for DBMeter {
                     DBMeterText::Custom(text) => text,
                     DBMeterText::Percentage => format!("{}%", (level * 100.0) as usize).into(),
                 };
+                if vertical {
+                    // There's no room to print inside a vertical bar so show it on hover instead
+                    return response.on_hover_text(text.text());
+                }
                 let galley = text.into_galley(ui, Some(false), f32::INFINITY, TextStyle::Button);
                 let text_pos = outer_rect.left_center() - Vec2::new(0.0, galley.size().y / 2.0)
                     + vec2(ui.spacing().item_spacing.x, 0.0);
-                let text_color = visuals.override_text_color.unwrap_or(self.border_color);
+                let text_color = visuals.override_text_color.unwrap_or(border_color);
                 galley.paint_with_fallback_color(
                     &ui.painter().with_clip_rect(outer_rect),
                     text_pos,
@@ -1,9 +1,28 @@
 #![allow(non_snake_case)]
 
 mod CustomVerticalSlider;
+mod analyzer;
+mod balance_meter;
 mod biquad_filters;
+mod crossover;
 mod db_meter;
+mod limiter;
+mod loudness_trim;
+mod match_eq;
+mod morph;
+mod offline;
+mod phase_compensation;
+mod randomize;
+mod response_graph;
+mod settings_text;
+mod spectrogram;
+mod svg;
+mod test_signal;
+mod true_peak;
 mod ui_knob;
+mod vu_meter;
+mod wav;
+mod zoom_view;
 use atomic_float::AtomicF32;
 use nih_plug::prelude::*;
 use nih_plug_egui::{
@@ -13,10 +32,14 @@ use nih_plug_egui::{
 };
 use std::{
     ops::RangeInclusive,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicU32},
+        Arc, Mutex,
+    },
 };
 use CustomVerticalSlider::ParamSlider as VerticalParamSlider;
 use biquad_filters::FilterType;
+use nih_plug::params::persist::PersistentField;
 
 /**************************************************
  * Interleaf by Ardura
@@ -37,18 +60,454 @@ const WIDTH: u32 = 370;
 const HEIGHT: u32 = 660;
 
 // Constants
+/// Frequency knobs step by this many cents with the arrow keys so a step is the same musical
+/// distance at 30 Hz as it is at 15 kHz
+const FREQ_STEP_CENTS: f32 = 5.0;
+/// The biquads hold state for a left and right channel, so this is the most we process.
+/// Any channels after these are passed through untouched.
+const MAX_PROCESSED_CHANNELS: usize = 2;
 const VERT_BAR_HEIGHT: f32 = 260.0;
+const GRAPH_HEIGHT: f32 = 140.0;
+const ZOOM_HEIGHT: f32 = 70.0;
+const SPECTROGRAM_HEIGHT: f32 = 90.0;
 const VERT_BAR_WIDTH: f32 = 32.0;
 
 /// The time it takes for the peak meter to decay by 12 dB after switching to complete silence.
 const PEAK_METER_DECAY_MS: f64 = 360.0;
+/// The same as above but for the slower peak hold line on the meters
+const PEAK_HOLD_DECAY_MS: f64 = 3000.0;
+/// Limits for the editor's meter window, the in/out meters read the RMS level over this long
+const METER_WINDOW_MIN_MS: f32 = 50.0;
+const METER_WINDOW_MAX_MS: f32 = 3000.0;
+/// VU needles reach 99% of a steady level in this long
+const VU_INTEGRATION_MS: f64 = 300.0;
+/// 0 VU sits at this level
+const VU_REFERENCE_DBFS: f32 = -18.0;
+/// VU meters read the average of the rectified signal scaled so a sine reads its RMS level
+const VU_FORM_FACTOR: f32 = 1.111;
 
 const MAIN_FONT: nih_plug_egui::egui::FontId = FontId::monospace(8.0);
 
+// Spectral balance meter detection bands - everything below/above these is compared
+const BALANCE_LOW_FREQ: f32 = 250.0;
+const BALANCE_HIGH_FREQ: f32 = 4000.0;
+/// Pink noise reads about this much darker through the detection filters since the low band
+/// covers more octaves, so this is added back to make pink noise the centered "balanced" point.
+const BALANCE_PINK_OFFSET_DB: f32 = 1.9;
+/// The balance indicator is fully leaned over at this many dB of tilt
+const BALANCE_RANGE_DB: f32 = 12.0;
+/// Below this output level the balance is treated as silence and held at the center
+const BALANCE_SILENCE_DB: f32 = -60.0;
+
+/// Res the bands start at and Reset Band falls back to
+const DEFAULT_RES: f32 = 0.707;
+
+// How the res knobs show their value
+const RES_DISPLAY_Q: u32 = 0;
+const RES_DISPLAY_OCTAVES: u32 = 1;
+const RES_DISPLAY_HZ: u32 = 2;
+
+/// x2 Auto only engages the cascade for bands at or above the threshold boosting by this much,
+/// or pass types resonant past this Q since those peak at the cutoff
+const OS_AUTO_MIN_BOOST_DB: f32 = 3.0;
+const OS_AUTO_MIN_Q: f32 = 0.707;
+/// Switching the x2 cascade in or out dips the bands to flat and back over this long
+const OS_SWITCH_FADE_MS: f32 = 5.0;
+
+/// At full drive a shelf's contribution is pushed into the tanh this many times harder
+const SHELF_DRIVE_MAX: f32 = 4.0;
+
+/// Holding a band bypassed (and letting go) crossfades over this long so it doesn't click
+const BAND_BYPASS_FADE_MS: f32 = 10.0;
+
+/// Points from 20 Hz to 20 kHz averaged for the broadband level a type change is compensated by
+const TYPE_LEVEL_POINTS: usize = 64;
+
+/// Boost sweep hunting listens through a narrow boost, then cuts the spot that stood out most
+/// from its neighbours by up to this much at the cut Q. Less prominent than the minimum and the
+/// band is left alone.
+const HUNT_BOOST_DB: f32 = 12.0;
+const HUNT_BOOST_Q: f32 = 8.0;
+const HUNT_CUT_Q: f32 = 1.0;
+const HUNT_MIN_PROMINENCE_DB: f32 = 6.0;
+const HUNT_MAX_CUT_DB: f32 = 12.0;
+
+/// The resonance suppressor only cuts a resonance standing at least this far above its
+/// neighbours, by the excess times the strength up to the maximum, at the narrowest Res
+const SUPPRESSOR_MIN_PROMINENCE_DB: f32 = 3.0;
+const SUPPRESSOR_MAX_CUT_DB: f32 = 12.0;
+const SUPPRESSOR_Q: f32 = 1.0;
+/// How fast the suppressor's cut digs in and lets go, and how fast the notch glides to a new spot
+const SUPPRESSOR_ATTACK_MS: f32 = 20.0;
+const SUPPRESSOR_RELEASE_MS: f32 = 250.0;
+const SUPPRESSOR_GLIDE_MS: f32 = 50.0;
+
+/// Bulk changes crossfade over this long
+const BULK_FADE_MS: f32 = 20.0;
+
+/// The effective Q readouts are refreshed this often while the editor is open
+const EFFECTIVE_Q_INTERVAL_MS: f32 = 100.0;
+
+/// Stored in the listen band when nothing is being auditioned
+const NO_LISTEN: i32 = -1;
+/// Listen Level brings every band's audition to where one this many octaves wide would sit on
+/// pink-ish material, within the maximum either way
+const LISTEN_REFERENCE_OCTAVES: f32 = 2.0;
+const LISTEN_LEVEL_MAX_DB: f32 = 18.0;
+
+/// The peak frequency readout goes blank when the loudest analyzer bin is under this level
+const PEAK_FREQ_GATE_DB: f32 = -50.0;
+
+/// Longest lookahead the output limiter can be set to
+const LIMITER_MAX_LOOKAHEAD_MS: f32 = 10.0;
+/// The gain reduction meter shows this much reduction at full scale
+const LIMITER_METER_RANGE_DB: f32 = 12.0;
+
+// Auto Quality - when process takes more than the overload fraction of the buffer's real-time
+// duration for this many buffers in a row we step down: first oversampling is turned off, then
+// the interleaves are capped
+const AUTO_QUALITY_OVERLOAD: f32 = 0.7;
+const AUTO_QUALITY_RECOVER: f32 = 0.3;
+const AUTO_QUALITY_BUFFERS: u32 = 8;
+const AUTO_QUALITY_MAX_LEVEL: u32 = 2;
+const AUTO_QUALITY_INTERLEAVE: f32 = 2.0;
+
+// Display preferences that go back to their defaults when a state from before they existed is
+// loaded, otherwise a reused instance keeps showing whatever the previous state had
+const VIEW_PREF_KEYS: [&str; 29] = [
+    "band-order",
+    "meters-vertical",
+    "note-names",
+    "note-numbers",
+    "needle-meters",
+    "true-peak",
+    "compact",
+    "meter-target-gain",
+    "meter-pre-output",
+    "meter-window",
+    "gain-snap",
+    "reset-res",
+    "link-q",
+    "res-display",
+    "analyzer-mode",
+    "analyzer-spectrogram",
+    "analyzer-hold",
+    "analyzer-zoom",
+    "zoom-low",
+    "zoom-high",
+    "graph-min-freq",
+    "graph-max-freq",
+    "graph-auto-db",
+    "graph-db-range",
+    "graph-phase",
+    "graph-note-grid",
+    "analyzer-threshold",
+    "analyzer-threshold-level",
+    "band-target-level",
+];
+
+// GUI state that only lives while the editor is open
+struct EditorState {
+    // Seed shown next to the randomize button so results can be reproduced
+    random_seed: u32,
+    random_slight: bool,
+    random_musical: bool,
+    // Result of the last null test render
+    null_result: String,
+    // What the last loudness trim did
+    trim_result: String,
+    // Result of the last SVG export
+    svg_result: String,
+    // Settings line to copy from or paste into and what happened last
+    settings_text: String,
+    settings_result: String,
+    // Keep the controls showing in compact mode
+    pin_controls: bool,
+    // Last window height asked of the host so a host that says no isn't asked every frame
+    requested_height: u32,
+    // Display position of the band column whose header is being dragged
+    dragged_column: Option<usize>,
+    spectrogram_texture: Option<egui::TextureHandle>,
+    // Boost sweep hunt: where the slider is, the most prominent spot found this sweep and the
+    // band that gets the cut
+    hunt_freq: f32,
+    hunt_best: Option<(f32, f32)>,
+    hunt_band: usize,
+    hunt_result: String,
+    // Knob Listen started the current listen and should stop it on release
+    knob_listening: bool,
+    // Band last hovered or touched on the graph or band strip, the type cycle key changes this one
+    focused_band: Option<usize>,
+    // Degenerate biquad count already logged
+    degenerate_logged: u32,
+    // Band whose Res is leading Link Q while the other bands' gestures are open
+    linked_res: Option<usize>,
+}
+
+// How a fractional interleave fades between the counts either side of it
+#[derive(Clone, Copy, Enum, PartialEq)]
+enum BlendCurve {
+    Linear,
+    #[name = "Equal Power"]
+    EqualPower,
+}
+
+impl BlendCurve {
+    // Gains for the lower and upper interleave count at a fraction of the way between them
+    fn gains(&self, fraction: f32) -> (f32, f32) {
+        match self {
+            BlendCurve::Linear => (1.0 - fraction, fraction),
+            BlendCurve::EqualPower => {
+                let angle = fraction * std::f32::consts::FRAC_PI_2;
+                (angle.cos(), angle.sin())
+            }
+        }
+    }
+}
+
+// Type, frequency, gain and Q for each band, worked out once per buffer
+type BandSettings = [(FilterType, f32, f32, f32); 5];
+
 #[derive(Clone, Copy)]
 struct EQ {
     non_interleave_bands: [biquad_filters::Biquad; 5],
     interleave_bands: [biquad_filters::InterleavedBiquad; 5],
+    // One interleave count up, only runs while the interleave sits between whole counts
+    interleave_bands_next: [biquad_filters::InterleavedBiquad; 5],
+    // How much of each band is heard, 0 while it's held bypassed
+    band_mix: [f32; 5],
+    // Shelf drive per band, 0 for clean and for every type that isn't a shelf
+    band_drive: [f32; 5],
+    // Each band's own wet amount, blended with what came into the band
+    band_wet: [f32; 5],
+    // Phase compensation after each band, for each interleave count like the bands
+    phase_comp: [phase_compensation::PhaseCompensator; 5],
+    phase_comp_next: [phase_compensation::PhaseCompensator; 5],
+}
+
+impl EQ {
+    fn new() -> Self {
+        EQ {
+            non_interleave_bands: [
+                    // These defaults don't matter as they are overwritten immediately
+                    biquad_filters::Biquad::new( 44100.0,800.0,0.0, 0.707, FilterType::Peak)
+                    // 5 Bands of the above
+                    ; 5
+                ],
+            interleave_bands: [
+                    // These defaults don't matter as they are overwritten immediately
+                    biquad_filters::InterleavedBiquad::new( 44100.0,800.0,0.0, 0.707, FilterType::Peak, 2)
+                    // 5 Bands of the above
+                    ; 5
+                ],
+            interleave_bands_next: [
+                    biquad_filters::InterleavedBiquad::new( 44100.0,800.0,0.0, 0.707, FilterType::Peak, 3)
+                    ; 5
+                ],
+            band_mix: [1.0; 5],
+            band_drive: [0.0; 5],
+            band_wet: [1.0; 5],
+            phase_comp: [phase_compensation::PhaseCompensator::new(); 5],
+            phase_comp_next: [phase_compensation::PhaseCompensator::new(); 5],
+        }
+    }
+
+    // Put every interleaved band back on its start offset
+    fn reset_interleave(&mut self) {
+        for filter in self.interleave_bands.iter_mut().chain(self.interleave_bands_next.iter_mut()) {
+            filter.reset_index();
+        }
+    }
+
+    // Fade each band towards bypassed (bit set in the mask) or back in by one sample's step
+    fn update_band_mix(&mut self, bypass_mask: u32, fade_step: f32) {
+        for (index, mix) in self.band_mix.iter_mut().enumerate() {
+            let target = if bypass_mask & (1 << index) != 0 { 0.0 } else { 1.0 };
+            *mix = if *mix < target {
+                (*mix + fade_step).min(target)
+            } else {
+                (*mix - fade_step).max(target)
+            };
+        }
+    }
+
+    // Make sure we are always on the correct sample rate, then update our bands
+    fn set_bands(
+        &mut self,
+        params: &InterleafParams,
+        band_settings: &BandSettings,
+        sr: f32,
+        interleave: f32,
+        oversampling: usize,
+    ) {
+        // Set our interleaves, the next bands sit one count up for fractional interleaves
+        let blending = interleave.fract() > 0.0;
+        for ((filter, next_filter), offset) in self
+            .interleave_bands
+            .iter_mut()
+            .zip(self.interleave_bands_next.iter_mut())
+            .zip(params.offsets())
+        {
+            filter.set_interleave(interleave as usize);
+            filter.set_start_offset(offset.value() as usize);
+            next_filter.set_interleave(interleave as usize + 1);
+            next_filter.set_start_offset(offset.value() as usize);
+        }
+
+        for (index, &(filter_type, freq, gain, q)) in band_settings.iter().enumerate() {
+            // Update our types
+            self.interleave_bands[index].set_type(filter_type);
+            self.interleave_bands_next[index].set_type(filter_type);
+            self.non_interleave_bands[index].set_type(filter_type);
+            self.interleave_bands[index].set_soft_q(params.soft_q.value());
+            self.interleave_bands_next[index].set_soft_q(params.soft_q.value());
+            self.non_interleave_bands[index].set_soft_q(params.soft_q.value());
+            let gain = params.pass_gain(gain, filter_type, oversampling);
+            self.band_drive[index] = match filter_type {
+                FilterType::LowShelf | FilterType::HighShelf => params.drives()[index].value(),
+                _ => 0.0,
+            };
+            self.band_wet[index] = params.wets()[index].value();
+            let compensate = params.phase_comps()[index].value();
+            let passes = (oversampling + 1) as f32;
+            self.phase_comp[index].update(
+                compensate,
+                (filter_type, freq, gain, q),
+                params.soft_q.value(),
+                sr,
+                if interleave >= 2.0 { interleave.floor() } else { 1.0 },
+                passes,
+            );
+            if blending {
+                self.phase_comp_next[index].update(
+                    compensate,
+                    (filter_type, freq, gain, q),
+                    params.soft_q.value(),
+                    sr,
+                    interleave.floor() + 1.0,
+                    passes,
+                );
+            }
+
+            if interleave >= 2.0 {
+                // Use the interleaved biquads
+                self.interleave_bands[index].update(
+                    sr,
+                    freq,
+                    gain,
+                    q,
+                );
+            } else {
+                // No interleaved biquads
+                self.non_interleave_bands[index].update(
+                    sr,
+                    freq,
+                    gain,
+                    q,
+                );
+            }
+            if blending {
+                self.interleave_bands_next[index].update(
+                    sr,
+                    freq,
+                    gain,
+                    q,
+                );
+            }
+        }
+    }
+
+    // Run a sample through the bands. A fractional interleave runs the counts either side and
+    // fades between them on the blend curve.
+    fn process(
+        &mut self,
+        in_l: f32,
+        in_r: f32,
+        interleave: f32,
+        oversampling: usize,
+        blend_curve: BlendCurve,
+    ) -> (f32, f32) {
+        let whole = interleave.floor();
+        let fraction = interleave - whole;
+        let (low_l, low_r) = self.process_chain(in_l, in_r, whole, oversampling, false);
+        if fraction <= 0.0 {
+            return (low_l, low_r);
+        }
+        let (high_l, high_r) = self.process_chain(in_l, in_r, whole + 1.0, oversampling, true);
+        let (low_gain, high_gain) = blend_curve.gains(fraction);
+        (low_l * low_gain + high_l * high_gain, low_r * low_gain + high_r * high_gain)
+    }
+
+    // Run a sample through one whole interleave count of the band chain, each band is cascaded
+    // once more per oversampling step
+    fn process_chain(&mut self, in_l: f32, in_r: f32, interleave: f32, oversampling: usize, next: bool) -> (f32, f32) {
+        let mut processed_sample_l: f32 = 0.0;
+        let mut processed_sample_r: f32 = 0.0;
+        if interleave >= 2.0 {
+            // Perform processing on the sample using the filters, each band takes the last one's
+            // output and the first takes the input
+            let mut temp_l: f32 = in_l;
+            let mut temp_r: f32 = in_r;
+            let bands = if next { &mut self.interleave_bands_next } else { &mut self.interleave_bands };
+            let compensators = if next { &mut self.phase_comp_next } else { &mut self.phase_comp };
+            for (index, filter) in bands.iter_mut().enumerate() {
+                // What this band gets, kept for the bypass crossfade
+                let (band_in_l, band_in_r) = (temp_l, temp_r);
+                // Once plus once more per oversampling step
+                for _ in 0..=oversampling {
+                    (temp_l, temp_r) = filter.process_sample(temp_l, temp_r);
+                    filter.increment_index();
+                }
+                let drive = self.band_drive[index];
+                if drive > 0.0 {
+                    temp_l = band_in_l + shelf_drive(temp_l - band_in_l, drive);
+                    temp_r = band_in_r + shelf_drive(temp_r - band_in_r, drive);
+                }
+                // The bypass fade and the band's own wet both blend with what came in
+                let mix = self.band_mix[index] * self.band_wet[index];
+                if mix < 1.0 {
+                    temp_l = band_in_l + (temp_l - band_in_l) * mix;
+                    temp_r = band_in_r + (temp_r - band_in_r) * mix;
+                }
+                // After the blend so the dry part is shifted with it and the magnitude stays put
+                (temp_l, temp_r) = compensators[index].process_sample(temp_l, temp_r);
+
+                // Sum up our output
+                processed_sample_l = temp_l;
+                processed_sample_r = temp_r;
+            }
+        } else {
+            // Perform processing on the sample using the filters, each band takes the last one's
+            // output and the first takes the input
+            let mut temp_l: f32 = in_l;
+            let mut temp_r: f32 = in_r;
+            for (index, filter) in self.non_interleave_bands.iter_mut().enumerate() {
+                // What this band gets, kept for the bypass crossfade
+                let (band_in_l, band_in_r) = (temp_l, temp_r);
+                // Once plus once more per oversampling step
+                for _ in 0..=oversampling {
+                    (temp_l, temp_r) = filter.process_sample(temp_l, temp_r);
+                }
+                let drive = self.band_drive[index];
+                if drive > 0.0 {
+                    temp_l = band_in_l + shelf_drive(temp_l - band_in_l, drive);
+                    temp_r = band_in_r + shelf_drive(temp_r - band_in_r, drive);
+                }
+                // The bypass fade and the band's own wet both blend with what came in
+                let mix = self.band_mix[index] * self.band_wet[index];
+                if mix < 1.0 {
+                    temp_l = band_in_l + (temp_l - band_in_l) * mix;
+                    temp_r = band_in_r + (temp_r - band_in_r) * mix;
+                }
+                // After the blend so the dry part is shifted with it and the magnitude stays put
+                (temp_l, temp_r) = self.phase_comp[index].process_sample(temp_l, temp_r);
+                // Sum up our output
+                processed_sample_l = temp_l;
+                processed_sample_r = temp_r;
+            }
+        }
+        (processed_sample_l, processed_sample_r)
+    }
 }
 
 pub struct Interleaf {
@@ -56,13 +515,140 @@ pub struct Interleaf {
 
     // normalize the peak meter's response based on the sample rate with this
     out_meter_decay_weight: f32,
+    peak_hold_decay_weight: f32,
+    vu_weight: f32,
+    // Per sample weight of the in/out meters' running mean square, the window it was worked out
+    // for, and the mean squares themselves which carry on across buffers
+    meter_window_weight: f32,
+    meter_window_applied: f32,
+    in_meter_square: f32,
+    out_meter_square: f32,
+
+    // Equalizer made of peaks, one per multiband region. Only process and reset touch this so it's
+    // owned outright, the GUI builds its own filters from the params
+    equalizer: [EQ; crossover::MAX_REGIONS],
 
-    // Equalizer made of peaks
-    equalizer: Arc<Mutex<EQ>>,
+    // Crossovers for multiband processing
+    multiband: crossover::MultibandSplit,
+
+    // Copy of the EQ and crossovers left on the settings from before a bulk change and how far the
+    // crossfade away from it has got (1 when not fading)
+    bulk_fade_equalizer: [EQ; crossover::MAX_REGIONS],
+    bulk_fade_multiband: crossover::MultibandSplit,
+    bulk_fade_mix: f32,
+    // Set by process until deactivate, being prepared again while it's set means a state load on
+    // running audio. The reset after that load keeps the limiter's lookahead and the rotations.
+    processing: bool,
+    keep_next_reset: bool,
 
     // The current data for the different meters
     out_meter: Arc<AtomicF32>,
     in_meter: Arc<AtomicF32>,
+    out_meter_peak: Arc<AtomicF32>,
+    in_meter_peak: Arc<AtomicF32>,
+    // Integrated levels for the VU needles
+    in_vu: Arc<AtomicF32>,
+    out_vu: Arc<AtomicF32>,
+    // Inter-sample peaks for the output peak hold in true peak mode
+    true_peak: true_peak::TruePeakDetector,
+
+    // Spectral balance meter: two broad detection filters and their smoothed energies
+    balance_low_filter: biquad_filters::Biquad,
+    balance_high_filter: biquad_filters::Biquad,
+    balance_low_energy: f32,
+    balance_high_energy: f32,
+    // High to low band energy ratio in dB, 0 is balanced
+    balance_meter: Arc<AtomicF32>,
+
+    // Output lookahead limiter, its gain reduction in dB and the latency we last reported
+    limiter: limiter::LookaheadLimiter,
+    limiter_meter: Arc<AtomicF32>,
+    limiter_latency: u32,
+
+    // Auto Quality: current reduction step (0 is full quality), consecutive buffer counts and the
+    // step shared with the GUI
+    quality_level: u32,
+    overload_count: u32,
+    headroom_count: u32,
+    quality_meter: Arc<AtomicU32>,
+
+    // x2 passes running now and wanted this buffer, a change dips the bands through flat so the
+    // cascade can switch without a click. The cascade adds no latency either way.
+    oversampling_active: usize,
+    oversampling_target: usize,
+    oversampling_mix: f32,
+
+    // Input spectrum analyzer, its bins for the graph and the loudest frequency (0 when gated).
+    // Process only fills the rings and copies full frames into analyzer_frame, the background task
+    // does the FFTs with its own analyzers and publishes the bins. The second ring and bins are
+    // right or side when the analyzer is split.
+    analyzer: analyzer::Analyzer,
+    analyzer_second: analyzer::Analyzer,
+    analyzer_frame: Arc<Mutex<Vec<f32>>>,
+    analyzer_fft: Arc<Mutex<[analyzer::Analyzer; 2]>>,
+    analyzer_bins: Arc<Mutex<Vec<f32>>>,
+    analyzer_bins_second: Arc<Mutex<Vec<f32>>>,
+    // Smoothed output minus input per bin in dB for the analyzer's difference mode
+    analyzer_difference: Arc<Mutex<Vec<f32>>>,
+    // Loudest unsmoothed level per bin since the hold was last cleared, in dBFS
+    analyzer_held: Arc<Mutex<Vec<f32>>>,
+    spectrogram: Arc<Mutex<spectrogram::Spectrogram>>,
+    peak_frequency: Arc<AtomicF32>,
+
+    // Match EQ capture, its state shared with the GUI and the finished reference - input curve
+    match_capture: match_eq::MatchCapture,
+    match_state: Arc<AtomicU32>,
+    match_difference: Arc<Mutex<Vec<f32>>>,
+    // Process copies each pair of frames here and the background task averages them
+    match_frame: Arc<Mutex<Vec<f32>>>,
+    match_average: Arc<Mutex<match_eq::MatchAverage>>,
+
+    // One shot loudness trim measurement, its state shared with the GUI and the output gain it found
+    trim_measure: loudness_trim::TrimMeasure,
+    trim_state: Arc<AtomicU32>,
+    trim_gain: Arc<AtomicF32>,
+
+    // Bit per band that's being held bypassed in the editor
+    held_bypass: Arc<AtomicU32>,
+
+    // Band being auditioned through a bandpass instead of the EQ, or NO_LISTEN
+    listen_band: Arc<AtomicI32>,
+    listen_filter: biquad_filters::Biquad,
+    // How far into the listen we are, and the band heard while fading out of it
+    listen_mix: f32,
+    listen_fade_band: usize,
+
+    // Solo region auditions only the input between two frequencies, whatever the bands are doing
+    solo_region: Arc<AtomicBool>,
+    solo_low: Arc<AtomicF32>,
+    solo_high: Arc<AtomicF32>,
+    solo_highpass: biquad_filters::Biquad,
+    solo_lowpass: biquad_filters::Biquad,
+
+    // Pink noise in place of the input, an editor only test mode that isn't saved
+    test_noise: Arc<AtomicBool>,
+    pink_noise: test_signal::PinkNoise,
+
+    // Frequency of the editor's boost sweep, 0 when not hunting
+    hunt_freq: Arc<AtomicF32>,
+    hunt_filter: biquad_filters::Biquad,
+
+    // Resonance suppressor detection, runs whether the editor is open or not. Process fills the
+    // ring from what the bands see and the background task finds the strongest resonance in the
+    // region and how far it stands out of its neighbours (0 when nothing does).
+    suppressor_analyzer: analyzer::Analyzer,
+    suppressor_frame: Arc<Mutex<Vec<f32>>>,
+    suppressor_fft: Arc<Mutex<analyzer::Analyzer>>,
+    suppressor_target: Arc<AtomicF32>,
+    suppressor_excess: Arc<AtomicF32>,
+
+    // Q each band really runs at after Q Scale, morph, the suppressor and soft Q, for the editor's
+    // readouts, and the samples left until they're refreshed
+    effective_q: Arc<[AtomicF32; 5]>,
+    effective_q_countdown: usize,
+
+    // Sample rate from initialize() for editor side rendering
+    sample_rate: Arc<AtomicF32>,
 }
 
 #[derive(Params)]
@@ -70,6 +656,152 @@ struct InterleafParams {
     #[persist = "editor-state"]
     editor_state: Arc<EguiState>,
 
+    // Band setups stored for morphing between, process reads these every buffer
+    #[persist = "morph-a"]
+    morph_a: morph::SnapshotSlot,
+    #[persist = "morph-b"]
+    morph_b: morph::SnapshotSlot,
+
+    // Where the resonance suppressor has its band and how deep it cuts, smoothed by process each
+    // buffer. Not saved, the suppressor finds its way back from the audio.
+    suppressor_freq: Arc<AtomicF32>,
+    suppressor_cut: Arc<AtomicF32>,
+
+    // Set by the editor's actions that move several bands at once, process crossfades to them
+    bulk_change: AtomicBool,
+
+    // Which band each column of the band strip shows, left to right
+    #[persist = "band-order"]
+    band_order: Arc<Mutex<[usize; 5]>>,
+
+    // Free text name shown in the title so instances can be told apart, "Vocal EQ" etc.
+    #[persist = "instance-label"]
+    instance_label: Arc<Mutex<String>>,
+
+    // Draw the in/out meters vertically next to the bands instead of stacked at the top
+    #[persist = "meters-vertical"]
+    meters_vertical: Arc<AtomicBool>,
+
+    // Band frequencies show the nearest note after the Hz value
+    #[persist = "note-names"]
+    note_names: Arc<AtomicBool>,
+
+    // Band frequencies show the MIDI note number and cents instead of the note name
+    #[persist = "note-numbers"]
+    note_numbers: Arc<AtomicBool>,
+
+    // Show the in/out levels as VU needles instead of bars
+    #[persist = "needle-meters"]
+    needle_meters: Arc<AtomicBool>,
+
+    // Output peak hold and clip use a 4x oversampled true peak instead of sample peaks
+    #[persist = "true-peak"]
+    true_peak_meter: Arc<AtomicBool>,
+
+    // Compact mode only shows the meters and graph until the controls are hovered or pinned, and
+    // shrinks the window down to them while they're hidden
+    #[persist = "compact"]
+    compact: Arc<AtomicBool>,
+
+    // Economy mode for sessions with lots of instances: interleave is capped like Auto Quality's
+    // last step, x2 is skipped and the analyzer, spectrogram and phase curve aren't worked out
+    #[persist = "economy"]
+    economy: Arc<AtomicBool>,
+
+    // Meter with the in/out gain knob positions instead of the smoothed gain the audio gets
+    #[persist = "meter-target-gain"]
+    meter_target_gain: Arc<AtomicBool>,
+
+    // Output meter reads before the output gain and limiter, clip then means the EQ itself is over
+    #[persist = "meter-pre-output"]
+    meter_pre_output: Arc<AtomicBool>,
+
+    // How long the in/out meters average over, per sample so it's the same at any buffer size
+    #[persist = "meter-window"]
+    meter_window: Arc<AtomicF32>,
+
+    // Grid size in dB that band gains snap to while dragging, 0 is off
+    #[persist = "gain-snap"]
+    gain_snap: Arc<AtomicF32>,
+
+    // Res that Reset Band puts a band back to, DEFAULT_RES until it's changed
+    #[persist = "reset-res"]
+    reset_res: Arc<AtomicF32>,
+
+    // Changing a band's type moves the output gain so the broadband level stays about the same
+    #[persist = "type-compensation"]
+    type_compensation: Arc<AtomicBool>,
+
+    // Dragging a node on the graph listens to that band until it's let go
+    #[persist = "auto-listen"]
+    auto_listen: Arc<AtomicBool>,
+
+    // Holding any of a band's knobs or its gain slider listens to that band
+    #[persist = "knob-listen"]
+    knob_listen: Arc<AtomicBool>,
+
+    // Listening to a band evens its level out by its bandwidth so narrow and wide bands compare
+    #[persist = "listen-level"]
+    listen_level: Arc<AtomicBool>,
+
+    // Res knobs show Q, bandwidth in octaves or bandwidth in Hz
+    #[persist = "res-display"]
+    res_display: Arc<AtomicU32>,
+
+    // Analyzer shows the summed input, or left/right or mid/side overlaid
+    #[persist = "analyzer-mode"]
+    analyzer_mode: Arc<AtomicU32>,
+
+    // Scrolling spectrogram under the graph in place of the spectrum curves
+    #[persist = "analyzer-spectrogram"]
+    analyzer_spectrogram: Arc<AtomicBool>,
+
+    // Keep the loudest each bin has been until it's cleared, for catching the odd spike
+    #[persist = "analyzer-hold"]
+    analyzer_hold: Arc<AtomicBool>,
+
+    // Close up of the analyzer under the graph, the region is set by right dragging on the graph
+    #[persist = "analyzer-zoom"]
+    analyzer_zoom: Arc<AtomicBool>,
+    #[persist = "zoom-low"]
+    zoom_low: Arc<AtomicF32>,
+    #[persist = "zoom-high"]
+    zoom_high: Arc<AtomicF32>,
+
+    // Frequency range across the response graph, wider is handy at high sample rates
+    #[persist = "graph-min-freq"]
+    graph_min_freq: Arc<AtomicF32>,
+    #[persist = "graph-max-freq"]
+    graph_max_freq: Arc<AtomicF32>,
+
+    // Graph dB range, fitted to the curve when auto or plus and minus the manual range
+    #[persist = "graph-auto-db"]
+    graph_auto_db: Arc<AtomicBool>,
+    #[persist = "graph-db-range"]
+    graph_db_range: Arc<AtomicF32>,
+
+    // Overlay the EQ's combined phase shift on the graph
+    #[persist = "graph-phase"]
+    graph_phase: Arc<AtomicBool>,
+
+    // Graph frequency gridlines on notes instead of decades
+    #[persist = "graph-note-grid"]
+    graph_note_grid: Arc<AtomicBool>,
+
+    // Reference line across the analyzer at a dBFS level, bins over it are marked
+    #[persist = "analyzer-threshold"]
+    analyzer_threshold: Arc<AtomicBool>,
+    #[persist = "analyzer-threshold-level"]
+    analyzer_threshold_level: Arc<AtomicF32>,
+
+    // Moving any band's Res knob sets every band's Res to match, unlinking leaves them where they are
+    #[persist = "link-q"]
+    link_q: Arc<AtomicBool>,
+
+    // dBFS a band's Gain to Target aims the input at its frequency for
+    #[persist = "band-target-level"]
+    band_target_level: Arc<AtomicF32>,
+
     #[id = "input_gain"]
     pub input_gain: FloatParam,
 
@@ -85,6 +817,92 @@ struct InterleafParams {
     #[id = "interleaves"]
     pub interleaves: FloatParam,
 
+    #[id = "interleave_curve"]
+    pub interleave_curve: EnumParam<BlendCurve>,
+
+    // Multiplies every band's resonance to widen or tighten the whole curve together
+    #[id = "q_scale"]
+    pub q_scale: FloatParam,
+
+    // Morph runs the bands from part way between the stored A and B setups instead of the knobs
+    #[id = "morph_enabled"]
+    pub morph_enabled: BoolParam,
+
+    #[id = "morph"]
+    pub morph: FloatParam,
+
+    // Resonance suppressor: one band becomes a dynamic notch on the strongest resonance between
+    // the region bounds, cutting more the further it stands out
+    #[id = "suppressor"]
+    pub suppressor: BoolParam,
+
+    #[id = "suppressor_band"]
+    pub suppressor_band: IntParam,
+
+    #[id = "suppressor_strength"]
+    pub suppressor_strength: FloatParam,
+
+    #[id = "suppressor_low"]
+    pub suppressor_low: FloatParam,
+
+    #[id = "suppressor_high"]
+    pub suppressor_high: FloatParam,
+
+    // Split the gain of gain using bands across the x2 cascade passes
+    #[id = "os_makeup"]
+    pub os_makeup: BoolParam,
+
+    // Only run the x2 cascade while a band above the threshold is boosting, saves CPU otherwise
+    #[id = "os_auto"]
+    pub os_auto: BoolParam,
+
+    #[id = "os_auto_freq"]
+    pub os_auto_freq: FloatParam,
+
+    // Ease off the Q of bands close to Nyquist so high resonances don't get shrill
+    #[id = "soft_q"]
+    pub soft_q: BoolParam,
+
+    // Run the whole band chain on the side signal only, mid passes through untouched
+    #[id = "side_only"]
+    pub side_only: BoolParam,
+
+    // Opt-in automatic quality reduction when the CPU can't keep up
+    #[id = "auto_quality"]
+    pub auto_quality: BoolParam,
+
+    // Multiband split, 1 region is off
+    #[id = "regions"]
+    pub regions: IntParam,
+
+    #[id = "crossover_low"]
+    pub crossover_low: FloatParam,
+
+    #[id = "crossover_high"]
+    pub crossover_high: FloatParam,
+
+    #[id = "interleaves_low"]
+    pub interleaves_low: FloatParam,
+
+    #[id = "interleaves_mid"]
+    pub interleaves_mid: FloatParam,
+
+    #[id = "interleaves_high"]
+    pub interleaves_high: FloatParam,
+
+    // Output lookahead limiter
+    #[id = "limiter"]
+    pub limiter: BoolParam,
+
+    #[id = "limiter_ceiling"]
+    pub limiter_ceiling: FloatParam,
+
+    #[id = "limiter_lookahead"]
+    pub limiter_lookahead: FloatParam,
+
+    #[id = "limiter_release"]
+    pub limiter_release: FloatParam,
+
     // Bands
     #[id = "freq_band_0"]
     pub freq_band_0: FloatParam,
@@ -148,6 +966,93 @@ struct InterleafParams {
 
     #[id = "type_4"]
     pub type_4: EnumParam<biquad_filters::FilterType>,
+
+    // Interleave start offsets
+    #[id = "offset_0"]
+    pub offset_0: IntParam,
+
+    #[id = "offset_1"]
+    pub offset_1: IntParam,
+
+    #[id = "offset_2"]
+    pub offset_2: IntParam,
+
+    #[id = "offset_3"]
+    pub offset_3: IntParam,
+
+    #[id = "offset_4"]
+    pub offset_4: IntParam,
+
+    // Analog style saturation of a shelf band's boost or cut, does nothing on other types
+    #[id = "drive_0"]
+    pub drive_0: FloatParam,
+
+    #[id = "drive_1"]
+    pub drive_1: FloatParam,
+
+    #[id = "drive_2"]
+    pub drive_2: FloatParam,
+
+    #[id = "drive_3"]
+    pub drive_3: FloatParam,
+
+    #[id = "drive_4"]
+    pub drive_4: FloatParam,
+
+    // How much of each band is heard against what came into it, for parallel processing per band
+    #[id = "wet_0"]
+    pub wet_0: FloatParam,
+
+    #[id = "wet_1"]
+    pub wet_1: FloatParam,
+
+    #[id = "wet_2"]
+    pub wet_2: FloatParam,
+
+    #[id = "wet_3"]
+    pub wet_3: FloatParam,
+
+    #[id = "wet_4"]
+    pub wet_4: FloatParam,
+
+    // Allpass compensation after each band that flattens its phase through the band, see
+    // phase_compensation.rs
+    #[id = "phase_comp_0"]
+    pub phase_comp_0: BoolParam,
+
+    #[id = "phase_comp_1"]
+    pub phase_comp_1: BoolParam,
+
+    #[id = "phase_comp_2"]
+    pub phase_comp_2: BoolParam,
+
+    #[id = "phase_comp_3"]
+    pub phase_comp_3: BoolParam,
+
+    #[id = "phase_comp_4"]
+    pub phase_comp_4: BoolParam,
+
+    // Sweep macro: moves one band's frequency from its own knob onto the sweep bounds, its gain, Q
+    // and type stay put. At 0 the band sits at its own frequency knob again.
+    #[id = "sweep"]
+    pub sweep: FloatParam,
+
+    #[id = "sweep_band"]
+    pub sweep_band: IntParam,
+
+    #[id = "sweep_low"]
+    pub sweep_low: FloatParam,
+
+    #[id = "sweep_high"]
+    pub sweep_high: FloatParam,
+
+    // Synced, the sweep goes up and back down over this many beats while the transport plays
+    // and the macro sets how far up it goes
+    #[id = "sweep_sync"]
+    pub sweep_sync: BoolParam,
+
+    #[id = "sweep_beats"]
+    pub sweep_beats: IntParam,
 }
 
 impl Default for Interleaf {
@@ -155,89 +1060,730 @@ impl Default for Interleaf {
         Self {
             params: Arc::new(InterleafParams::default()),
             out_meter_decay_weight: 1.0,
+            meter_window_weight: 1.0,
+            meter_window_applied: 0.0,
+            in_meter_square: 0.0,
+            out_meter_square: 0.0,
+            peak_hold_decay_weight: 1.0,
+            vu_weight: 1.0,
             out_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
             in_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            out_meter_peak: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            in_meter_peak: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            in_vu: Arc::new(AtomicF32::new(0.0)),
+            out_vu: Arc::new(AtomicF32::new(0.0)),
+            // These get their real sample rate in initialize()
+            true_peak: true_peak::TruePeakDetector::new(),
+            balance_low_filter: biquad_filters::Biquad::new(44100.0, BALANCE_LOW_FREQ, 0.0, 0.707, FilterType::LowPass),
+            balance_high_filter: biquad_filters::Biquad::new(44100.0, BALANCE_HIGH_FREQ, 0.0, 0.707, FilterType::HighPass),
+            balance_low_energy: 0.0,
+            balance_high_energy: 0.0,
+            balance_meter: Arc::new(AtomicF32::new(0.0)),
+            // This gets reallocated for the real sample rate in initialize()
+            limiter: limiter::LookaheadLimiter::new(0),
+            limiter_meter: Arc::new(AtomicF32::new(0.0)),
+            limiter_latency: 0,
+            quality_level: 0,
+            overload_count: 0,
+            headroom_count: 0,
+            oversampling_active: 0,
+            oversampling_target: 0,
+            oversampling_mix: 1.0,
+            quality_meter: Arc::new(AtomicU32::new(0)),
+            analyzer: analyzer::Analyzer::new(),
+            analyzer_second: analyzer::Analyzer::new(),
+            analyzer_frame: Arc::new(Mutex::new(vec![0.0; analyzer::FFT_SIZE * 2])),
+            analyzer_fft: Arc::new(Mutex::new([analyzer::Analyzer::new(), analyzer::Analyzer::new()])),
+            analyzer_bins: Arc::new(Mutex::new(vec![analyzer::ANALYZER_MIN_DB; analyzer::NUM_BINS])),
+            analyzer_bins_second: Arc::new(Mutex::new(vec![analyzer::ANALYZER_MIN_DB; analyzer::NUM_BINS])),
+            analyzer_difference: Arc::new(Mutex::new(vec![0.0; analyzer::NUM_BINS])),
+            analyzer_held: Arc::new(Mutex::new(vec![analyzer::ANALYZER_MIN_DB; analyzer::NUM_BINS])),
+            spectrogram: Arc::new(Mutex::new(spectrogram::Spectrogram::new())),
+            peak_frequency: Arc::new(AtomicF32::new(0.0)),
+            match_capture: match_eq::MatchCapture::new(),
+            match_state: Arc::new(AtomicU32::new(match_eq::MATCH_IDLE)),
+            trim_measure: loudness_trim::TrimMeasure::new(),
+            trim_state: Arc::new(AtomicU32::new(loudness_trim::TRIM_IDLE)),
+            trim_gain: Arc::new(AtomicF32::new(1.0)),
+            match_difference: Arc::new(Mutex::new(vec![0.0; analyzer::NUM_BINS])),
+            match_frame: Arc::new(Mutex::new(vec![0.0; 2 * analyzer::FFT_SIZE])),
+            match_average: Arc::new(Mutex::new(match_eq::MatchAverage::new())),
+            held_bypass: Arc::new(AtomicU32::new(0)),
+            listen_band: Arc::new(AtomicI32::new(NO_LISTEN)),
+            listen_filter: biquad_filters::Biquad::new(44100.0, 1000.0, 0.0, 0.707, FilterType::BandPass),
+            listen_mix: 0.0,
+            listen_fade_band: 0,
+            solo_region: Arc::new(AtomicBool::new(false)),
+            solo_low: Arc::new(AtomicF32::new(200.0)),
+            solo_high: Arc::new(AtomicF32::new(2000.0)),
+            solo_highpass: biquad_filters::Biquad::new(44100.0, 200.0, 0.0, 0.707, FilterType::HighPass),
+            solo_lowpass: biquad_filters::Biquad::new(44100.0, 2000.0, 0.0, 0.707, FilterType::LowPass),
+            test_noise: Arc::new(AtomicBool::new(false)),
+            pink_noise: test_signal::PinkNoise::new(),
+            hunt_freq: Arc::new(AtomicF32::new(0.0)),
+            hunt_filter: biquad_filters::Biquad::new(44100.0, 1000.0, HUNT_BOOST_DB, HUNT_BOOST_Q, FilterType::Peak),
+            suppressor_analyzer: analyzer::Analyzer::new(),
+            suppressor_frame: Arc::new(Mutex::new(vec![0.0; analyzer::FFT_SIZE])),
+            suppressor_fft: Arc::new(Mutex::new(analyzer::Analyzer::new())),
+            suppressor_target: Arc::new(AtomicF32::new(0.0)),
+            suppressor_excess: Arc::new(AtomicF32::new(0.0)),
+            effective_q: Arc::new(std::array::from_fn(|_| AtomicF32::new(0.0))),
+            effective_q_countdown: 0,
+            sample_rate: Arc::new(AtomicF32::new(44100.0)),
             // Hard code to 44100, will update in processing
-            equalizer: Arc::new(Mutex::new(EQ {
-                non_interleave_bands: [
-                        // These defaults don't matter as they are overwritten immediately
-                        biquad_filters::Biquad::new( 44100.0,800.0,0.0, 0.707, FilterType::Peak)
-                        // 5 Bands of the above
-                        ; 5
-                    ],
-                interleave_bands: [
-                        // These defaults don't matter as they are overwritten immediately
-                        biquad_filters::InterleavedBiquad::new( 44100.0,800.0,0.0, 0.707, FilterType::Peak, 2)
-                        // 5 Bands of the above
-                        ; 5
-                    ],
-            })),
+            equalizer: [EQ::new(); crossover::MAX_REGIONS],
+            // Also overwritten immediately
+            multiband: crossover::MultibandSplit::new(44100.0, 250.0, 2500.0),
+            bulk_fade_equalizer: [EQ::new(); crossover::MAX_REGIONS],
+            bulk_fade_multiband: crossover::MultibandSplit::new(44100.0, 250.0, 2500.0),
+            bulk_fade_mix: 1.0,
+            processing: false,
+            keep_next_reset: false,
         }
     }
 }
 
-impl Default for InterleafParams {
-    fn default() -> Self {
-        Self {
-            editor_state: EguiState::from_size(WIDTH, HEIGHT),
+impl InterleafParams {
+    /// Each band's (type, freq, gain, res) params in band order
+    pub fn bands(&self) -> [(&EnumParam<FilterType>, &FloatParam, &FloatParam, &FloatParam); 5] {
+        [
+            (&self.type_0, &self.freq_band_0, &self.gain_band_0, &self.res_band_0),
+            (&self.type_1, &self.freq_band_1, &self.gain_band_1, &self.res_band_1),
+            (&self.type_2, &self.freq_band_2, &self.gain_band_2, &self.res_band_2),
+            (&self.type_3, &self.freq_band_3, &self.gain_band_3, &self.res_band_3),
+            (&self.type_4, &self.freq_band_4, &self.gain_band_4, &self.res_band_4),
+        ]
+    }
 
-            // Input gain dB parameter
-            input_gain: FloatParam::new(
-                "In",
-                util::db_to_gain(0.0),
-                FloatRange::Skewed {
-                    min: util::db_to_gain(-12.0),
-                    max: util::db_to_gain(12.0),
-                    factor: FloatRange::gain_skew_factor(-12.0, 12.0),
-                },
-            )
-            .with_smoother(SmoothingStyle::Logarithmic(50.0))
-            .with_value_to_string(formatters::v2s_f32_rounded(1))
-            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+    /// Each band's interleave start offset in band order
+    pub fn offsets(&self) -> [&IntParam; 5] {
+        [&self.offset_0, &self.offset_1, &self.offset_2, &self.offset_3, &self.offset_4]
+    }
 
-            // Output gain parameter
-            output_gain: FloatParam::new(
-                "Out",
-                util::db_to_gain(0.0),
-                FloatRange::Skewed {
-                    min: util::db_to_gain(-12.0),
-                    max: util::db_to_gain(12.0),
-                    factor: FloatRange::gain_skew_factor(-12.0, 12.0),
-                },
-            )
-            .with_smoother(SmoothingStyle::Logarithmic(50.0))
-            .with_value_to_string(formatters::v2s_f32_rounded(1))
-            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+    pub fn drives(&self) -> [&FloatParam; 5] {
+        [&self.drive_0, &self.drive_1, &self.drive_2, &self.drive_3, &self.drive_4]
+    }
 
-            // Dry/Wet parameter
-            dry_wet: FloatParam::new("Wet", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
-                .with_unit("%")
-                .with_value_to_string(formatters::v2s_f32_percentage(2))
-                .with_string_to_value(formatters::s2v_f32_percentage()),
+    pub fn wets(&self) -> [&FloatParam; 5] {
+        [&self.wet_0, &self.wet_1, &self.wet_2, &self.wet_3, &self.wet_4]
+    }
 
-            oversampling: FloatParam::new(
-                "x2",
-                0.0,
-                FloatRange::Linear {
-                    min: 0.0,
-                    max: 1.0,
-                },
-            )
-            .with_value_to_string(format_x2())
-            .with_step_size(1.0),
+    pub fn phase_comps(&self) -> [&BoolParam; 5] {
+        [&self.phase_comp_0, &self.phase_comp_1, &self.phase_comp_2, &self.phase_comp_3, &self.phase_comp_4]
+    }
 
-            interleaves: FloatParam::new(
-                "Interleave",
-                4.0,
-                FloatRange::Linear {
-                    min: 1.0,
-                    max: 10.0,
-                },
+    /// How far along the sweep macro is from 0 (resting) to 1 (at the high bound). Synced it
+    /// follows a triangle over the sweep beats scaled by the macro, without a playing transport
+    /// it's just the macro.
+    pub fn sweep_position(&self, pos_beats: Option<f64>) -> f32 {
+        let amount = self.sweep.value();
+        match pos_beats {
+            Some(beats) if self.sweep_sync.value() => {
+                let phase = (beats / self.sweep_beats.value() as f64).fract() as f32;
+                amount * (1.0 - (2.0 * phase - 1.0).abs())
+            }
+            _ => amount,
+        }
+    }
+
+    /// Whether the sweep macro has a band, at rest it's back on its own frequency knob. This
+    /// goes by the macro and not the position so a synced sweep passing 0 doesn't jump.
+    fn swept_band(&self, index: usize) -> bool {
+        index as i32 == self.sweep_band.value() && self.sweep.value() > 0.0
+    }
+
+    /// A band's frequency with the sweep macro applied. It starts from the band's own frequency
+    /// and blends log evenly onto the sweep between the bounds as the position rises, so it's on
+    /// the high bound at full travel and moving the macro off 0 doesn't jump.
+    pub fn band_frequency(&self, index: usize, sweep_position: f32) -> f32 {
+        let manual = self.bands()[index].1.value();
+        if self.swept_band(index) {
+            let low = self.sweep_low.value();
+            let high = self.sweep_high.value();
+            let swept = low * (high / low).powf(sweep_position);
+            manual * (swept / manual).powf(sweep_position)
+        } else {
+            manual
+        }
+    }
+
+    /// A band's resonance after the global Q Scale. Q Scale can take it past the Res knob's range
+    /// so it's only held inside the range the filters are designed for.
+    pub fn band_q(&self, res_param: &FloatParam) -> f32 {
+        self.scaled_q(res_param.value())
+    }
+
+    fn scaled_q(&self, q: f32) -> f32 {
+        (q * self.q_scale.value()).clamp(biquad_filters::MIN_Q, biquad_filters::MAX_Q)
+    }
+
+    /// Type, frequency, gain and Q that each band runs at. With Morph on and both setups stored the
+    /// bands come from between A and B instead of the knobs, the sweep macro still moves its band.
+    /// The resonance suppressor's band is a peak at wherever the suppressor has it.
+    pub fn band_settings(&self, sweep_position: f32) -> BandSettings {
+        let bands = self.bands();
+        let mut settings = std::array::from_fn(|index| {
+            let (type_param, _, gain_param, res_param) = bands[index];
+            (
+                type_param.value(),
+                self.band_frequency(index, sweep_position),
+                gain_param.value(),
+                self.band_q(res_param),
             )
-            .with_step_size(1.0)
-            .with_value_to_string(format_interleave()),
+        });
+        if self.morph_enabled.value() {
+            self.morph_settings(&mut settings);
+        }
+        if self.suppressor.value() {
+            settings[self.suppressor_band.value() as usize] = (
+                FilterType::Peak,
+                self.suppressor_freq.load(std::sync::atomic::Ordering::Relaxed),
+                -self.suppressor_cut.load(std::sync::atomic::Ordering::Relaxed),
+                SUPPRESSOR_Q,
+            );
+        }
+        settings
+    }
 
-            // Non Param Buttons
+    fn morph_settings(&self, settings: &mut BandSettings) {
+        let (Some(a), Some(b)) = (self.morph_a.load(), self.morph_b.load()) else {
+            return;
+        };
+        for (index, setting) in settings.iter_mut().enumerate() {
+            let (filter_type, freq, gain, q) = morph::morph_band(&a, &b, index, self.morph.value());
+            *setting = (
+                filter_type,
+                if self.swept_band(index) { setting.1 } else { freq },
+                gain,
+                self.scaled_q(q),
+            );
+        }
+    }
+
+    /// How many x2 passes actually run. With x2 Auto the cascade only stays in while a band at or
+    /// above the threshold frequency is boosting or resonant enough to matter there.
+    pub fn effective_oversampling(&self, band_settings: &BandSettings) -> usize {
+        let oversampling = self.oversampling.value() as usize;
+        if oversampling == 0 || !self.os_auto.value() {
+            return oversampling;
+        }
+        let threshold = self.os_auto_freq.value();
+        let needed = band_settings.iter().any(|(filter_type, freq, gain, q)| {
+            if *freq < threshold {
+                return false;
+            }
+            match filter_type {
+                FilterType::Peak | FilterType::LowShelf | FilterType::HighShelf => *gain >= OS_AUTO_MIN_BOOST_DB,
+                FilterType::LowPass | FilterType::HighPass | FilterType::BandPass => *q > OS_AUTO_MIN_Q,
+                FilterType::Off | FilterType::Notch => false,
+            }
+        });
+        if needed {
+            oversampling
+        } else {
+            0
+        }
+    }
+
+    /// The "x2" control doesn't resample, it cascades every band once more per step, so a +6 dB
+    /// peak would land at +12 dB. With makeup on the gain types get their gain split across the
+    /// passes which keeps the band centers and shelf plateaus where the knobs say. The pass and
+    /// cut types still get steeper with each pass since that is the character of the cascade.
+    pub fn pass_gain(&self, gain: f32, filter_type: FilterType, oversampling: usize) -> f32 {
+        if self.os_makeup.value() && filter_type.uses_gain() {
+            gain / (oversampling + 1) as f32
+        } else {
+            gain
+        }
+    }
+
+    /// Combined magnitude in dB of all the bands at each frequency. The filters are built fresh
+    /// from the params so the GUI never has to touch the audio thread's EQ.
+    /// Interleaved bands respond like H(z^N) and oversampling cascades each band once per pass.
+    /// Multiband regions are drawn with the main interleave count.
+    pub fn frequency_response(&self, sample_rate: f32, freqs: &[f32]) -> Vec<f32> {
+        self.band_curve(sample_rate, freqs, |db, _| db)
+    }
+
+    /// Combined phase shift in degrees of all the bands at each frequency, wrapped to +-180. Built
+    /// the same way as `frequency_response`, a fractional interleave blends the two counts' phase.
+    pub fn phase_response(&self, sample_rate: f32, freqs: &[f32]) -> Vec<f32> {
+        self.band_curve(sample_rate, freqs, |_, phase| phase)
+            .into_iter()
+            .map(|phase| {
+                let degrees = phase.to_degrees();
+                degrees - 360.0 * ((degrees + 180.0) / 360.0).floor()
+            })
+            .collect()
+    }
+
+    // Sums a per band value over the bands at each frequency, the magnitude in dB and the phase
+    // both add up through the cascade. `band_value` picks from a band's magnitude and phase once
+    // its x2 passes are stacked and its Wet has blended it with the dry path like process does.
+    fn band_curve(
+        &self,
+        sample_rate: f32,
+        freqs: &[f32],
+        band_value: impl Fn(f32, f32) -> f32,
+    ) -> Vec<f32> {
+        let interleave = self.interleaves.value();
+        let whole = interleave.floor();
+        let fraction = interleave - whole;
+        // Whole counts either side, a fractional interleave is drawn as a blend of the two in dB
+        let low_factor = if whole >= 2.0 { whole } else { 1.0 };
+        let high_factor = whole + 1.0;
+        let settings = self.band_settings(self.sweep_position(None));
+        let oversampling = self.effective_oversampling(&settings);
+        let passes = (oversampling + 1) as f32;
+        let wets = self.wets().map(|wet| wet.value());
+        let filters: [biquad_filters::Biquad; 5] = std::array::from_fn(|index| {
+            let (filter_type, freq, gain, q) = settings[index];
+            let mut filter = biquad_filters::Biquad::new(
+                sample_rate,
+                freq,
+                self.pass_gain(gain, filter_type, oversampling),
+                q,
+                filter_type,
+            );
+            filter.set_soft_q(self.soft_q.value());
+            filter
+        });
+        // Phase compensation for each count, it comes after the wet blend and only moves the phase
+        let compensators = |factor: f32| -> [phase_compensation::PhaseCompensator; 5] {
+            std::array::from_fn(|index| {
+                let (filter_type, freq, gain, q) = settings[index];
+                let mut compensator = phase_compensation::PhaseCompensator::new();
+                compensator.update(
+                    self.phase_comps()[index].value(),
+                    (filter_type, freq, self.pass_gain(gain, filter_type, oversampling), q),
+                    self.soft_q.value(),
+                    sample_rate,
+                    factor,
+                    passes,
+                );
+                compensator
+            })
+        };
+        let low_compensators = compensators(low_factor);
+        let high_compensators = if fraction > 0.0 { compensators(high_factor) } else { low_compensators };
+        freqs
+            .iter()
+            .map(|freq| {
+                let response = |factor: f32, band_compensators: &[phase_compensation::PhaseCompensator; 5]| -> f32 {
+                    filters
+                        .iter()
+                        .zip(wets)
+                        .zip(band_compensators)
+                        .map(|((filter, wet), compensator)| {
+                            let db = filter.frequency_response(freq * factor) * passes;
+                            let phase = filter.phase_response(freq * factor) * passes;
+                            let shift = compensator.phase_response(*freq);
+                            if wet >= 1.0 {
+                                return band_value(db, phase + shift);
+                            }
+                            let magnitude = util::db_to_gain(db);
+                            let re = 1.0 + (magnitude * phase.cos() - 1.0) * wet;
+                            let im = magnitude * phase.sin() * wet;
+                            band_value(20.0 * (re * re + im * im).sqrt().max(1e-6).log10(), im.atan2(re) + shift)
+                        })
+                        .sum()
+                };
+                if fraction > 0.0 {
+                    response(low_factor, &low_compensators) * (1.0 - fraction)
+                        + response(high_factor, &high_compensators) * fraction
+                } else {
+                    response(low_factor, &low_compensators)
+                }
+            })
+            .collect()
+    }
+
+    /// Puts one band back to flat at its default frequency and the Reset Res preference, as a shelf
+    /// at either end or a peak in between. All four params are one gesture so it's a single undo
+    /// step. The bands don't follow the smoothers so it's flagged as a bulk change and process
+    /// crossfades over to it instead of jumping.
+    pub fn reset_band(&self, setter: &ParamSetter<'_>, index: usize) {
+        let bands = self.bands();
+        let freq_param = bands[index].1;
+        let filter_type = if index == 0 {
+            FilterType::LowShelf
+        } else if index == bands.len() - 1 {
+            FilterType::HighShelf
+        } else {
+            FilterType::Peak
+        };
+        self.set_band(
+            setter,
+            index,
+            filter_type,
+            freq_param.default_plain_value(),
+            0.0,
+            self.reset_res.load(std::sync::atomic::Ordering::Relaxed),
+        );
+    }
+
+    /// Sets all four of a band's params as one gesture so it's a single undo step, flagged as a
+    /// bulk change so process crossfades over to it
+    pub fn set_band(
+        &self,
+        setter: &ParamSetter<'_>,
+        index: usize,
+        filter_type: FilterType,
+        freq: f32,
+        gain: f32,
+        res: f32,
+    ) {
+        let (type_param, freq_param, gain_param, res_param) = self.bands()[index];
+        setter.begin_set_parameter(type_param);
+        setter.begin_set_parameter(freq_param);
+        setter.begin_set_parameter(gain_param);
+        setter.begin_set_parameter(res_param);
+        setter.set_parameter(type_param, filter_type);
+        setter.set_parameter(freq_param, freq);
+        setter.set_parameter(gain_param, gain);
+        setter.set_parameter(res_param, res);
+        setter.end_set_parameter(type_param);
+        setter.end_set_parameter(freq_param);
+        setter.end_set_parameter(gain_param);
+        setter.end_set_parameter(res_param);
+        self.bulk_change.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Opens a gesture on every band's Res apart from `from`, which Link Q is about to move along
+    /// with it. They stay open through `link_res` until `end_link_res` so a drag is one undo step.
+    pub fn begin_link_res(&self, setter: &ParamSetter<'_>, from: usize) {
+        for (index, (_, _, _, res_param)) in self.bands().into_iter().enumerate() {
+            if index != from {
+                setter.begin_set_parameter(res_param);
+            }
+        }
+    }
+
+    /// Set every band's Res to `res` apart from the one it came from, inside the gestures
+    /// `begin_link_res` opened
+    pub fn link_res(&self, setter: &ParamSetter<'_>, from: usize, res: f32) {
+        for (index, (_, _, _, res_param)) in self.bands().into_iter().enumerate() {
+            if index != from && res_param.value() != res {
+                setter.set_parameter(res_param, res);
+            }
+        }
+    }
+
+    pub fn end_link_res(&self, setter: &ParamSetter<'_>, from: usize) {
+        for (index, (_, _, _, res_param)) in self.bands().into_iter().enumerate() {
+            if index != from {
+                setter.end_set_parameter(res_param);
+            }
+        }
+    }
+
+    /// Set a band's gain to bring the level measured at its frequency to `target_db`, clamped to
+    /// the gain range. The analyzer shows the input before the EQ so the gain is just the gap.
+    pub fn gain_to_target(&self, setter: &ParamSetter<'_>, index: usize, level_db: f32, target_db: f32) {
+        let gain_param = self.bands()[index].2;
+        setter.begin_set_parameter(gain_param);
+        setter.set_parameter(
+            gain_param,
+            (target_db - level_db).clamp(gain_param.preview_plain(0.0), gain_param.preview_plain(1.0)),
+        );
+        setter.end_set_parameter(gain_param);
+    }
+
+    /// After a type change, move the output gain by however much the broadband level moved from
+    /// `level_before` so the overall level stays about the same
+    pub fn compensate_type_change(&self, setter: &ParamSetter<'_>, level_before: f32, sample_rate: f32) {
+        let change = level_before - self.broadband_level_db(sample_rate);
+        let output_gain = &self.output_gain;
+        let gain = output_gain
+            .preview_plain(output_gain.preview_normalized(output_gain.value() * util::db_to_gain(change)));
+        setter.begin_set_parameter(output_gain);
+        setter.set_parameter(output_gain, gain);
+        setter.end_set_parameter(output_gain);
+    }
+
+    /// Power average in dB of the response over log spaced points across the audible range,
+    /// roughly how much louder or quieter the EQ makes broadband material
+    pub fn broadband_level_db(&self, sample_rate: f32) -> f32 {
+        let freqs: Vec<f32> = (0..TYPE_LEVEL_POINTS)
+            .map(|i| 20.0 * 1000.0_f32.powf(i as f32 / (TYPE_LEVEL_POINTS - 1) as f32))
+            .filter(|freq| *freq < sample_rate * biquad_filters::MAX_FREQ_RATIO)
+            .collect();
+        let power: f32 = self
+            .frequency_response(sample_rate, &freqs)
+            .iter()
+            .map(|db| 10.0_f32.powf(db / 10.0))
+            .sum();
+        10.0 * (power / freqs.len().max(1) as f32).max(1e-12).log10()
+    }
+}
+
+impl Default for InterleafParams {
+    fn default() -> Self {
+        // Shared with the frequency formatters so switching it shows up on the knobs right away
+        let note_names = Arc::new(AtomicBool::new(true));
+        let note_numbers = Arc::new(AtomicBool::new(false));
+        Self {
+            editor_state: EguiState::from_size(WIDTH, HEIGHT),
+            morph_a: morph::SnapshotSlot::new(),
+            morph_b: morph::SnapshotSlot::new(),
+            suppressor_freq: Arc::new(AtomicF32::new(4000.0)),
+            bulk_change: AtomicBool::new(false),
+            suppressor_cut: Arc::new(AtomicF32::new(0.0)),
+            band_order: Arc::new(Mutex::new([0, 1, 2, 3, 4])),
+            instance_label: Arc::new(Mutex::new(String::new())),
+            note_names: note_names.clone(),
+            note_numbers: note_numbers.clone(),
+            meters_vertical: Arc::new(AtomicBool::new(false)),
+            needle_meters: Arc::new(AtomicBool::new(false)),
+            true_peak_meter: Arc::new(AtomicBool::new(false)),
+            compact: Arc::new(AtomicBool::new(false)),
+            economy: Arc::new(AtomicBool::new(false)),
+            meter_target_gain: Arc::new(AtomicBool::new(false)),
+            meter_pre_output: Arc::new(AtomicBool::new(false)),
+            meter_window: Arc::new(AtomicF32::new(PEAK_METER_DECAY_MS as f32)),
+            gain_snap: Arc::new(AtomicF32::new(0.0)),
+            reset_res: Arc::new(AtomicF32::new(DEFAULT_RES)),
+            type_compensation: Arc::new(AtomicBool::new(false)),
+            auto_listen: Arc::new(AtomicBool::new(false)),
+            knob_listen: Arc::new(AtomicBool::new(false)),
+            listen_level: Arc::new(AtomicBool::new(false)),
+            res_display: Arc::new(AtomicU32::new(RES_DISPLAY_Q)),
+            analyzer_mode: Arc::new(AtomicU32::new(analyzer::ANALYZER_SUM)),
+            analyzer_spectrogram: Arc::new(AtomicBool::new(false)),
+            analyzer_hold: Arc::new(AtomicBool::new(false)),
+            analyzer_zoom: Arc::new(AtomicBool::new(false)),
+            zoom_low: Arc::new(AtomicF32::new(2000.0)),
+            zoom_high: Arc::new(AtomicF32::new(4000.0)),
+            graph_min_freq: Arc::new(AtomicF32::new(response_graph::GRAPH_MIN_FREQ)),
+            graph_max_freq: Arc::new(AtomicF32::new(response_graph::GRAPH_MAX_FREQ)),
+            graph_auto_db: Arc::new(AtomicBool::new(false)),
+            graph_phase: Arc::new(AtomicBool::new(false)),
+            graph_note_grid: Arc::new(AtomicBool::new(false)),
+            analyzer_threshold: Arc::new(AtomicBool::new(false)),
+            analyzer_threshold_level: Arc::new(AtomicF32::new(-24.0)),
+            link_q: Arc::new(AtomicBool::new(false)),
+            band_target_level: Arc::new(AtomicF32::new(-24.0)),
+            graph_db_range: Arc::new(AtomicF32::new(response_graph::GRAPH_DB_RANGE)),
+
+            // Input gain dB parameter
+            input_gain: FloatParam::new(
+                "In",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-12.0),
+                    max: util::db_to_gain(12.0),
+                    factor: FloatRange::gain_skew_factor(-12.0, 12.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            // Output gain parameter
+            output_gain: FloatParam::new(
+                "Out",
+                util::db_to_gain(0.0),
+                FloatRange::Skewed {
+                    min: util::db_to_gain(-12.0),
+                    max: util::db_to_gain(12.0),
+                    factor: FloatRange::gain_skew_factor(-12.0, 12.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(50.0))
+            .with_value_to_string(formatters::v2s_f32_rounded(1))
+            .with_string_to_value(formatters::s2v_f32_gain_to_db()),
+
+            // Dry/Wet parameter, past 100% the EQ's change is exaggerated by taking dry away again.
+            // That can push the output past 0 dBFS so keep an eye on the clip indicator.
+            dry_wet: FloatParam::new("Wet", 1.0, FloatRange::Linear { min: 0.0, max: 2.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(2))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            oversampling: FloatParam::new(
+                "x2",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 1.0,
+                },
+            )
+            .with_value_to_string(format_x2())
+            .with_step_size(1.0),
+
+            // Between whole counts the two either side get blended on the blend curve
+            interleaves: FloatParam::new(
+                "Interleave",
+                4.0,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: 10.0,
+                },
+            )
+            .with_step_size(0.01)
+            .with_value_to_string(format_interleave()),
+            interleave_curve: EnumParam::new("Blend Curve", BlendCurve::EqualPower),
+            q_scale: FloatParam::new(
+                "Q Scale",
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.25,
+                    max: 4.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit("x")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+            morph_enabled: BoolParam::new("Morph", false),
+            morph: FloatParam::new("Morph A-B", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            suppressor: BoolParam::new("Suppressor", false),
+            suppressor_band: IntParam::new("Suppressor Band", 4, IntRange::Linear { min: 0, max: 4 }),
+            suppressor_strength: FloatParam::new(
+                "Suppressor Strength",
+                0.5,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            suppressor_low: FloatParam::new(
+                "Suppressor Low",
+                2000.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: 0.3,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0)),
+            suppressor_high: FloatParam::new(
+                "Suppressor High",
+                8000.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: 0.3,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0)),
+
+            os_makeup: BoolParam::new("x2 Makeup", true),
+            os_auto: BoolParam::new("x2 Auto", false),
+            os_auto_freq: FloatParam::new(
+                "x2 Above",
+                8000.0,
+                FloatRange::Skewed {
+                    min: 1000.0,
+                    max: 20000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+            soft_q: BoolParam::new("Soft Q", true),
+            side_only: BoolParam::new("Side EQ", false),
+            auto_quality: BoolParam::new("Auto Quality", false).non_automatable(),
+
+            regions: IntParam::new(
+                "Regions",
+                1,
+                IntRange::Linear {
+                    min: 1,
+                    max: crossover::MAX_REGIONS as i32,
+                },
+            )
+            .with_value_to_string(format_regions()),
+            crossover_low: FloatParam::new(
+                "X Low",
+                250.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: 0.3,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0)),
+            // Only used when running 3 regions
+            crossover_high: FloatParam::new(
+                "X High",
+                2500.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: 0.3,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0)),
+            interleaves_low: FloatParam::new(
+                "Low Int",
+                4.0,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: 10.0,
+                },
+            )
+            .with_step_size(1.0)
+            .with_value_to_string(format_interleave()),
+            interleaves_mid: FloatParam::new(
+                "Mid Int",
+                4.0,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: 10.0,
+                },
+            )
+            .with_step_size(1.0)
+            .with_value_to_string(format_interleave()),
+            interleaves_high: FloatParam::new(
+                "High Int",
+                4.0,
+                FloatRange::Linear {
+                    min: 1.0,
+                    max: 10.0,
+                },
+            )
+            .with_step_size(1.0)
+            .with_value_to_string(format_interleave()),
+
+            limiter: BoolParam::new("Limiter", false),
+            limiter_ceiling: FloatParam::new(
+                "Ceiling",
+                -0.3,
+                FloatRange::Linear {
+                    min: -12.0,
+                    max: 0.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            // This is the attack of the limiter as well as the latency it adds
+            limiter_lookahead: FloatParam::new(
+                "Lookahead",
+                5.0,
+                FloatRange::Linear {
+                    min: 0.5,
+                    max: LIMITER_MAX_LOOKAHEAD_MS,
+                },
+            )
+            .with_step_size(0.1)
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            limiter_release: FloatParam::new(
+                "Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 10.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            // Non Param Buttons
             freq_band_0: FloatParam::new(
                 "Band 0",
                 200.0,
@@ -247,9 +1793,8 @@ impl Default for InterleafParams {
                     factor: 0.3,
                 },
             )
-            .with_step_size(1.0)
             .with_smoother(SmoothingStyle::Linear(5.0))
-            .with_value_to_string(formatters::v2s_f32_hz_then_khz_with_note_name(2, false)),
+            .with_value_to_string(format_frequency(note_names.clone(), note_numbers.clone())),
             freq_band_1: FloatParam::new(
                 "Band 1",
                 800.0,
@@ -259,9 +1804,8 @@ impl Default for InterleafParams {
                     factor: 0.4,
                 },
             )
-            .with_step_size(1.0)
             .with_smoother(SmoothingStyle::Linear(5.0))
-            .with_value_to_string(formatters::v2s_f32_hz_then_khz_with_note_name(2, false)),
+            .with_value_to_string(format_frequency(note_names.clone(), note_numbers.clone())),
             freq_band_2: FloatParam::new(
                 "Band 2",
                 2000.0,
@@ -271,9 +1815,8 @@ impl Default for InterleafParams {
                     factor: 0.5,
                 },
             )
-            .with_step_size(1.0)
             .with_smoother(SmoothingStyle::Linear(5.0))
-            .with_value_to_string(formatters::v2s_f32_hz_then_khz_with_note_name(2, false)),
+            .with_value_to_string(format_frequency(note_names.clone(), note_numbers.clone())),
             freq_band_3: FloatParam::new(
                 "Band 3",
                 8000.0,
@@ -283,9 +1826,8 @@ impl Default for InterleafParams {
                     factor: 0.7,
                 },
             )
-            .with_step_size(1.0)
             .with_smoother(SmoothingStyle::Linear(5.0))
-            .with_value_to_string(formatters::v2s_f32_hz_then_khz_with_note_name(2, false)),
+            .with_value_to_string(format_frequency(note_names.clone(), note_numbers.clone())),
             freq_band_4: FloatParam::new(
                 "Band 4",
                 15000.0,
@@ -295,9 +1837,8 @@ impl Default for InterleafParams {
                     factor: 1.0,
                 },
             )
-            .with_step_size(1.0)
             .with_smoother(SmoothingStyle::Linear(5.0))
-            .with_value_to_string(formatters::v2s_f32_hz_then_khz_with_note_name(2, false)),
+            .with_value_to_string(format_frequency(note_names.clone(), note_numbers.clone())),
 
             // Gain Bands
             gain_band_0: FloatParam::new(
@@ -350,7 +1891,7 @@ impl Default for InterleafParams {
             // Res Bands
             res_band_0: FloatParam::new(
                 "Res 0",
-                0.707,
+                DEFAULT_RES,
                 FloatRange::Linear {
                     min: 0.01,
                     max: 1.0,
@@ -360,7 +1901,7 @@ impl Default for InterleafParams {
             .with_value_to_string(formatters::v2s_f32_rounded(1)),
             res_band_1: FloatParam::new(
                 "Res 1",
-                0.707,
+                DEFAULT_RES,
                 FloatRange::Linear {
                     min: 0.01,
                     max: 1.0,
@@ -370,7 +1911,7 @@ impl Default for InterleafParams {
             .with_value_to_string(formatters::v2s_f32_rounded(1)),
             res_band_2: FloatParam::new(
                 "Res 2",
-                0.707,
+                DEFAULT_RES,
                 FloatRange::Linear {
                     min: 0.01,
                     max: 1.0,
@@ -380,7 +1921,7 @@ impl Default for InterleafParams {
             .with_value_to_string(formatters::v2s_f32_rounded(1)),
             res_band_3: FloatParam::new(
                 "Res 3",
-                0.707,
+                DEFAULT_RES,
                 FloatRange::Linear {
                     min: 0.01,
                     max: 1.0,
@@ -390,7 +1931,7 @@ impl Default for InterleafParams {
             .with_value_to_string(formatters::v2s_f32_rounded(1)),
             res_band_4: FloatParam::new(
                 "Res 4",
-                0.707,
+                DEFAULT_RES,
                 FloatRange::Linear {
                     min: 0.01,
                     max: 1.0,
@@ -405,51 +1946,446 @@ impl Default for InterleafParams {
             type_2: EnumParam::new("Type 2", FilterType::Peak),
             type_3: EnumParam::new("Type 3", FilterType::Peak),
             type_4: EnumParam::new("Type 4", FilterType::HighShelf),
+
+            // Interleave start offsets, the biquads wrap around so this covers every position
+            offset_0: IntParam::new("Offset 0", 0, IntRange::Linear { min: 0, max: 9 }),
+            offset_1: IntParam::new("Offset 1", 0, IntRange::Linear { min: 0, max: 9 }),
+            offset_2: IntParam::new("Offset 2", 0, IntRange::Linear { min: 0, max: 9 }),
+            offset_3: IntParam::new("Offset 3", 0, IntRange::Linear { min: 0, max: 9 }),
+            offset_4: IntParam::new("Offset 4", 0, IntRange::Linear { min: 0, max: 9 }),
+
+            drive_0: FloatParam::new("Drive 0", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            drive_1: FloatParam::new("Drive 1", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            drive_2: FloatParam::new("Drive 2", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            drive_3: FloatParam::new("Drive 3", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            drive_4: FloatParam::new("Drive 4", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            wet_0: FloatParam::new("Wet 0", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            wet_1: FloatParam::new("Wet 1", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            wet_2: FloatParam::new("Wet 2", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            wet_3: FloatParam::new("Wet 3", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            wet_4: FloatParam::new("Wet 4", 1.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            phase_comp_0: BoolParam::new("Phase Comp 0", false),
+            phase_comp_1: BoolParam::new("Phase Comp 1", false),
+            phase_comp_2: BoolParam::new("Phase Comp 2", false),
+            phase_comp_3: BoolParam::new("Phase Comp 3", false),
+            phase_comp_4: BoolParam::new("Phase Comp 4", false),
+
+            sweep: FloatParam::new("Sweep", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .with_unit("%")
+                .with_value_to_string(formatters::v2s_f32_percentage(0))
+                .with_string_to_value(formatters::s2v_f32_percentage()),
+            sweep_band: IntParam::new("Sweep Band", 0, IntRange::Linear { min: 0, max: 4 }),
+            sweep_low: FloatParam::new(
+                "Sweep Low",
+                200.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: 0.3,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0)),
+            sweep_high: FloatParam::new(
+                "Sweep High",
+                5000.0,
+                FloatRange::Skewed {
+                    min: 20.0,
+                    max: 20000.0,
+                    factor: 0.3,
+                },
+            )
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0)),
+            sweep_sync: BoolParam::new("Sweep Sync", false),
+            sweep_beats: IntParam::new("Sweep Beats", 4, IntRange::Linear { min: 1, max: 16 })
+                .with_unit(" beats"),
         }
     }
 }
 
 impl Interleaf {
-    fn create_band_gui(
-        ui: &mut Ui,
-        type_param: &EnumParam<FilterType>,
-        freq_param: &FloatParam,
-        gain_param: &FloatParam,
-        res_param: &FloatParam,
-        setter: &ParamSetter<'_>,
-        knob_size: f32,
-    ) {
-        ui.vertical(|ui| {
-            ui.add(
-                VerticalParamSlider::for_param(gain_param, setter)
-                    .with_width(VERT_BAR_WIDTH * 2.0)
-                    .with_height(VERT_BAR_HEIGHT)
-                    .set_reversed(true),
-            );
-            let mut type_knob = ui_knob::ArcKnob::for_param(type_param, setter, knob_size);
-            type_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
-            type_knob.set_fill_color(ACCENT);
-            type_knob.set_line_color(MAIN);
-            type_knob.set_show_label(true);
-            type_knob.set_text_size(10.0);
-            ui.add(type_knob);
+    // Moves the suppressor's band toward what detection last found, digging in at the attack and
+    // letting go at the release. With nothing standing out it relaxes back to flat where it is.
+    fn update_suppressor(&mut self, num_samples: usize, sr: f32) {
+        let excess = if self.params.suppressor.value() {
+            self.suppressor_excess.load(std::sync::atomic::Ordering::Relaxed)
+        } else {
+            0.0
+        };
+        let target_cut = if excess >= SUPPRESSOR_MIN_PROMINENCE_DB {
+            (excess * self.params.suppressor_strength.value()).min(SUPPRESSOR_MAX_CUT_DB)
+        } else {
+            0.0
+        };
+        let weight = |ms: f32| (-(num_samples as f32) / (ms * 0.001 * sr)).exp();
 
-            let mut freq_knob = ui_knob::ArcKnob::for_param(freq_param, setter, knob_size);
-            freq_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
-            freq_knob.set_fill_color(ACCENT);
-            freq_knob.set_line_color(MAIN);
-            freq_knob.set_show_label(true);
-            freq_knob.set_text_size(10.0);
-            ui.add(freq_knob);
+        let cut = self.params.suppressor_cut.load(std::sync::atomic::Ordering::Relaxed);
+        let cut_weight = weight(if target_cut > cut { SUPPRESSOR_ATTACK_MS } else { SUPPRESSOR_RELEASE_MS });
+        self.params.suppressor_cut.store(
+            target_cut + (cut - target_cut) * cut_weight,
+            std::sync::atomic::Ordering::Relaxed,
+        );
 
-            let mut res_knob = ui_knob::ArcKnob::for_param(res_param, setter, knob_size);
-            res_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+        // Glide evenly in octaves so jumps between resonances don't sweep through the top end
+        let target_freq = self.suppressor_target.load(std::sync::atomic::Ordering::Relaxed);
+        if target_cut > 0.0 && target_freq > 0.0 {
+            let freq = self.params.suppressor_freq.load(std::sync::atomic::Ordering::Relaxed);
+            self.params.suppressor_freq.store(
+                freq * (target_freq / freq).powf(1.0 - weight(SUPPRESSOR_GLIDE_MS)),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+        }
+    }
+
+    // Caps the interleave count once Auto Quality has reached its second step or in economy mode
+    fn limit_interleave(&self, interleave: f32) -> f32 {
+        if self.quality_level >= 2 || self.params.economy.load(std::sync::atomic::Ordering::Relaxed) {
+            interleave.min(AUTO_QUALITY_INTERLEAVE)
+        } else {
+            interleave
+        }
+    }
+
+    // Steps the quality down when we keep using too much of the real-time budget, and back up once
+    // there's plenty of headroom again. Takes the fraction of the buffer's duration process took.
+    fn update_quality(&mut self, budget_used: f32) {
+        if !self.params.auto_quality.value() {
+            self.quality_level = 0;
+            self.overload_count = 0;
+            self.headroom_count = 0;
+        } else if budget_used > AUTO_QUALITY_OVERLOAD {
+            self.headroom_count = 0;
+            self.overload_count += 1;
+            if self.overload_count >= AUTO_QUALITY_BUFFERS && self.quality_level < AUTO_QUALITY_MAX_LEVEL {
+                self.quality_level += 1;
+                self.overload_count = 0;
+            }
+        } else if budget_used < AUTO_QUALITY_RECOVER {
+            self.overload_count = 0;
+            self.headroom_count += 1;
+            // Be much slower to step back up so we don't flip back and forth
+            if self.headroom_count >= AUTO_QUALITY_BUFFERS * 16 && self.quality_level > 0 {
+                self.quality_level -= 1;
+                self.headroom_count = 0;
+            }
+        } else {
+            self.overload_count = 0;
+            self.headroom_count = 0;
+        }
+        self.quality_meter
+            .store(self.quality_level, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    // Keeps the limiter's lookahead and release in line with the params, returns the new latency
+    // to report to the host when it changed
+    fn update_limiter(&mut self, sample_rate: f32) -> Option<u32> {
+        let lookahead = (self.params.limiter_lookahead.value() / 1000.0 * sample_rate) as usize;
+        if lookahead != self.limiter.lookahead() {
+            self.limiter.set_lookahead(lookahead);
+        }
+        self.limiter
+            .set_release(self.params.limiter_release.value(), sample_rate);
+
+        let latency = if self.params.limiter.value() {
+            self.limiter.lookahead() as u32
+        } else {
+            0
+        };
+        if latency != self.limiter_latency {
+            self.limiter_latency = latency;
+            Some(latency)
+        } else {
+            None
+        }
+    }
+
+    fn create_meter(
+        level_normalized: f32,
+        peak_normalized: f32,
+        text: String,
+        bar_color: Color32,
+        vertical: bool,
+    ) -> db_meter::DBMeter {
+        let mut meter = db_meter::DBMeter::new(level_normalized)
+            .peak_hold(peak_normalized)
+            .vertical(vertical)
+            .text(text);
+        if vertical {
+            meter = meter
+                .desired_width(VERT_BAR_WIDTH / 2.0)
+                .desired_height(VERT_BAR_HEIGHT);
+        }
+        meter.set_background_color(BLACK);
+        meter.set_bar_color(bar_color);
+        meter.set_border_color(MAIN);
+        meter
+    }
+
+    // Checkbox for a bool param that goes through the setter as one gesture
+    fn create_toggle(ui: &mut Ui, param: &BoolParam, setter: &ParamSetter<'_>, label: &str) {
+        let mut value = param.value();
+        if ui.checkbox(&mut value, label).changed() {
+            setter.begin_set_parameter(param);
+            setter.set_parameter(param, value);
+            setter.end_set_parameter(param);
+        }
+    }
+
+    // Same style as the bottom row knobs
+    fn create_small_knob<P: Param>(ui: &mut Ui, param: &P, setter: &ParamSetter<'_>) {
+        let mut knob = ui_knob::ArcKnob::for_param(param, setter, VERT_BAR_WIDTH - 4.0);
+        knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+        knob.set_text_size(10.0);
+        knob.set_fill_color(ACCENT);
+        knob.set_line_color(LIGHT);
+        ui.add(knob);
+    }
+
+    // Typed entry for a float param, anything out of range gets clamped and shown clamped
+    fn create_value_entry(ui: &mut Ui, param: &FloatParam, setter: &ParamSetter<'_>, suffix: &str) {
+        let mut value = param.value();
+        let range = param.preview_plain(0.0)..=param.preview_plain(1.0);
+        let speed = (param.preview_plain(1.0) - param.preview_plain(0.0)) / 500.0;
+        let response = ui.add(
+            egui::DragValue::new(&mut value)
+                .clamp_range(range)
+                .speed(speed)
+                .max_decimals(2)
+                .suffix(suffix),
+        );
+        // Drags are one gesture, typed values are their own
+        if response.drag_started() {
+            setter.begin_set_parameter(param);
+        }
+        if response.changed() {
+            if response.dragged() {
+                setter.set_parameter(param, value);
+            } else {
+                setter.begin_set_parameter(param);
+                setter.set_parameter(param, value);
+                setter.end_set_parameter(param);
+            }
+        }
+        if response.drag_released() {
+            setter.end_set_parameter(param);
+        }
+    }
+
+    // Every band's settings as rows of typed fields
+    fn create_band_table(ui: &mut Ui, params: &InterleafParams, setter: &ParamSetter<'_>) {
+        egui::Grid::new("band_table").striped(true).show(ui, |ui| {
+            ui.label("Band");
+            ui.label("Type");
+            ui.label("Freq");
+            ui.label("Gain");
+            ui.label("Q");
+            ui.end_row();
+            for (index, (type_param, freq_param, gain_param, res_param)) in params.bands().iter().enumerate() {
+                ui.label(index.to_string());
+                let current_type = type_param.value();
+                egui::ComboBox::from_id_source(("band_table_type", index))
+                    .selected_text(FilterType::variants()[current_type.to_index()])
+                    .show_ui(ui, |ui| {
+                        for (variant_index, name) in FilterType::variants().iter().enumerate() {
+                            let variant = FilterType::from_index(variant_index);
+                            if ui.selectable_label(variant == current_type, *name).clicked() {
+                                setter.begin_set_parameter(*type_param);
+                                setter.set_parameter(*type_param, variant);
+                                setter.end_set_parameter(*type_param);
+                            }
+                        }
+                    });
+                Self::create_value_entry(ui, freq_param, setter, " Hz");
+                Self::create_value_entry(ui, gain_param, setter, " dB");
+                Self::create_value_entry(ui, res_param, setter, "");
+                ui.end_row();
+            }
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_band_gui(
+        ui: &mut Ui,
+        type_param: &EnumParam<FilterType>,
+        freq_param: &FloatParam,
+        gain_param: &FloatParam,
+        res_param: &FloatParam,
+        setter: &ParamSetter<'_>,
+        knob_size: f32,
+        gain_snap_db: f32,
+        sample_rate: f32,
+        res_display: u32,
+        effective_q: f32,
+        touched: &std::cell::Cell<bool>,
+    ) -> bool {
+        ui.vertical(|ui| {
+            // Convert the dB grid to a normalized step, the gain range is linear so this is exact
+            let gain_snap = if gain_snap_db > 0.0 {
+                Some(gain_param.preview_normalized(gain_snap_db) - gain_param.preview_normalized(0.0))
+            } else {
+                None
+            };
+            // Pass and cut types ignore gain, the value is kept for switching back
+            let gain_response = ui.add_enabled(
+                type_param.value().uses_gain(),
+                VerticalParamSlider::for_param(gain_param, setter)
+                    .with_width(VERT_BAR_WIDTH * 2.0)
+                    .with_height(VERT_BAR_HEIGHT)
+                    .set_reversed(true)
+                    .with_snap(gain_snap)
+                    .with_double_click_value(0.0),
+            );
+            if gain_response.is_pointer_button_down_on() {
+                touched.set(true);
+            }
+            let mut type_knob = ui_knob::ArcKnob::for_param(type_param, setter, knob_size);
+            type_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+            type_knob.set_fill_color(ACCENT);
+            type_knob.set_line_color(MAIN);
+            type_knob.set_show_label(true);
+            type_knob.set_text_size(10.0);
+            type_knob.set_touch_flag(touched);
+            let type_response = ui.add(type_knob).on_hover_text(type_param.value().description());
+
+            let mut freq_knob = ui_knob::ArcKnob::for_param(freq_param, setter, knob_size);
+            freq_knob.set_step_fn(|normalized, steps| {
+                freq_param.preview_normalized(step_frequency(
+                    freq_param.preview_plain(normalized),
+                    steps as f32 * FREQ_STEP_CENTS,
+                ))
+            });
+            // The filters hold anything past this where it is so show that on the knob
+            freq_knob.set_usable_limit(
+                freq_param.preview_normalized(sample_rate * biquad_filters::MAX_FREQ_RATIO),
+            );
+            freq_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+            freq_knob.set_fill_color(ACCENT);
+            freq_knob.set_line_color(MAIN);
+            freq_knob.set_show_label(true);
+            freq_knob.set_text_size(10.0);
+            freq_knob.set_touch_flag(touched);
+            let freq_response = ui.add(freq_knob);
+
+            let mut res_knob = ui_knob::ArcKnob::for_param(res_param, setter, knob_size);
+            res_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
             res_knob.set_fill_color(ACCENT);
             res_knob.set_line_color(MAIN);
             res_knob.set_show_label(true);
+            match res_display {
+                RES_DISPLAY_OCTAVES => {
+                    res_knob.set_label(format!("{:.2} oct", biquad_filters::q_to_octaves(res_param.value())));
+                }
+                RES_DISPLAY_HZ => {
+                    res_knob.set_label(formatters::v2s_f32_hz_then_khz(1)(
+                        biquad_filters::q_to_bandwidth_hz(freq_param.value(), res_param.value(), sample_rate),
+                    ));
+                }
+                _ => {}
+            }
             res_knob.set_text_size(10.0);
-            ui.add(res_knob);
-        });
+            res_knob.set_touch_flag(touched);
+            let res_response = ui.add(res_knob);
+            // What the band really runs at once Q Scale, morphing and soft Q are through with it
+            ui.label(RichText::new(format!("Q {effective_q:.2}")).size(9.0).color(LIGHT))
+                .on_hover_text("Effective Q after Q Scale, Morph, the Suppressor and Soft Q");
+
+            // Holding Ctrl/Cmd on any of the knobs bypasses the band until let go
+            ui.input(|i| i.modifiers.command)
+                && [type_response, freq_response, res_response]
+                    .iter()
+                    .any(|response| response.is_pointer_button_down_on())
+        })
+        .inner
+    }
+}
+
+// Soft clips what a shelf adds or takes away, the dry part it leaves alone stays clean. Small
+// changes pass nearly untouched so quiet material keeps the linear shelf's response.
+fn shelf_drive(contribution: f32, drive: f32) -> f32 {
+    let amount = 1.0 + drive * SHELF_DRIVE_MAX;
+    (contribution * amount).tanh() / amount
+}
+
+// Runs a sample through the bands. Multiband runs a copy of the EQ on each crossover region
+// with its own interleave, otherwise the first EQ gets the main interleave.
+// This is shared by process and offline rendering so they always sound the same.
+// Holding the settings runs the EQ as it was last set, for the bulk change crossfade.
+#[allow(clippy::too_many_arguments)]
+fn run_bands(
+    eq: &mut [EQ; crossover::MAX_REGIONS],
+    multiband: &mut crossover::MultibandSplit,
+    params: &InterleafParams,
+    sr: f32,
+    in_l: f32,
+    in_r: f32,
+    interleave: f32,
+    region_interleaves: [f32; crossover::MAX_REGIONS],
+    oversampling: usize,
+    bypass_mask: u32,
+    band_settings: &BandSettings,
+    hold_settings: bool,
+) -> (f32, f32) {
+    let regions = params.regions.value() as usize;
+    let blend_curve = params.interleave_curve.value();
+    let fade_step = 1000.0 / (BAND_BYPASS_FADE_MS * sr);
+    if regions > 1 {
+        if !hold_settings {
+            multiband.update(sr, params.crossover_low.value(), params.crossover_high.value());
+        }
+        let split = multiband.split(in_l, in_r, regions);
+        let mut processed_sample_l: f32 = 0.0;
+        let mut processed_sample_r: f32 = 0.0;
+        for (region, (region_l, region_r)) in split.iter().take(regions).enumerate() {
+            // Two regions use the low and high settings
+            let interleave_index = if regions == 2 && region == 1 { 2 } else { region };
+            let region_interleave = region_interleaves[interleave_index];
+            if !hold_settings {
+                eq[region].set_bands(params, band_settings, sr, region_interleave, oversampling);
+                eq[region].update_band_mix(bypass_mask, fade_step);
+            }
+            let (out_l, out_r) =
+                eq[region].process(*region_l, *region_r, region_interleave, oversampling, blend_curve);
+            processed_sample_l += out_l;
+            processed_sample_r += out_r;
+        }
+        (processed_sample_l, processed_sample_r)
+    } else {
+        if !hold_settings {
+            eq[0].set_bands(params, band_settings, sr, interleave, oversampling);
+            eq[0].update_band_mix(bypass_mask, fade_step);
+        }
+        eq[0].process(in_l, in_r, interleave, oversampling, blend_curve)
     }
 }
 
@@ -466,11 +2402,22 @@ impl Plugin for Interleaf {
         AudioIOLayout {
             main_input_channels: NonZeroU32::new(2),
             main_output_channels: NonZeroU32::new(2),
+            // Reference for match EQ
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {
+                aux_inputs: &["Reference"],
+                ..PortNames::const_default()
+            },
             ..AudioIOLayout::const_default()
         },
         AudioIOLayout {
             main_input_channels: NonZeroU32::new(1),
             main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            names: PortNames {
+                aux_inputs: &["Reference"],
+                ..PortNames::const_default()
+            },
             ..AudioIOLayout::const_default()
         },
     ];
@@ -478,7 +2425,7 @@ impl Plugin for Interleaf {
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
     type SysExMessage = ();
-    type BackgroundTask = ();
+    type BackgroundTask = analyzer::AnalyzerTask;
 
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
@@ -488,11 +2435,67 @@ impl Plugin for Interleaf {
         let params = self.params.clone();
         let in_meter = self.in_meter.clone();
         let out_meter = self.out_meter.clone();
+        let in_meter_peak = self.in_meter_peak.clone();
+        let out_meter_peak = self.out_meter_peak.clone();
+        let in_vu = self.in_vu.clone();
+        let out_vu = self.out_vu.clone();
+        let balance_meter = self.balance_meter.clone();
+        let limiter_meter = self.limiter_meter.clone();
+        let quality_meter = self.quality_meter.clone();
+        let analyzer_bins = self.analyzer_bins.clone();
+        let analyzer_bins_second = self.analyzer_bins_second.clone();
+        let analyzer_difference = self.analyzer_difference.clone();
+        let analyzer_held = self.analyzer_held.clone();
+        let spectrogram = self.spectrogram.clone();
+        let peak_frequency = self.peak_frequency.clone();
+        let listen_band = self.listen_band.clone();
+        let solo_region = self.solo_region.clone();
+        let solo_low = self.solo_low.clone();
+        let solo_high = self.solo_high.clone();
+        let hunt_freq = self.hunt_freq.clone();
+        let effective_q = self.effective_q.clone();
+        let test_noise = self.test_noise.clone();
+        let held_bypass = self.held_bypass.clone();
+        let match_state = self.match_state.clone();
+        let trim_state = self.trim_state.clone();
+        let trim_gain = self.trim_gain.clone();
+        let match_difference = self.match_difference.clone();
+        let sample_rate = self.sample_rate.clone();
         create_egui_editor(
             self.params.editor_state.clone(),
-            (),
+            EditorState {
+                random_seed: 1,
+                random_slight: false,
+                random_musical: true,
+                null_result: String::new(),
+                trim_result: String::new(),
+                svg_result: String::new(),
+                settings_text: String::new(),
+                settings_result: String::new(),
+                pin_controls: false,
+                requested_height: self.params.editor_state.size().1,
+                dragged_column: None,
+                spectrogram_texture: None,
+                hunt_freq: 1000.0,
+                hunt_best: None,
+                hunt_band: 0,
+                hunt_result: String::new(),
+                knob_listening: false,
+                focused_band: None,
+                degenerate_logged: 0,
+                linked_res: None,
+            },
             |_, _| {},
-            move |egui_ctx, setter, _state| {
+            move |egui_ctx, setter, state| {
+                // Process can't log so degenerate band settings get reported from here
+                let degenerate = biquad_filters::DEGENERATE_BIQUADS.load(std::sync::atomic::Ordering::Relaxed);
+                if degenerate != state.degenerate_logged {
+                    nih_warn!(
+                        "{} biquads got degenerate settings and are passing through instead",
+                        degenerate.wrapping_sub(state.degenerate_logged)
+                    );
+                    state.degenerate_logged = degenerate;
+                }
                 egui::CentralPanel::default().show(egui_ctx, |ui| {
                     // Assign default colors
                     ui.style_mut().visuals.widgets.inactive.bg_stroke.color = BLACK;
@@ -524,15 +2527,63 @@ impl Plugin for Interleaf {
 
                     // GUI Structure
                     ui.vertical(|ui| {
-                        // Spacing :)
-                        ui.label(
-                            RichText::new(" Interleaf - Interleaving EQ")
-                                .font(FontId::proportional(14.0))
-                                .color(LIGHT),
-                        )
-                        .on_hover_text("by Ardura!");
+                        ui.horizontal(|ui| {
+                            // Spacing :)
+                            ui.label(
+                                RichText::new(" Interleaf - Interleaving EQ")
+                                    .font(FontId::proportional(14.0))
+                                    .color(LIGHT),
+                            )
+                            .on_hover_text("by Ardura!");
+
+                            let mut instance_label = params.instance_label.lock().unwrap();
+                            ui.add(
+                                egui::TextEdit::singleline(&mut *instance_label)
+                                    .hint_text("Instance name")
+                                    .desired_width(140.0)
+                                    .text_color(ACCENT),
+                            );
+                            drop(instance_label);
+
+                            let mut compact = params.compact.load(std::sync::atomic::Ordering::Relaxed);
+                            if ui.checkbox(&mut compact, "Compact").changed() {
+                                params
+                                    .compact
+                                    .store(compact, std::sync::atomic::Ordering::Relaxed);
+                            }
+
+                            let mut economy = params.economy.load(std::sync::atomic::Ordering::Relaxed);
+                            if ui
+                                .checkbox(&mut economy, "Economy")
+                                .on_hover_text("Save CPU with many instances: caps interleave at 2, skips x2 and turns off the analyzer, spectrogram and phase curve")
+                                .changed()
+                            {
+                                params
+                                    .economy
+                                    .store(economy, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            if economy {
+                                ui.label(RichText::new("ECONOMY").color(ACCENT));
+                            }
+
+                            // Never saved and only while the editor is open so it can't end up in a mix
+                            let mut noise = test_noise.load(std::sync::atomic::Ordering::Relaxed);
+                            if ui
+                                .checkbox(&mut noise, "Test Noise")
+                                .on_hover_text("Replace the input with pink noise while this window is open")
+                                .changed()
+                            {
+                                test_noise.store(noise, std::sync::atomic::Ordering::Relaxed);
+                            }
+                            if noise {
+                                ui.label(RichText::new("INPUT REPLACED").color(ACCENT));
+                            }
+                        });
 
                         // Peak Meters
+                        let meters_vertical = params
+                            .meters_vertical
+                            .load(std::sync::atomic::Ordering::Relaxed);
                         let in_meter =
                             util::gain_to_db(in_meter.load(std::sync::atomic::Ordering::Relaxed));
                         let in_meter_text = if in_meter > util::MINUS_INFINITY_DB {
@@ -541,144 +2592,1206 @@ impl Plugin for Interleaf {
                             String::from("-inf dBFS Input")
                         };
                         let in_meter_normalized = (in_meter + 60.0) / 60.0;
-                        ui.allocate_space(egui::Vec2::splat(2.0));
-                        let mut in_meter_obj =
-                            db_meter::DBMeter::new(in_meter_normalized).text(in_meter_text);
-                        in_meter_obj.set_background_color(BLACK);
-                        in_meter_obj.set_bar_color(LIGHT);
-                        in_meter_obj.set_border_color(MAIN);
-                        ui.add(in_meter_obj);
+                        let in_peak_normalized = (util::gain_to_db(
+                            in_meter_peak.load(std::sync::atomic::Ordering::Relaxed),
+                        ) + 60.0)
+                            / 60.0;
+                        let in_meter_obj = Self::create_meter(
+                            in_meter_normalized,
+                            in_peak_normalized,
+                            in_meter_text,
+                            LIGHT,
+                            meters_vertical,
+                        );
 
                         let out_meter =
                             util::gain_to_db(out_meter.load(std::sync::atomic::Ordering::Relaxed));
+                        // Clip shows while the held peak is over full scale. That's the plugin's output
+                        // normally, or the EQ before the output gain and limiter with Meter Pre Out.
+                        let out_peak = out_meter_peak.load(std::sync::atomic::Ordering::Relaxed);
+                        let pre_output = params.meter_pre_output.load(std::sync::atomic::Ordering::Relaxed);
+                        let clip_text = if out_peak >= 1.0 {
+                            match (params.true_peak_meter.load(std::sync::atomic::Ordering::Relaxed), pre_output) {
+                                (true, true) => " - CLIP (TP, Pre)",
+                                (true, false) => " - CLIP (TP)",
+                                (false, true) => " - CLIP (Pre)",
+                                (false, false) => " - CLIP",
+                            }
+                        } else {
+                            ""
+                        };
+                        let out_name = if pre_output { "Pre Out" } else { "Output" };
                         let out_meter_text = if out_meter > util::MINUS_INFINITY_DB {
-                            format!("{out_meter:.1} dBFS Output")
+                            format!("{out_meter:.1} dBFS {out_name}{clip_text}")
                         } else {
-                            String::from("-inf dBFS Output")
+                            format!("-inf dBFS {out_name}{clip_text}")
                         };
                         let out_meter_normalized = (out_meter + 60.0) / 60.0;
+                        let out_peak_normalized = (util::gain_to_db(out_peak) + 60.0) / 60.0;
+                        let out_meter_obj = Self::create_meter(
+                            out_meter_normalized,
+                            out_peak_normalized,
+                            out_meter_text,
+                            ACCENT,
+                            meters_vertical,
+                        );
+
+                        // Vertical meters get drawn alongside the bands instead
+                        let mut vertical_meters = None;
+                        if params.needle_meters.load(std::sync::atomic::Ordering::Relaxed) {
+                            ui.allocate_space(egui::Vec2::splat(2.0));
+                            ui.horizontal(|ui| {
+                                for (level, label) in [(&in_vu, "Input"), (&out_vu, "Output")] {
+                                    let vu = util::gain_to_db(
+                                        level.load(std::sync::atomic::Ordering::Relaxed) * VU_FORM_FACTOR,
+                                    ) - VU_REFERENCE_DBFS;
+                                    let vu_text = if vu > vu_meter::VU_MIN {
+                                        format!("{vu:+.1} VU {label}")
+                                    } else {
+                                        format!("-inf VU {label}")
+                                    };
+                                    let mut needle = vu_meter::VuMeter::new(vu)
+                                        .desired_width(WIDTH as f32 / 2.0 - 12.0)
+                                        .text(vu_text);
+                                    needle.set_background_color(BLACK);
+                                    needle.set_border_color(MAIN);
+                                    needle.set_needle_color(LIGHT);
+                                    ui.add(needle);
+                                }
+                            });
+                        } else if meters_vertical {
+                            vertical_meters = Some((in_meter_obj, out_meter_obj));
+                        } else {
+                            ui.allocate_space(egui::Vec2::splat(2.0));
+                            ui.add(in_meter_obj);
+                            ui.allocate_space(egui::Vec2::splat(2.0));
+                            ui.add(out_meter_obj);
+                        }
+
+                        // Spectral balance - held at the center during silence
+                        let balance = balance_meter.load(std::sync::atomic::Ordering::Relaxed);
+                        let balance_text = if out_meter > BALANCE_SILENCE_DB {
+                            let lean = if balance > 1.0 {
+                                "Bright"
+                            } else if balance < -1.0 {
+                                "Dark"
+                            } else {
+                                "Balanced"
+                            };
+                            format!("{balance:+.1} dB {lean}")
+                        } else {
+                            String::from("Balance: Silence")
+                        };
+                        ui.allocate_space(egui::Vec2::splat(2.0));
+                        let mut balance_meter_obj =
+                            balance_meter::BalanceMeter::new(balance / BALANCE_RANGE_DB)
+                                .text(balance_text);
+                        balance_meter_obj.set_background_color(BLACK);
+                        balance_meter_obj.set_bar_color(MAIN);
+                        balance_meter_obj.set_border_color(LIGHT);
+                        ui.add(balance_meter_obj);
+
+                        // Limiter gain reduction
+                        let reduction = limiter_meter.load(std::sync::atomic::Ordering::Relaxed);
                         ui.allocate_space(egui::Vec2::splat(2.0));
-                        let mut out_meter_obj =
-                            db_meter::DBMeter::new(out_meter_normalized).text(out_meter_text);
-                        out_meter_obj.set_background_color(BLACK);
-                        out_meter_obj.set_bar_color(ACCENT);
-                        out_meter_obj.set_border_color(MAIN);
-                        ui.add(out_meter_obj);
+                        let mut reduction_obj =
+                            db_meter::DBMeter::new(-reduction / LIMITER_METER_RANGE_DB)
+                                .text(format!("{reduction:.1} dB Limiter"));
+                        reduction_obj.set_background_color(BLACK);
+                        reduction_obj.set_bar_color(MAIN);
+                        reduction_obj.set_border_color(MAIN);
+                        ui.add(reduction_obj);
+
+                        // Loudest input frequency, economy mode stops the analyzer so it would be stale
+                        let peak = peak_frequency.load(std::sync::atomic::Ordering::Relaxed);
+                        let peak_text = if peak > 0.0 && !params.economy.load(std::sync::atomic::Ordering::Relaxed) {
+                            format!("Peak: {}", formatters::v2s_f32_hz_then_khz(2)(peak))
+                        } else {
+                            String::from("Peak: --")
+                        };
+                        ui.label(RichText::new(peak_text).color(LIGHT));
+
+                        // Bands held bypassed this frame by Ctrl/Cmd clicking their node or knobs
+                        let held_bands = std::cell::Cell::new(0u32);
+                        // Band the type cycle key goes to, the graph and band strip move it
+                        let focused_band = std::cell::Cell::new(state.focused_band);
+
+                        // Response graph over the input spectrum, economy mode leaves only the curve
+                        let economy = params.economy.load(std::sync::atomic::Ordering::Relaxed);
+                        let analyzer_mode = params.analyzer_mode.load(std::sync::atomic::Ordering::Relaxed);
+                        let analyzer_spectrogram = !economy
+                            && params
+                                .analyzer_spectrogram
+                                .load(std::sync::atomic::Ordering::Relaxed);
+                        let graph_range = params.graph_min_freq.load(std::sync::atomic::Ordering::Relaxed)
+                            ..=params.graph_max_freq.load(std::sync::atomic::Ordering::Relaxed);
+                        let bins = analyzer_bins.lock().unwrap();
+                        let bins_second = analyzer_bins_second.lock().unwrap();
+                        let difference = analyzer_difference.lock().unwrap();
+                        let held = analyzer_held.lock().unwrap();
+                        let mut graph = response_graph::ResponseGraph::new(
+                            &params,
+                            setter,
+                            sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                            egui::vec2(WIDTH as f32 - 16.0, GRAPH_HEIGHT),
+                        )
+                        .with_freq_range(graph_range.clone())
+                        .with_db_range(params.graph_db_range.load(std::sync::atomic::Ordering::Relaxed))
+                        .with_held_bypass(&held_bands)
+                        .with_band_focus(&focused_band);
+                        if params.graph_auto_db.load(std::sync::atomic::Ordering::Relaxed) {
+                            graph = graph.with_auto_db_range();
+                        }
+                        if !economy && params.graph_phase.load(std::sync::atomic::Ordering::Relaxed) {
+                            graph = graph.with_phase();
+                        }
+                        if params.graph_note_grid.load(std::sync::atomic::Ordering::Relaxed) {
+                            graph = graph.with_note_grid();
+                        }
+                        if !economy && params.analyzer_threshold.load(std::sync::atomic::Ordering::Relaxed) {
+                            graph = graph.with_threshold(&params.analyzer_threshold_level);
+                        }
+                        if !economy {
+                            graph = graph.with_gain_target(&bins);
+                        }
+                        if !economy && !analyzer_spectrogram {
+                            if params.analyzer_hold.load(std::sync::atomic::Ordering::Relaxed) {
+                                graph = graph.with_held_spectrum(&held);
+                            }
+                            graph = graph.with_spectrum(&bins);
+                            if analyzer_mode == analyzer::ANALYZER_DIFF {
+                                graph = graph.with_difference(&difference);
+                            } else if analyzer_mode != analyzer::ANALYZER_SUM {
+                                graph = graph.with_second_spectrum(&bins_second);
+                            }
+                        }
+                        let analyzer_zoom = !economy && params.analyzer_zoom.load(std::sync::atomic::Ordering::Relaxed);
+                        if analyzer_zoom {
+                            graph = graph.with_zoom_select(&params.zoom_low, &params.zoom_high);
+                        }
+                        if params.auto_listen.load(std::sync::atomic::Ordering::Relaxed) {
+                            graph = graph.with_auto_listen(&listen_band);
+                        }
+                        graph.set_background_color(BLACK);
+                        graph.set_grid_color(MAIN);
+                        graph.set_line_color(LIGHT);
+                        graph.set_node_color(ACCENT);
+                        graph.set_spectrum_color(ACCENT);
+                        graph.set_second_spectrum_color(MAIN);
+                        graph.set_difference_color(MAIN);
+                        graph.set_held_color(LIGHT);
+                        graph.set_phase_color(MAIN);
+                        ui.add(graph);
+                        if analyzer_spectrogram {
+                            let history = spectrogram.lock().unwrap();
+                            let mut waterfall = spectrogram::SpectrogramView::new(
+                                &history,
+                                &mut state.spectrogram_texture,
+                                sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                                graph_range,
+                                egui::vec2(WIDTH as f32 - 16.0, SPECTROGRAM_HEIGHT),
+                            );
+                            waterfall.set_background_color(BLACK);
+                            waterfall.set_heat_colors(ACCENT, LIGHT);
+                            waterfall.set_grid_color(MAIN);
+                            ui.add(waterfall);
+                        }
+                        if analyzer_zoom {
+                            let mut zoom = zoom_view::ZoomView::new(
+                                &bins,
+                                sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                                params.zoom_low.load(std::sync::atomic::Ordering::Relaxed),
+                                params.zoom_high.load(std::sync::atomic::Ordering::Relaxed),
+                                egui::vec2(WIDTH as f32 - 16.0, ZOOM_HEIGHT),
+                            );
+                            zoom.set_background_color(BLACK);
+                            zoom.set_grid_color(MAIN);
+                            zoom.set_line_color(ACCENT);
+                            ui.add(zoom);
+                        }
+                        drop(bins);
+                        drop(bins_second);
+                        drop(difference);
+                        drop(held);
 
                         ui.separator();
 
-                        // UI Control area
-                        egui::scroll_area::ScrollArea::horizontal()
-                            .auto_shrink([true; 2])
-                            .show(ui, |ui| {
-                                ui.vertical(|ui|{
-                                    ui.horizontal(|ui| {
-                                        // Draw our band UI
-                                        Self::create_band_gui(
-                                            ui,
-                                            &params.type_0,
-                                            &params.freq_band_0,
-                                            &params.gain_band_0,
-                                            &params.res_band_0,
-                                            setter,
-                                            VERT_BAR_WIDTH,
-                                        );
-                                        Self::create_band_gui(
-                                            ui,
-                                            &params.type_1,
-                                            &params.freq_band_1,
-                                            &params.gain_band_1,
-                                            &params.res_band_1,
-                                            setter,
-                                            VERT_BAR_WIDTH,
-                                        );
-                                        Self::create_band_gui(
-                                            ui,
-                                            &params.type_2,
-                                            &params.freq_band_2,
-                                            &params.gain_band_2,
-                                            &params.res_band_2,
-                                            setter,
-                                            VERT_BAR_WIDTH,
-                                        );
-                                        Self::create_band_gui(
-                                            ui,
-                                            &params.type_3,
-                                            &params.freq_band_3,
-                                            &params.gain_band_3,
-                                            &params.res_band_3,
-                                            setter,
-                                            VERT_BAR_WIDTH,
-                                        );
-                                        Self::create_band_gui(
-                                            ui,
-                                            &params.type_4,
-                                            &params.freq_band_4,
-                                            &params.gain_band_4,
-                                            &params.res_band_4,
-                                            setter,
-                                            VERT_BAR_WIDTH,
-                                        );
-                                    });
-                                    // Bottom controls
-                                    ui.horizontal(|ui| {
-                                        let mut os_knob = ui_knob::ArcKnob::for_param(
-                                            &params.oversampling,
-                                            setter,
-                                            VERT_BAR_WIDTH - 4.0,
-                                        );
-                                        os_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
-                                        os_knob.set_text_size(12.0);
-                                        os_knob.set_fill_color(ACCENT);
-                                        os_knob.set_line_color(LIGHT);
-                                        ui.add(os_knob);
+                        // Apply a finished trim measurement every frame, the controls can be hidden
+                        // in compact mode while it measures
+                        let current_trim = trim_state.load(std::sync::atomic::Ordering::Relaxed);
+                        if current_trim == loudness_trim::TRIM_READY {
+                            let gain = trim_gain.load(std::sync::atomic::Ordering::Relaxed);
+                            let output_gain = &params.output_gain;
+                            // Past the output range it's as close as the knob goes
+                            let gain = output_gain.preview_plain(output_gain.preview_normalized(gain));
+                            setter.begin_set_parameter(output_gain);
+                            setter.set_parameter(output_gain, gain);
+                            setter.end_set_parameter(output_gain);
+                            state.trim_result = format!("Output set to {:+.1} dB", util::gain_to_db(gain));
+                            trim_state.store(loudness_trim::TRIM_IDLE, std::sync::atomic::Ordering::Relaxed);
+                        } else if current_trim == loudness_trim::TRIM_TOO_QUIET {
+                            state.trim_result = String::from("Not enough signal, nothing changed");
+                            trim_state.store(loudness_trim::TRIM_IDLE, std::sync::atomic::Ordering::Relaxed);
+                        }
+
+                        // Compact mode hides the controls below until hovered or pinned
+                        let compact = params.compact.load(std::sync::atomic::Ordering::Relaxed);
+                        let show_controls = !compact
+                            || state.pin_controls
+                            || ui.rect_contains_pointer(ui.available_rect_before_wrap());
+                        if compact {
+                            ui.horizontal(|ui| {
+                                ui.label("Hover here to show the bands");
+                                ui.checkbox(&mut state.pin_controls, "Pin");
+                            });
+                        }
+                        // The editor's size lives in its persisted state, that's what the host
+                        // reads back when we ask it to resize
+                        let height = if show_controls {
+                            HEIGHT
+                        } else {
+                            (ui.cursor().top() + 8.0).ceil() as u32
+                        };
+                        if height != state.requested_height {
+                            state.requested_height = height;
+                            if let Ok(size) = Arc::try_unwrap(EguiState::from_size(WIDTH, height)) {
+                                params.editor_state.set(size);
+                                setter.raw_context.request_resize();
+                            }
+                        }
+
+                        if show_controls {
+                            // UI Control area
+                            egui::scroll_area::ScrollArea::both()
+                                .auto_shrink([true; 2])
+                                .show(ui, |ui| {
+                                    ui.vertical(|ui|{
+                                        let gain_snap_db = params
+                                            .gain_snap
+                                            .load(std::sync::atomic::Ordering::Relaxed);
+                                        let current_sample_rate =
+                                            sample_rate.load(std::sync::atomic::Ordering::Relaxed);
+                                        let res_display = params
+                                            .res_display
+                                            .load(std::sync::atomic::Ordering::Relaxed);
+                                        ui.horizontal(|ui| {
+                                            // Draw our band UI in the display order, each column still drives its
+                                            // own band. Processing stays in band order.
+                                            let mut order = *params.band_order.lock().unwrap();
+                                            let mut sorted = order;
+                                            sorted.sort_unstable();
+                                            if sorted != [0, 1, 2, 3, 4] {
+                                                order = [0, 1, 2, 3, 4];
+                                            }
+                                            let bands = params.bands();
+                                            // Level before this frame's type changes, only worked out when compensating
+                                            let types_before = bands.map(|(type_param, _, _, _)| type_param.value());
+                                            let res_before = bands.map(|(_, _, _, res_param)| res_param.value());
+                                            let level_before = if params
+                                                .type_compensation
+                                                .load(std::sync::atomic::Ordering::Relaxed)
+                                            {
+                                                Some(params.broadband_level_db(current_sample_rate))
+                                            } else {
+                                                None
+                                            };
+                                            let mut column_rects = [Rect::NOTHING; 5];
+                                            let mut touched_band = None;
+                                            for (position, band) in order.iter().enumerate() {
+                                                let touched = std::cell::Cell::new(false);
+                                                let (type_param, freq_param, gain_param, res_param) = bands[*band];
+                                                let column = ui.vertical(|ui| {
+                                                    let header = ui
+                                                        .add(
+                                                            egui::Label::new(RichText::new(format!("Band {band}")).color(
+                                                                if state.focused_band == Some(*band) { ACCENT } else { LIGHT },
+                                                            ))
+                                                                .sense(egui::Sense::click_and_drag()),
+                                                        )
+                                                        .on_hover_text("Drag onto another band to move this column, right click to reset it or set its gain from the analyzer")
+                                                        .context_menu(|ui| {
+                                                            let bins = analyzer_bins.lock().unwrap();
+                                                            response_graph::band_menu(
+                                                                ui,
+                                                                &params,
+                                                                setter,
+                                                                *band,
+                                                                (!economy).then_some(&bins[..]),
+                                                                current_sample_rate,
+                                                            );
+                                                        });
+                                                    if header.drag_started() {
+                                                        state.dragged_column = Some(position);
+                                                    }
+                                                    Self::create_band_gui(
+                                                        ui,
+                                                        type_param,
+                                                        freq_param,
+                                                        gain_param,
+                                                        res_param,
+                                                        setter,
+                                                        VERT_BAR_WIDTH,
+                                                        gain_snap_db,
+                                                        current_sample_rate,
+                                                        res_display,
+                                                        effective_q[*band].load(std::sync::atomic::Ordering::Relaxed),
+                                                        &touched,
+                                                    )
+                                                });
+                                                column_rects[position] = column.response.rect;
+                                                if touched.get() {
+                                                    touched_band = Some(*band);
+                                                }
+                                                if touched.get() || column.response.hovered() {
+                                                    focused_band.set(Some(*band));
+                                                }
+                                                if column.inner {
+                                                    held_bands.set(held_bands.get() | 1 << band);
+                                                }
+                                            }
+                                            // Knob Listen auditions whichever band's controls are held, after the graph so it wins
+                                            if params.knob_listen.load(std::sync::atomic::Ordering::Relaxed) {
+                                                if let Some(band) = touched_band {
+                                                    listen_band.store(band as i32, std::sync::atomic::Ordering::Relaxed);
+                                                    state.knob_listening = true;
+                                                } else if state.knob_listening {
+                                                    listen_band.store(NO_LISTEN, std::sync::atomic::Ordering::Relaxed);
+                                                    state.knob_listening = false;
+                                                }
+                                            }
+                                            // With Link Q a Res knob that moved takes every other band along. Their
+                                            // gestures stay open until that band's column is let go.
+                                            if params.link_q.load(std::sync::atomic::Ordering::Relaxed) {
+                                                if let Some((band, (_, _, _, res_param))) = bands
+                                                    .iter()
+                                                    .enumerate()
+                                                    .find(|(band, (_, _, _, res_param))| res_param.value() != res_before[*band])
+                                                {
+                                                    let leading = *state.linked_res.get_or_insert_with(|| {
+                                                        params.begin_link_res(setter, band);
+                                                        band
+                                                    });
+                                                    params.link_res(setter, leading, res_param.value());
+                                                }
+                                            }
+                                            if let Some(band) = state.linked_res {
+                                                if touched_band != Some(band) {
+                                                    params.end_link_res(setter, band);
+                                                    state.linked_res = None;
+                                                }
+                                            }
+                                            // A type knob moved, make up the broadband level difference on the output
+                                            if let Some(level_before) = level_before {
+                                                if bands.map(|(type_param, _, _, _)| type_param.value()) != types_before {
+                                                    params.compensate_type_change(setter, level_before, current_sample_rate);
+                                                }
+                                            }
+                                            // Dropping a column header moves that column to where it was let go
+                                            if let Some(from) = state.dragged_column {
+                                                if ui.input(|i| i.pointer.any_released()) {
+                                                    let pointer = ui.input(|i| i.pointer.interact_pos());
+                                                    let target = pointer.and_then(|pointer| {
+                                                        column_rects
+                                                            .iter()
+                                                            .position(|rect| pointer.x >= rect.left() && pointer.x <= rect.right())
+                                                    });
+                                                    if let Some(to) = target {
+                                                        if from < to {
+                                                            order[from..=to].rotate_left(1);
+                                                        } else {
+                                                            order[to..=from].rotate_right(1);
+                                                        }
+                                                        *params.band_order.lock().unwrap() = order;
+                                                    }
+                                                    state.dragged_column = None;
+                                                }
+                                            }
+                                            if let Some((in_meter_obj, out_meter_obj)) = vertical_meters {
+                                                ui.add(in_meter_obj);
+                                                ui.add(out_meter_obj);
+                                            }
+                                        });
+                                        // Bottom controls
+                                        ui.horizontal(|ui| {
+                                            let mut os_knob = ui_knob::ArcKnob::for_param(
+                                                &params.oversampling,
+                                                setter,
+                                                VERT_BAR_WIDTH - 4.0,
+                                            );
+                                            os_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+                                            os_knob.set_text_size(12.0);
+                                            os_knob.set_fill_color(ACCENT);
+                                            os_knob.set_line_color(LIGHT);
+                                            ui.add(os_knob);
+                                            Self::create_toggle(ui, &params.os_makeup, setter, "Makeup");
+                                            Self::create_toggle(ui, &params.os_auto, setter, "Auto");
+                                            Self::create_small_knob(ui, &params.os_auto_freq, setter);
+                                            Self::create_small_knob(ui, &params.q_scale, setter);
+                                            Self::create_toggle(ui, &params.soft_q, setter, "Soft Q");
+                                            let mut link_q = params.link_q.load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut link_q, "Link Q")
+                                                .on_hover_text("Moving any band's Res sets all of them to match, unlinking leaves each where it is")
+                                                .changed()
+                                            {
+                                                params.link_q.store(link_q, std::sync::atomic::Ordering::Relaxed);
+                                            }
             
-                                        let mut interleave_knob = ui_knob::ArcKnob::for_param(
-                                            &params.interleaves,
-                                            setter,
-                                            VERT_BAR_WIDTH - 4.0,
-                                        );
-                                        interleave_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
-                                        interleave_knob.set_text_size(8.0);
-                                        interleave_knob.set_fill_color(ACCENT);
-                                        interleave_knob.set_line_color(LIGHT);
-                                        ui.add(interleave_knob);
+                                            let mut interleave_knob = ui_knob::ArcKnob::for_param(
+                                                &params.interleaves,
+                                                setter,
+                                                VERT_BAR_WIDTH - 4.0,
+                                            );
+                                            interleave_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+                                            interleave_knob.set_text_size(8.0);
+                                            interleave_knob.set_fill_color(ACCENT);
+                                            interleave_knob.set_line_color(LIGHT);
+                                            ui.add(interleave_knob);
+                                            Self::create_small_knob(ui, &params.interleave_curve, setter);
             
-                                        let mut gain_knob = ui_knob::ArcKnob::for_param(
-                                            &params.input_gain,
-                                            setter,
-                                            VERT_BAR_WIDTH - 4.0,
-                                        );
-                                        gain_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
-                                        gain_knob.set_text_size(10.0);
-                                        gain_knob.set_fill_color(ACCENT);
-                                        gain_knob.set_line_color(LIGHT);
-                                        ui.add(gain_knob);
+                                            let mut gain_knob = ui_knob::ArcKnob::for_param(
+                                                &params.input_gain,
+                                                setter,
+                                                VERT_BAR_WIDTH - 4.0,
+                                            );
+                                            gain_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+                                            gain_knob.set_text_size(10.0);
+                                            gain_knob.set_fill_color(ACCENT);
+                                            gain_knob.set_line_color(LIGHT);
+                                            ui.add(gain_knob);
             
-                                        let mut output_knob = ui_knob::ArcKnob::for_param(
-                                            &params.output_gain,
-                                            setter,
-                                            VERT_BAR_WIDTH - 4.0,
-                                        );
-                                        output_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
-                                        output_knob.set_text_size(10.0);
-                                        output_knob.set_fill_color(ACCENT);
-                                        output_knob.set_line_color(LIGHT);
-                                        ui.add(output_knob);
+                                            let mut output_knob = ui_knob::ArcKnob::for_param(
+                                                &params.output_gain,
+                                                setter,
+                                                VERT_BAR_WIDTH - 4.0,
+                                            );
+                                            output_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+                                            output_knob.set_text_size(10.0);
+                                            output_knob.set_fill_color(ACCENT);
+                                            output_knob.set_line_color(LIGHT);
+                                            ui.add(output_knob);
             
-                                        let mut dry_wet_knob = ui_knob::ArcKnob::for_param(
-                                            &params.dry_wet,
-                                            setter,
-                                            VERT_BAR_WIDTH - 4.0,
-                                        );
-                                        dry_wet_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
-                                        dry_wet_knob.set_text_size(10.0);
-                                        dry_wet_knob.set_fill_color(ACCENT);
-                                        dry_wet_knob.set_line_color(LIGHT);
-                                        ui.add(dry_wet_knob);
+                                            let mut dry_wet_knob = ui_knob::ArcKnob::for_param(
+                                                &params.dry_wet,
+                                                setter,
+                                                VERT_BAR_WIDTH - 4.0,
+                                            );
+                                            dry_wet_knob.preset_style(ui_knob::KnobStyle::NewPresets2);
+                                            dry_wet_knob.set_text_size(10.0);
+                                            dry_wet_knob.set_fill_color(ACCENT);
+                                            dry_wet_knob.set_line_color(LIGHT);
+                                            ui.add(dry_wet_knob)
+                                                .on_hover_text("Above 100% exaggerates the EQ and can go past 0 dBFS");
+                                        });
+                                        ui.horizontal(|ui| {
+                                            Self::create_toggle(ui, &params.side_only, setter, "Side EQ");
+                                            Self::create_toggle(ui, &params.auto_quality, setter, "Auto Quality");
+                                            match quality_meter.load(std::sync::atomic::Ordering::Relaxed) {
+                                                0 => {}
+                                                1 => {
+                                                    ui.colored_label(Color32::RED, "CPU: x2 off");
+                                                }
+                                                _ => {
+                                                    ui.colored_label(Color32::RED, "CPU: x2 off, 2 interleaves");
+                                                }
+                                            }
+                                            Self::create_toggle(ui, &params.limiter, setter, "Limiter");
+                                            Self::create_small_knob(ui, &params.limiter_ceiling, setter);
+                                            Self::create_small_knob(ui, &params.limiter_lookahead, setter);
+                                            Self::create_small_knob(ui, &params.limiter_release, setter);
+                                        });
+                                        // Multiband split and per region interleaves
+                                        ui.horizontal(|ui| {
+                                            Self::create_small_knob(ui, &params.regions, setter);
+                                            Self::create_small_knob(ui, &params.crossover_low, setter);
+                                            Self::create_small_knob(ui, &params.crossover_high, setter);
+                                            Self::create_small_knob(ui, &params.interleaves_low, setter);
+                                            Self::create_small_knob(ui, &params.interleaves_mid, setter);
+                                            Self::create_small_knob(ui, &params.interleaves_high, setter);
+                                        });
+                                        // Solo region, hear only the input between the two bounds
+                                        ui.horizontal(|ui| {
+                                            let mut solo = solo_region.load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut solo, "Solo Region")
+                                                .on_hover_text("Hear only this part of the input, ignoring the bands")
+                                                .changed()
+                                            {
+                                                solo_region.store(solo, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                            for bound in [&solo_low, &solo_high] {
+                                                let mut freq = bound.load(std::sync::atomic::Ordering::Relaxed);
+                                                ui.add(
+                                                    egui::DragValue::new(&mut freq)
+                                                        .clamp_range(20.0..=20000.0)
+                                                        .speed(5.0)
+                                                        .suffix(" Hz"),
+                                                );
+                                                bound.store(freq, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                        });
+                                        // Boost, sweep, cut: sweeping a narrow boost makes resonances easy to hear,
+                                        // letting go cuts the spot that stood out most in the input spectrum
+                                        ui.horizontal(|ui| {
+                                            let hunt_response = ui
+                                                .add(
+                                                    egui::Slider::new(&mut state.hunt_freq, 20.0..=20000.0)
+                                                        .logarithmic(true)
+                                                        .suffix(" Hz")
+                                                        .text("Hunt"),
+                                                )
+                                                .on_hover_text("Drag to sweep a narrow boost, letting go cuts the strongest resonance found");
+                                            if hunt_response.dragged() {
+                                                hunt_freq.store(state.hunt_freq, std::sync::atomic::Ordering::Relaxed);
+                                                let prominence = analyzer::prominence_db(
+                                                    &analyzer_bins.lock().unwrap(),
+                                                    current_sample_rate,
+                                                    state.hunt_freq,
+                                                );
+                                                if state.hunt_best.map_or(true, |(_, best)| prominence > best) {
+                                                    state.hunt_best = Some((state.hunt_freq, prominence));
+                                                }
+                                            } else {
+                                                hunt_freq.store(0.0, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                            if hunt_response.drag_released() {
+                                                match state.hunt_best.take() {
+                                                    Some((freq, prominence)) if prominence >= HUNT_MIN_PROMINENCE_DB => {
+                                                        let cut = -prominence.min(HUNT_MAX_CUT_DB);
+                                                        params.set_band(
+                                                            setter,
+                                                            state.hunt_band,
+                                                            FilterType::Peak,
+                                                            freq,
+                                                            cut,
+                                                            HUNT_CUT_Q,
+                                                        );
+                                                        state.hunt_result = format!(
+                                                            "Cut {:.1} dB at {}",
+                                                            cut,
+                                                            formatters::v2s_f32_hz_then_khz(1)(freq)
+                                                        );
+                                                    }
+                                                    _ => {
+                                                        state.hunt_result = String::from("No clear resonance, band left alone");
+                                                    }
+                                                }
+                                            }
+                                            egui::ComboBox::from_id_source("hunt_band")
+                                                .selected_text(format!("Band {}", state.hunt_band))
+                                                .show_ui(ui, |ui| {
+                                                    for band in 0..5 {
+                                                        ui.selectable_value(&mut state.hunt_band, band, format!("Band {band}"));
+                                                    }
+                                                });
+                                            ui.label(&state.hunt_result);
+                                        });
+                                        // Exact values for people who'd rather type
+                                        egui::CollapsingHeader::new("Band Table").show(ui, |ui| {
+                                            Self::create_band_table(ui, &params, setter);
+                                        });
+                                        // Sweep macro for one band between its bounds
+                                        ui.horizontal(|ui| {
+                                            Self::create_small_knob(ui, &params.sweep, setter);
+                                            Self::create_small_knob(ui, &params.sweep_band, setter);
+                                            Self::create_small_knob(ui, &params.sweep_low, setter);
+                                            Self::create_small_knob(ui, &params.sweep_high, setter);
+                                            Self::create_small_knob(ui, &params.sweep_sync, setter);
+                                            Self::create_small_knob(ui, &params.sweep_beats, setter);
+                                        });
+                                        // Morph between two stored band setups
+                                        ui.horizontal(|ui| {
+                                            Self::create_toggle(ui, &params.morph_enabled, setter, "Morph");
+                                            for (label, snapshot) in [("Store A", &params.morph_a), ("Store B", &params.morph_b)] {
+                                                let stored = snapshot.is_stored();
+                                                if ui
+                                                    .button(if stored { format!("{label} *") } else { String::from(label) })
+                                                    .on_hover_text("Keep the bands as they are now, Morph moves between A and B")
+                                                    .clicked()
+                                                {
+                                                    snapshot.store(&morph::capture(&params));
+                                                }
+                                            }
+                                            Self::create_small_knob(ui, &params.morph, setter);
+                                        });
+                                        // Resonance suppressor, a dynamic notch on one band
+                                        ui.horizontal(|ui| {
+                                            Self::create_toggle(ui, &params.suppressor, setter, "Suppressor");
+                                            Self::create_small_knob(ui, &params.suppressor_band, setter);
+                                            Self::create_small_knob(ui, &params.suppressor_strength, setter);
+                                            Self::create_small_knob(ui, &params.suppressor_low, setter);
+                                            Self::create_small_knob(ui, &params.suppressor_high, setter);
+                                            if params.suppressor.value() {
+                                                ui.label(format!(
+                                                    "-{:.1} dB @ {:.0} Hz",
+                                                    params.suppressor_cut.load(std::sync::atomic::Ordering::Relaxed),
+                                                    params.suppressor_freq.load(std::sync::atomic::Ordering::Relaxed),
+                                                ));
+                                            }
+                                        });
+                                        // Where each band's interleave rotation starts
+                                        ui.horizontal(|ui| {
+                                            for offset in params.offsets() {
+                                                Self::create_small_knob(ui, offset, setter);
+                                            }
+                                        });
+                                        // Shelf drive, only the bands that are shelves can use it
+                                        ui.horizontal(|ui| {
+                                            for ((type_param, _, _, _), drive) in params.bands().iter().zip(params.drives()) {
+                                                let is_shelf = matches!(
+                                                    type_param.value(),
+                                                    FilterType::LowShelf | FilterType::HighShelf
+                                                );
+                                                ui.add_enabled_ui(is_shelf, |ui| {
+                                                    Self::create_small_knob(ui, drive, setter);
+                                                });
+                                            }
+                                        });
+                                        // Per band wet for blending a band in parallel with its input
+                                        ui.horizontal(|ui| {
+                                            for wet in params.wets() {
+                                                Self::create_small_knob(ui, wet, setter);
+                                            }
+                                        });
+                                        // Phase compensation, only the bands with a gain can use it
+                                        ui.horizontal(|ui| {
+                                            for ((type_param, _, _, _), phase_comp) in params.bands().iter().zip(params.phase_comps()) {
+                                                ui.add_enabled_ui(type_param.value().uses_gain(), |ui| {
+                                                    Self::create_toggle(ui, phase_comp, setter, "Phase");
+                                                })
+                                                .response
+                                                .on_hover_text("Flattens the band's phase through its passband with allpasses, the magnitude doesn't change");
+                                            }
+                                        });
+                                        // Randomize the bands from the shown seed
+                                        ui.horizontal(|ui| {
+                                            if ui.button("Randomize").clicked() {
+                                                state.random_seed =
+                                                    randomize::Xorshift::new(state.random_seed).next_u32();
+                                                randomize::randomize_bands(
+                                                    &params,
+                                                    setter,
+                                                    state.random_seed,
+                                                    state.random_slight,
+                                                    state.random_musical,
+                                                );
+                                            }
+                                            ui.add(egui::DragValue::new(&mut state.random_seed).prefix("Seed "));
+                                            if ui.button("Apply Seed").clicked() {
+                                                randomize::randomize_bands(
+                                                    &params,
+                                                    setter,
+                                                    state.random_seed,
+                                                    state.random_slight,
+                                                    state.random_musical,
+                                                );
+                                            }
+                                            ui.checkbox(&mut state.random_slight, "Slight");
+                                            ui.checkbox(&mut state.random_musical, "Musical");
+                                        });
+                                        // Match EQ: capture the sidechain reference against the input, then fit the bands
+                                        ui.horizontal(|ui| {
+                                            let current_state = match_state.load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .add_enabled(
+                                                    current_state != match_eq::MATCH_CAPTURING
+                                                        && current_state != match_eq::MATCH_AVERAGING,
+                                                    egui::Button::new("Capture Reference"),
+                                                )
+                                                .on_hover_text("Play the reference into the sidechain and your track into the input")
+                                                .clicked()
+                                            {
+                                                match_state.store(match_eq::MATCH_CAPTURING, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                            if ui
+                                                .add_enabled(current_state == match_eq::MATCH_READY, egui::Button::new("Apply Match"))
+                                                .clicked()
+                                            {
+                                                let difference = match_difference.lock().unwrap();
+                                                match_eq::apply_match(
+                                                    &params,
+                                                    setter,
+                                                    &difference,
+                                                    sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                                                );
+                                            }
+                                            ui.label(match current_state {
+                                                match_eq::MATCH_CAPTURING | match_eq::MATCH_AVERAGING => "Capturing...",
+                                                match_eq::MATCH_READY => "Reference captured",
+                                                match_eq::MATCH_NO_SIDECHAIN => "No signal on the sidechain",
+                                                _ => "",
+                                            });
+                                        });
+                                        // One shot output trim to the input loudness, applied above once process has measured
+                                        ui.horizontal(|ui| {
+                                            let current_state = trim_state.load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .add_enabled(
+                                                    current_state != loudness_trim::TRIM_MEASURING,
+                                                    egui::Button::new("Match Loudness"),
+                                                )
+                                                .on_hover_text("Set the output gain so the output is as loud as the input")
+                                                .clicked()
+                                            {
+                                                trim_state.store(loudness_trim::TRIM_MEASURING, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                            if current_state == loudness_trim::TRIM_MEASURING {
+                                                ui.label("Measuring...");
+                                            } else {
+                                                ui.label(state.trim_result.as_str());
+                                            }
+                                        });
+                                        // Debug: render noise through the current settings and write wet minus dry
+                                        ui.horizontal(|ui| {
+                                            if ui.button("Null Test").clicked() {
+                                                let path = std::env::temp_dir().join("interleaf_null_difference.wav");
+                                                state.null_result = match offline::null_test(
+                                                    &params,
+                                                    sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                                                    &path,
+                                                ) {
+                                                    Ok(peak) if peak > util::MINUS_INFINITY_DB => format!("Peak diff {peak:.1} dB -> {}", path.display()),
+                                                    Ok(_) => format!("Nulls completely -> {}", path.display()),
+                                                    Err(error) => format!("Null test failed: {error}"),
+                                                };
+                                            }
+                                            ui.label(state.null_result.as_str());
+                                        });
+                                        // Save the response curve for sharing
+                                        ui.horizontal(|ui| {
+                                            if ui.button("Export SVG").clicked() {
+                                                if let Some(path) = rfd::FileDialog::new()
+                                                    .add_filter("SVG", &["svg"])
+                                                    .set_file_name("interleaf_response.svg")
+                                                    .save_file()
+                                                {
+                                                    state.svg_result = match svg::write_response_svg(
+                                                        &params,
+                                                        sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                                                        &path,
+                                                    ) {
+                                                        Ok(()) => format!("Saved {}", path.display()),
+                                                        Err(error) => format!("Export failed: {error}"),
+                                                    };
+                                                }
+                                            }
+                                            ui.label(state.svg_result.as_str());
+                                        });
+                                        // Share settings as a line of text
+                                        ui.horizontal(|ui| {
+                                            if ui.button("Copy Settings").clicked() {
+                                                state.settings_text = settings_text::to_text(&params);
+                                                ui.output_mut(|output| output.copied_text = state.settings_text.clone());
+                                                state.settings_result = String::from("Copied to clipboard");
+                                            }
+                                            if ui.button("Paste Settings").clicked() {
+                                                state.settings_result =
+                                                    match settings_text::apply_text(&params, setter, &state.settings_text) {
+                                                        Ok(count) => format!("Applied {count} settings"),
+                                                        Err(error) => error,
+                                                    };
+                                            }
+                                            ui.add(
+                                                egui::TextEdit::singleline(&mut state.settings_text)
+                                                    .hint_text("Paste settings here")
+                                                    .desired_width(120.0),
+                                            );
+                                        });
+                                        ui.label(state.settings_result.as_str());
+                                        // Editor preferences
+                                        ui.horizontal(|ui| {
+                                            let mut vertical = params
+                                                .meters_vertical
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui.checkbox(&mut vertical, "Vertical Meters").changed() {
+                                                params.meters_vertical.store(
+                                                    vertical,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                            }
+
+                                            let mut note_names = params
+                                                .note_names
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui.checkbox(&mut note_names, "Note Names").changed() {
+                                                params.note_names.store(
+                                                    note_names,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                            }
+
+                                            let mut note_numbers = params
+                                                .note_numbers
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut note_numbers, "Note Numbers")
+                                                .on_hover_text("MIDI note number and cents in place of the note name")
+                                                .changed()
+                                            {
+                                                params.note_numbers.store(
+                                                    note_numbers,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                            }
+
+                                            let mut needles = params
+                                                .needle_meters
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui.checkbox(&mut needles, "VU Needles").changed() {
+                                                params.needle_meters.store(
+                                                    needles,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                            }
+
+                                            let mut true_peak = params
+                                                .true_peak_meter
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut true_peak, "True Peak")
+                                                .on_hover_text("Catch overs between samples on the output peak and clip")
+                                                .changed()
+                                            {
+                                                params.true_peak_meter.store(
+                                                    true_peak,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                            }
+
+                                            let mut target_gain = params
+                                                .meter_target_gain
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut target_gain, "Meter Knob Gain")
+                                                .on_hover_text("Meters follow the gain knobs instantly instead of the smoothed gain applied to the audio")
+                                                .changed()
+                                            {
+                                                params.meter_target_gain.store(
+                                                    target_gain,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                            }
+
+                                            let mut pre_output = params
+                                                .meter_pre_output
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut pre_output, "Meter Pre Out")
+                                                .on_hover_text("Output meter and clip read before the output gain and limiter")
+                                                .changed()
+                                            {
+                                                params.meter_pre_output.store(
+                                                    pre_output,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                            }
+
+                                            let mut meter_window = params
+                                                .meter_window
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            ui.add(
+                                                egui::DragValue::new(&mut meter_window)
+                                                    .clamp_range(METER_WINDOW_MIN_MS..=METER_WINDOW_MAX_MS)
+                                                    .speed(5.0)
+                                                    .max_decimals(0)
+                                                    .prefix("Meter ")
+                                                    .suffix(" ms"),
+                                            )
+                                            .on_hover_text("How long the in/out meters average over");
+                                            params
+                                                .meter_window
+                                                .store(meter_window, std::sync::atomic::Ordering::Relaxed);
+
+                                            // Hold Alt while dragging to skip the grid
+                                            let mut snap = params
+                                                .gain_snap
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            egui::ComboBox::from_label("Gain Snap")
+                                                .selected_text(if snap > 0.0 {
+                                                    format!("{snap} dB")
+                                                } else {
+                                                    String::from("Off")
+                                                })
+                                                .show_ui(ui, |ui| {
+                                                    ui.selectable_value(&mut snap, 0.0, "Off");
+                                                    ui.selectable_value(&mut snap, 0.5, "0.5 dB");
+                                                    ui.selectable_value(&mut snap, 1.0, "1 dB");
+                                                });
+                                            params
+                                                .gain_snap
+                                                .store(snap, std::sync::atomic::Ordering::Relaxed);
+
+                                            let mut reset_res = params
+                                                .reset_res
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            ui.add(
+                                                egui::DragValue::new(&mut reset_res)
+                                                    .clamp_range(0.01..=1.0)
+                                                    .speed(0.005)
+                                                    .max_decimals(3)
+                                                    .prefix("Reset Res "),
+                                            )
+                                            .on_hover_text(format!("Res a band goes back to with Reset Band, {DEFAULT_RES} unless changed"));
+                                            params
+                                                .reset_res
+                                                .store(reset_res, std::sync::atomic::Ordering::Relaxed);
+                                        });
+                                        ui.horizontal(|ui| {
+                                            let mut auto_listen = params
+                                                .auto_listen
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut auto_listen, "Auto Listen")
+                                                .on_hover_text("Hear only the band you're dragging on the graph")
+                                                .changed()
+                                            {
+                                                params.auto_listen.store(
+                                                    auto_listen,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                );
+                                                listen_band.store(NO_LISTEN, std::sync::atomic::Ordering::Relaxed);
+                                            }
+
+                                            let mut knob_listen = params
+                                                .knob_listen
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut knob_listen, "Knob Listen")
+                                                .on_hover_text("Hear only the band whose knobs you're holding")
+                                                .changed()
+                                            {
+                                                params
+                                                    .knob_listen
+                                                    .store(knob_listen, std::sync::atomic::Ordering::Relaxed);
+                                                listen_band.store(NO_LISTEN, std::sync::atomic::Ordering::Relaxed);
+                                                state.knob_listening = false;
+                                            }
+
+                                            let mut listen_level = params
+                                                .listen_level
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut listen_level, "Listen Level")
+                                                .on_hover_text("Narrow and wide bands are heard at about the same loudness")
+                                                .changed()
+                                            {
+                                                params
+                                                    .listen_level
+                                                    .store(listen_level, std::sync::atomic::Ordering::Relaxed);
+                                            }
+
+                                            let mut type_compensation = params
+                                                .type_compensation
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut type_compensation, "Type Level")
+                                                .on_hover_text("Changing a band's type adjusts the output to keep the overall level")
+                                                .changed()
+                                            {
+                                                params
+                                                    .type_compensation
+                                                    .store(type_compensation, std::sync::atomic::Ordering::Relaxed);
+                                            }
+
+                                            let mut res_display = params
+                                                .res_display
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            egui::ComboBox::from_label("Res Display")
+                                                .selected_text(match res_display {
+                                                    RES_DISPLAY_OCTAVES => "Octaves",
+                                                    RES_DISPLAY_HZ => "Hz",
+                                                    _ => "Q",
+                                                })
+                                                .show_ui(ui, |ui| {
+                                                    ui.selectable_value(&mut res_display, RES_DISPLAY_Q, "Q");
+                                                    ui.selectable_value(&mut res_display, RES_DISPLAY_OCTAVES, "Octaves");
+                                                    ui.selectable_value(&mut res_display, RES_DISPLAY_HZ, "Hz");
+                                                });
+                                            params
+                                                .res_display
+                                                .store(res_display, std::sync::atomic::Ordering::Relaxed);
+
+                                            let mut analyzer_mode = params
+                                                .analyzer_mode
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            egui::ComboBox::from_label("Analyzer")
+                                                .selected_text(match analyzer_mode {
+                                                    analyzer::ANALYZER_LR => "L/R",
+                                                    analyzer::ANALYZER_MS => "M/S",
+                                                    analyzer::ANALYZER_DIFF => "Diff",
+                                                    _ => "Sum",
+                                                })
+                                                .show_ui(ui, |ui| {
+                                                    ui.selectable_value(&mut analyzer_mode, analyzer::ANALYZER_SUM, "Sum");
+                                                    ui.selectable_value(&mut analyzer_mode, analyzer::ANALYZER_LR, "L/R");
+                                                    ui.selectable_value(&mut analyzer_mode, analyzer::ANALYZER_MS, "M/S");
+                                                    ui.selectable_value(&mut analyzer_mode, analyzer::ANALYZER_DIFF, "Diff")
+                                                        .on_hover_text("Show how the output's spectrum differs from the input's on the EQ's dB scale, smoothed since it's noisy on transients");
+                                                });
+                                            params
+                                                .analyzer_mode
+                                                .store(analyzer_mode, std::sync::atomic::Ordering::Relaxed);
+
+                                            let mut analyzer_spectrogram = params
+                                                .analyzer_spectrogram
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut analyzer_spectrogram, "Spectrogram")
+                                                .on_hover_text("Show the analyzer scrolling over time instead of as curves")
+                                                .changed()
+                                            {
+                                                params
+                                                    .analyzer_spectrogram
+                                                    .store(analyzer_spectrogram, std::sync::atomic::Ordering::Relaxed);
+                                            }
+
+                                            // Starting a hold or clearing it begins from nothing
+                                            let mut analyzer_hold = params
+                                                .analyzer_hold
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            let hold_changed = ui
+                                                .checkbox(&mut analyzer_hold, "Hold")
+                                                .on_hover_text("Keep the loudest the analyzer has been as a dashed line until cleared")
+                                                .changed();
+                                            if hold_changed {
+                                                params
+                                                    .analyzer_hold
+                                                    .store(analyzer_hold, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                            if hold_changed || (analyzer_hold && ui.button("Clear").clicked()) {
+                                                analyzer_held.lock().unwrap().fill(analyzer::ANALYZER_MIN_DB);
+                                            }
+
+                                            let mut analyzer_threshold = params
+                                                .analyzer_threshold
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut analyzer_threshold, "Threshold")
+                                                .on_hover_text("Draggable line on the analyzer's dBFS scale, bins over it are marked")
+                                                .changed()
+                                            {
+                                                params
+                                                    .analyzer_threshold
+                                                    .store(analyzer_threshold, std::sync::atomic::Ordering::Relaxed);
+                                            }
+
+                                            let mut analyzer_zoom = params
+                                                .analyzer_zoom
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut analyzer_zoom, "Zoom")
+                                                .on_hover_text("Close up of the analyzer, right drag on the graph to pick the region")
+                                                .changed()
+                                            {
+                                                params
+                                                    .analyzer_zoom
+                                                    .store(analyzer_zoom, std::sync::atomic::Ordering::Relaxed);
+                                            }
+
+                                            // Each end stays at least an octave from the other
+                                            let mut graph_min = params
+                                                .graph_min_freq
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            let mut graph_max = params
+                                                .graph_max_freq
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            ui.label("Graph");
+                                            ui.add(
+                                                egui::DragValue::new(&mut graph_min)
+                                                    .clamp_range(response_graph::GRAPH_LOWEST_FREQ..=graph_max / 2.0)
+                                                    .speed(1.0)
+                                                    .suffix(" Hz"),
+                                            );
+                                            ui.add(
+                                                egui::DragValue::new(&mut graph_max)
+                                                    .clamp_range(graph_min * 2.0..=response_graph::GRAPH_HIGHEST_FREQ)
+                                                    .speed(100.0)
+                                                    .suffix(" Hz"),
+                                            );
+                                            params
+                                                .graph_min_freq
+                                                .store(graph_min, std::sync::atomic::Ordering::Relaxed);
+                                            params
+                                                .graph_max_freq
+                                                .store(graph_max, std::sync::atomic::Ordering::Relaxed);
+
+                                            let mut graph_auto_db = params
+                                                .graph_auto_db
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut graph_auto_db, "Fit dB")
+                                                .on_hover_text("Scale the graph to fit the whole curve, otherwise use the range here")
+                                                .changed()
+                                            {
+                                                params
+                                                    .graph_auto_db
+                                                    .store(graph_auto_db, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                            let mut graph_db_range = params
+                                                .graph_db_range
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            ui.add_enabled(
+                                                !graph_auto_db,
+                                                egui::DragValue::new(&mut graph_db_range)
+                                                    .clamp_range(
+                                                        response_graph::GRAPH_MIN_DB_RANGE..=response_graph::GRAPH_MAX_DB_RANGE,
+                                                    )
+                                                    .speed(0.5)
+                                                    .prefix("±")
+                                                    .suffix(" dB"),
+                                            );
+                                            params
+                                                .graph_db_range
+                                                .store(graph_db_range, std::sync::atomic::Ordering::Relaxed);
+
+                                            let mut graph_phase = params
+                                                .graph_phase
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut graph_phase, "Phase")
+                                                .on_hover_text("Overlay the EQ's phase shift, scaled +-180 degrees on the right edge")
+                                                .changed()
+                                            {
+                                                params
+                                                    .graph_phase
+                                                    .store(graph_phase, std::sync::atomic::Ordering::Relaxed);
+                                            }
+
+                                            let mut graph_note_grid = params
+                                                .graph_note_grid
+                                                .load(std::sync::atomic::Ordering::Relaxed);
+                                            if ui
+                                                .checkbox(&mut graph_note_grid, "Note Grid")
+                                                .on_hover_text("Gridlines on every C, and every semitone when there's room")
+                                                .changed()
+                                            {
+                                                params
+                                                    .graph_note_grid
+                                                    .store(graph_note_grid, std::sync::atomic::Ordering::Relaxed);
+                                            }
+                                        });
                                     });
                                 });
-                            });
+                        }
+
+                        held_bypass.store(held_bands.get(), std::sync::atomic::Ordering::Relaxed);
+
+                        // T steps the focused band's type forward and Shift+T back, wrapping around.
+                        // Typing in a text field doesn't count.
+                        state.focused_band = focused_band.get();
+                        let (cycle_pressed, backwards) =
+                            ui.input(|i| (i.key_pressed(egui::Key::T), i.modifiers.shift));
+                        if let Some(band) = state.focused_band {
+                            if cycle_pressed && !ui.ctx().wants_keyboard_input() {
+                                let type_param = params.bands()[band].0;
+                                let count = FilterType::variants().len();
+                                let index = type_param.value().to_index();
+                                let next = if backwards { (index + count - 1) % count } else { (index + 1) % count };
+                                setter.begin_set_parameter(type_param);
+                                setter.set_parameter(type_param, FilterType::from_index(next));
+                                setter.end_set_parameter(type_param);
+                            }
+                        }
                     });
                 });
             },
@@ -689,13 +3802,11 @@ impl Plugin for Interleaf {
         &mut self,
         _audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
-        // After `PEAK_METER_DECAY_MS` milliseconds of pure silence, the peak meter's value should
-        // have dropped by 12 dB
-        self.out_meter_decay_weight = 0.25f64
-            .powf((buffer_config.sample_rate as f64 * PEAK_METER_DECAY_MS / 1000.0).recip())
-            as f32;
+        if let Some(latency) = self.prepare(buffer_config.sample_rate) {
+            context.set_latency_samples(latency);
+        }
 
         true
     }
@@ -703,239 +3814,730 @@ impl Plugin for Interleaf {
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        aux: &mut AuxiliaryBuffers,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        let arc_eq = self.equalizer.clone();
-        for mut channel_samples in buffer.iter_samples() {
-            let mut out_amplitude = 0.0;
-            let mut in_amplitude = 0.0;
-            let mut processed_sample_l: f32 = 0.0;
-            let mut processed_sample_r: f32 = 0.0;
-            let num_samples = channel_samples.len();
-
-            let gain = util::gain_to_db(self.params.input_gain.smoothed.next());
-            let output_gain = self.params.output_gain.smoothed.next();
-            let dry_wet = self.params.dry_wet.value();
-
-            // Split left and right same way original subhoofer did
-            let mut in_l: f32 = *channel_samples.get_mut(0).unwrap();
-            let mut in_r: f32 = *channel_samples.get_mut(1).unwrap();
-
-            // Make sure we are always on the correct sample rate, then update our EQ
-            let mut eq = arc_eq.lock().unwrap();
-
-            let sr = _context.transport().sample_rate;
-
-            // Apply our input gain to our incoming signal
-            in_l *= util::db_to_gain(gain);
-            in_r *= util::db_to_gain(gain);
+        let host = ContextHost {
+            context: &*context,
+            editor_open: self.params.editor_state.is_open(),
+        };
+        self.process_buffer(buffer, aux, &host)
+    }
 
-            // Calculate our amplitude for the decibel meter
-            in_amplitude += in_l + in_r;
+    const MIDI_INPUT: MidiConfig = MidiConfig::None;
 
-            // Set our interleaves
-            let interleave = self.params.interleaves.value();
-            for filter in eq.interleave_bands.iter_mut() {
-                filter.set_interleave(interleave as usize);
-            }
+    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
 
-            // Update our types
-            eq.interleave_bands[0].set_type(self.params.type_0.value());
-            eq.interleave_bands[1].set_type(self.params.type_1.value());
-            eq.interleave_bands[2].set_type(self.params.type_2.value());
-            eq.interleave_bands[3].set_type(self.params.type_3.value());
-            eq.interleave_bands[4].set_type(self.params.type_4.value());
-            eq.non_interleave_bands[0].set_type(self.params.type_0.value());
-            eq.non_interleave_bands[1].set_type(self.params.type_1.value());
-            eq.non_interleave_bands[2].set_type(self.params.type_2.value());
-            eq.non_interleave_bands[3].set_type(self.params.type_3.value());
-            eq.non_interleave_bands[4].set_type(self.params.type_4.value());
+    // Process never blocks or allocates: it only try_locks what it shares with the GUI and the
+    // background tasks and skips that work when it's busy, and debug builds abort on any
+    // allocation through nih_plug's assert_process_allocs. Hosts that support it will refuse
+    // offline rendering with this on, so it's opt in through the feature.
+    const HARD_REALTIME_ONLY: bool = cfg!(feature = "hard_realtime");
 
-            if interleave >= 2.0 {
-                // Use the interleaved biquads
-                eq.interleave_bands[0].update(
-                    sr,
-                    self.params.freq_band_0.value(),
-                    self.params.gain_band_0.value(),
-                    self.params.res_band_0.value(),
-                );
-                eq.interleave_bands[1].update(
-                    sr,
-                    self.params.freq_band_1.value(),
-                    self.params.gain_band_1.value(),
-                    self.params.res_band_1.value(),
-                );
-                eq.interleave_bands[2].update(
-                    sr,
-                    self.params.freq_band_2.value(),
-                    self.params.gain_band_2.value(),
-                    self.params.res_band_2.value(),
-                );
-                eq.interleave_bands[3].update(
-                    sr,
-                    self.params.freq_band_3.value(),
-                    self.params.gain_band_3.value(),
-                    self.params.res_band_3.value(),
-                );
-                eq.interleave_bands[4].update(
-                    sr,
-                    self.params.freq_band_4.value(),
-                    self.params.gain_band_4.value(),
-                    self.params.res_band_4.value(),
+    fn task_executor(&mut self) -> TaskExecutor<Self> {
+        let analyzer_frame = self.analyzer_frame.clone();
+        let analyzer_fft = self.analyzer_fft.clone();
+        let analyzer_bins = self.analyzer_bins.clone();
+        let analyzer_bins_second = self.analyzer_bins_second.clone();
+        let analyzer_difference = self.analyzer_difference.clone();
+        let analyzer_held = self.analyzer_held.clone();
+        let spectrogram = self.spectrogram.clone();
+        let peak_frequency = self.peak_frequency.clone();
+        let sample_rate = self.sample_rate.clone();
+        let suppressor_frame = self.suppressor_frame.clone();
+        let suppressor_fft = self.suppressor_fft.clone();
+        let suppressor_target = self.suppressor_target.clone();
+        let suppressor_excess = self.suppressor_excess.clone();
+        let match_frame = self.match_frame.clone();
+        let match_average = self.match_average.clone();
+        let match_difference = self.match_difference.clone();
+        let match_state = self.match_state.clone();
+        Box::new(move |task| match task {
+            // Off the audio thread so waiting on the locks here is fine. The peak readout follows
+            // the first spectrum: the sum, left, mid or input.
+            analyzer::AnalyzerTask::ComputeFrame { split, difference, hold, spectrogram: waterfall } => {
+                let mut analyzers = analyzer_fft.lock().unwrap();
+                let frame = analyzer_frame.lock().unwrap();
+                let (first_frame, second_frame) = frame.split_at(analyzer::FFT_SIZE);
+                analyzers[0].compute_frame(first_frame);
+                if split {
+                    analyzers[1].compute_frame(second_frame);
+                }
+                drop(frame);
+                let peak = analyzer::peak_frequency(
+                    analyzers[0].frame(),
+                    sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                    PEAK_FREQ_GATE_DB,
                 );
-
-                // Perform processing on the sample using the filters
-                let mut temp_l: f32 = -2.0;
-                let mut temp_r: f32 = -2.0;
-                for filter in eq.interleave_bands.iter_mut() {
-                    for i in 0..=self.params.oversampling.value() as usize {
-                        match i {
-                            0 => {
-                                if temp_l == -2.0 {
-                                    // This is the first time we run a filter at all
-                                    (temp_l, temp_r) = filter.process_sample(in_l, in_r);
-                                } else {
-                                    // This is not the first time or first filter but first iteration of "A filter"
-                                    (temp_l, temp_r) = filter.process_sample(temp_l, temp_r);                                    
-                                }
-                            },
-                            _ => {
-                                // These are subsequent filter iterations for any filter in the order
-                                (temp_l, temp_r) = filter.process_sample(temp_l, temp_r);
-                            }
-                        }
-                        filter.increment_index();
+                peak_frequency.store(peak.unwrap_or(0.0), std::sync::atomic::Ordering::Relaxed);
+                analyzer_bins.lock().unwrap().copy_from_slice(analyzers[0].display());
+                if difference {
+                    analyzer::smooth_difference(
+                        &mut analyzer_difference.lock().unwrap(),
+                        analyzers[0].frame(),
+                        analyzers[1].frame(),
+                        PEAK_FREQ_GATE_DB,
+                    );
+                } else if split {
+                    analyzer_bins_second.lock().unwrap().copy_from_slice(analyzers[1].display());
+                }
+                if hold {
+                    for (held, db) in analyzer_held.lock().unwrap().iter_mut().zip(analyzers[0].frame()) {
+                        *held = held.max(*db);
                     }
-
-                    // Sum up our output
-                    processed_sample_l = temp_l;
-                    processed_sample_r = temp_r;
                 }
-            } else {
-                // No interleaved biquads
-                eq.non_interleave_bands[0].update(
-                    sr,
-                    self.params.freq_band_0.value(),
-                    self.params.gain_band_0.value(),
-                    self.params.res_band_0.value(),
-                );
-                eq.non_interleave_bands[1].update(
-                    sr,
-                    self.params.freq_band_1.value(),
-                    self.params.gain_band_1.value(),
-                    self.params.res_band_1.value(),
-                );
-                eq.non_interleave_bands[2].update(
-                    sr,
-                    self.params.freq_band_2.value(),
-                    self.params.gain_band_2.value(),
-                    self.params.res_band_2.value(),
-                );
-                eq.non_interleave_bands[3].update(
-                    sr,
-                    self.params.freq_band_3.value(),
-                    self.params.gain_band_3.value(),
-                    self.params.res_band_3.value(),
-                );
-                eq.non_interleave_bands[4].update(
-                    sr,
-                    self.params.freq_band_4.value(),
-                    self.params.gain_band_4.value(),
-                    self.params.res_band_4.value(),
-                );
+                if waterfall {
+                    spectrogram.lock().unwrap().push(analyzers[0].frame());
+                }
+            }
+            analyzer::AnalyzerTask::DetectResonance { low, high } => {
+                let mut analyzer = suppressor_fft.lock().unwrap();
+                analyzer.compute_frame(&suppressor_frame.lock().unwrap());
+                let (freq, excess) = analyzer::strongest_resonance(
+                    analyzer.frame(),
+                    sample_rate.load(std::sync::atomic::Ordering::Relaxed),
+                    low.min(high),
+                    low.max(high),
+                    PEAK_FREQ_GATE_DB,
+                )
+                .unwrap_or((0.0, 0.0));
+                suppressor_target.store(freq, std::sync::atomic::Ordering::Relaxed);
+                suppressor_excess.store(excess, std::sync::atomic::Ordering::Relaxed);
+            }
+            analyzer::AnalyzerTask::MatchFrame { first } => {
+                match_average.lock().unwrap().add_frames(&match_frame.lock().unwrap(), first);
+            }
+            analyzer::AnalyzerTask::MatchFinish => {
+                let new_state = if match_average.lock().unwrap().finish(&mut match_difference.lock().unwrap()) {
+                    match_eq::MATCH_READY
+                } else {
+                    match_eq::MATCH_NO_SIDECHAIN
+                };
+                match_state.store(new_state, std::sync::atomic::Ordering::Relaxed);
+            }
+        })
+    }
 
-                // Perform processing on the sample using the filters
-                let mut temp_l: f32 = -2.0;
-                let mut temp_r: f32 = -2.0;
-                for filter in eq.non_interleave_bands.iter_mut() {
-                    for i in 0..=self.params.oversampling.value() as usize {
-                        match i {
-                            0 => {
-                                if temp_l == -2.0 {
-                                    // This is the first time we run a filter at all
-                                    (temp_l, temp_r) = filter.process_sample(in_l, in_r);
-                                } else {
-                                    // This is not the first time or first filter but first iteration of "A filter"
-                                    (temp_l, temp_r) = filter.process_sample(temp_l, temp_r);                                    
-                                }
-                            },
-                            _ => {
-                                // These are subsequent filter iterations for any filter in the order
-                                (temp_l, temp_r) = filter.process_sample(temp_l, temp_r);
-                            }
-                        }
+    fn filter_state(state: &mut PluginState) {
+        // nih_plug only sets the fields a state has, fill in the defaults for the missing ones
+        let defaults = InterleafParams::default().serialize_fields();
+        for key in VIEW_PREF_KEYS {
+            if let Some(default) = defaults.get(key) {
+                state.fields.entry(key.to_string()).or_insert_with(|| default.clone());
+            }
+        }
 
-                    }
-                    // Sum up our output
-                    processed_sample_l = temp_l;
-                    processed_sample_r = temp_r;
+        // x2 and the interleave counts used to be compared with float equality and some older
+        // states or hosts hand them back as ints, bools or just off the step. Put them back on
+        // values that mean what they did.
+        use nih_plug::wrapper::state::ParamValue;
+        for (id, value) in state.params.iter_mut() {
+            let legacy = match value {
+                ParamValue::F32(value) => *value,
+                ParamValue::I32(value) => *value as f32,
+                ParamValue::Bool(value) => *value as u8 as f32,
+                _ => continue,
+            };
+            let repaired = match id.as_str() {
+                "oversampling" => Some(if legacy >= 0.5 { 1.0 } else { 0.0 }),
+                "interleaves" => Some(if legacy.is_finite() { legacy.clamp(1.0, 10.0) } else { 4.0 }),
+                "interleaves_low" | "interleaves_mid" | "interleaves_high" => {
+                    Some(if legacy.is_finite() { legacy.round().clamp(1.0, 10.0) } else { 4.0 })
                 }
+                _ => None,
+            };
+            if let Some(repaired) = repaired {
+                *value = ParamValue::F32(repaired);
             }
+        }
+    }
 
-            // Calculate dry/wet mix
-            let wet_gain = dry_wet;
-            let dry_gain = 1.0 - dry_wet;
-            processed_sample_l = in_l * dry_gain + processed_sample_l * wet_gain;
-            processed_sample_r = in_r * dry_gain + processed_sample_r * wet_gain;
+    fn reset(&mut self) {
+        // A state load on running audio is crossfading from the EQ it had, clearing anything here
+        // would drop what's in flight
+        if std::mem::take(&mut self.keep_next_reset) {
+            return;
+        }
+        self.limiter.reset();
+        self.true_peak.reset();
+        for region in self.equalizer.iter_mut() {
+            region.reset_interleave();
+        }
+    }
 
-            // Output gain
-            processed_sample_l *= output_gain;
-            processed_sample_r *= output_gain;
+    fn deactivate(&mut self) {
+        self.processing = false;
+    }
+}
+
+// What process needs from the host. Tests stand in their own since nih_plug's contexts can only
+// be made by its wrappers.
+trait Host {
+    fn sample_rate(&self) -> f32;
+    // Where the transport is in beats, only while it's playing
+    fn playing_beats(&self) -> Option<f64>;
+    fn editor_open(&self) -> bool;
+    fn execute_background(&self, task: analyzer::AnalyzerTask);
+    fn set_latency_samples(&self, samples: u32);
+}
+
+// The real host, the editor is only asked about once per buffer
+struct ContextHost<'a, C> {
+    context: &'a C,
+    editor_open: bool,
+}
+
+impl<C: ProcessContext<Interleaf>> Host for ContextHost<'_, C> {
+    fn sample_rate(&self) -> f32 {
+        self.context.transport().sample_rate
+    }
+
+    fn playing_beats(&self) -> Option<f64> {
+        let transport = self.context.transport();
+        if transport.playing {
+            transport.pos_beats()
+        } else {
+            None
+        }
+    }
+
+    fn editor_open(&self) -> bool {
+        self.editor_open
+    }
+
+    fn execute_background(&self, task: analyzer::AnalyzerTask) {
+        self.context.execute_background(task);
+    }
+
+    fn set_latency_samples(&self, samples: u32) {
+        self.context.set_latency_samples(samples);
+    }
+}
+
+impl Interleaf {
+    // Everything initialize sets up for a sample rate, returns the latency to report if it changed
+    fn prepare(&mut self, sample_rate: f32) -> Option<u32> {
+        // nih_plug loads a state on an active plugin by initializing and resetting it again, the
+        // filters still have the old settings until the next buffer so fade from a copy of them
+        if self.processing {
+            self.start_bulk_fade();
+            self.keep_next_reset = true;
+        }
+
+        // After `PEAK_METER_DECAY_MS` milliseconds of pure silence, the peak meter's value should
+        // have dropped by 12 dB
+        self.out_meter_decay_weight = 0.25f64
+            .powf((sample_rate as f64 * PEAK_METER_DECAY_MS / 1000.0).recip())
+            as f32;
+        self.peak_hold_decay_weight = 0.25f64
+            .powf((sample_rate as f64 * PEAK_HOLD_DECAY_MS / 1000.0).recip())
+            as f32;
+        // Per sample so the needles move the same at any buffer size
+        self.vu_weight = 0.01f64
+            .powf((sample_rate as f64 * VU_INTEGRATION_MS / 1000.0).recip())
+            as f32;
+
+        // The meter window's weight gets worked out again on the next buffer
+        self.meter_window_applied = 0.0;
+
+        self.sample_rate
+            .store(sample_rate, std::sync::atomic::Ordering::Relaxed);
+
+        // Move our balance detection filters to the real sample rate
+        self.balance_low_filter.update(sample_rate, BALANCE_LOW_FREQ, 0.0, 0.707);
+        self.balance_high_filter.update(sample_rate, BALANCE_HIGH_FREQ, 0.0, 0.707);
+
+        // Allocate the limiter's delay line here so process never has to, the same rate keeps the
+        // one that's running along with its lookahead
+        let max_lookahead = (LIMITER_MAX_LOOKAHEAD_MS / 1000.0 * sample_rate).ceil() as usize;
+        if self.limiter.max_lookahead() != max_lookahead {
+            self.limiter = limiter::LookaheadLimiter::new(max_lookahead);
+            self.limiter_latency = 0;
+        }
+        self.update_limiter(sample_rate)
+    }
+
+    // Fade from a copy of the EQ as it is now, a fade already running is left to finish
+    fn start_bulk_fade(&mut self) {
+        if self.bulk_fade_mix >= 1.0 {
+            self.bulk_fade_equalizer = self.equalizer;
+            self.bulk_fade_multiband = self.multiband;
+            self.bulk_fade_mix = 0.0;
+        }
+    }
+
+    // Everything process does, the host is behind `Host` so tests can run this without one
+    fn process_buffer(
+        &mut self,
+        buffer: &mut Buffer,
+        aux: &mut AuxiliaryBuffers,
+        host: &impl Host,
+    ) -> ProcessStatus {
+        let process_start = std::time::Instant::now();
+        let sr = host.sample_rate();
+        let editor_open = host.editor_open();
+        if let Some(latency) = self.update_limiter(sr) {
+            host.set_latency_samples(latency);
+        }
+        let buffer_seconds = buffer.samples() as f32 / sr;
+
+        // Sweep macro moves once per buffer, synced it follows the transport while playing
+        let sweep_position = self.params.sweep_position(host.playing_beats());
+
+        // Match EQ capture, hosts that don't connect the sidechain give us no aux buffer at all
+        if self.match_state.load(std::sync::atomic::Ordering::Relaxed) == match_eq::MATCH_CAPTURING
+            && !self.match_capture.is_active()
+        {
+            self.match_capture.start(sr);
+        }
+        let sidechain = aux.inputs.first().map(|sidechain| sidechain.as_slice_immutable());
+
+        // Loudness trim measurement
+        if self.trim_state.load(std::sync::atomic::Ordering::Relaxed) == loudness_trim::TRIM_MEASURING
+            && !self.trim_measure.is_active()
+        {
+            self.trim_measure.start(sr);
+        }
+
+        // Resonance suppressor moves once per buffer like the sweep
+        self.update_suppressor(buffer.samples(), sr);
+
+        // The bands only move once per buffer, everything below works from this copy
+        let band_settings = self.params.band_settings(sweep_position);
+
+        // x2 passes wanted this buffer, Auto Quality or economy mode may be holding them off
+        self.oversampling_target = if self.quality_level >= 1
+            || self.params.economy.load(std::sync::atomic::Ordering::Relaxed)
+        {
+            0
+        } else {
+            self.params.effective_oversampling(&band_settings)
+        };
+
+        // The in/out meters average over the editor's window, only redone when the window changes
+        let meter_window = self
+            .params
+            .meter_window
+            .load(std::sync::atomic::Ordering::Relaxed)
+            .clamp(METER_WINDOW_MIN_MS, METER_WINDOW_MAX_MS);
+        if meter_window != self.meter_window_applied {
+            self.meter_window_weight = (-1000.0 / (sr as f64 * meter_window as f64)).exp() as f32;
+            self.meter_window_applied = meter_window;
+        }
+
+        // Bulk changes like randomize, match EQ or band resets jump several bands at once, so
+        // crossfade from a copy of the EQ that keeps running on the old settings. Only these
+        // discrete changes fade, knobs and automation moving bands are left alone. State loads
+        // start theirs from prepare.
+        if self.params.bulk_change.swap(false, std::sync::atomic::Ordering::Relaxed) {
+            self.start_bulk_fade();
+        }
+        self.processing = true;
+
+        // Effective Q readouts, only a few times a second and only for an open editor
+        if editor_open {
+            if self.effective_q_countdown <= buffer.samples() {
+                self.effective_q_countdown = (EFFECTIVE_Q_INTERVAL_MS * 0.001 * sr) as usize;
+                for ((_, freq, _, q), effective_q) in band_settings.iter().zip(self.effective_q.iter()) {
+                    effective_q.store(
+                        biquad_filters::effective_q(*freq, sr, *q, self.params.soft_q.value()),
+                        std::sync::atomic::Ordering::Relaxed,
+                    );
+                }
+            } else {
+                self.effective_q_countdown -= buffer.samples();
+            }
+        }
+
+        // Bands held bypassed from the editor, closing it lets go of them
+        let held_bypass = if editor_open {
+            self.held_bypass.load(std::sync::atomic::Ordering::Relaxed)
+        } else {
+            0
+        };
+
+        for (sample_index, mut channel_samples) in buffer.iter_samples().enumerate() {
+            let mut out_amplitude = 0.0;
+            let mut in_amplitude = 0.0;
+            let mut processed_sample_l: f32 = 0.0;
+            let mut processed_sample_r: f32 = 0.0;
+
+            let gain = util::gain_to_db(self.params.input_gain.smoothed.next());
+            let output_gain = self.params.output_gain.smoothed.next();
+            // The smoothed gains above are what the audio gets. When metering at the knob gain we
+            // scale the meters from those to where the knobs are right now (the smoother targets)
+            // so the dBFS readouts match the knobs instantly during fast moves or automation
+            let (in_meter_scale, out_meter_scale) = if self
+                .params
+                .meter_target_gain
+                .load(std::sync::atomic::Ordering::Relaxed)
+            {
+                (
+                    self.params.input_gain.value() / util::db_to_gain(gain),
+                    self.params.output_gain.value() / output_gain,
+                )
+            } else {
+                (1.0, 1.0)
+            };
+            let dry_wet = self.params.dry_wet.value();
+
+            // Split left and right same way original subhoofer did
+            // Mono runs the left channel through both sides of the filters, anything past
+            // MAX_PROCESSED_CHANNELS is left as is so odd host layouts pass through unprocessed
+            let num_channels = channel_samples.len().min(MAX_PROCESSED_CHANNELS);
+            if num_channels == 0 {
+                continue;
+            }
+            let mut in_l: f32 = *channel_samples.get_mut(0).unwrap();
+            let mut in_r: f32 = if num_channels > 1 {
+                *channel_samples.get_mut(1).unwrap()
+            } else {
+                in_l
+            };
+
+            // Test noise stands in for the input while the editor is open, closing it stops the noise
+            if self.test_noise.load(std::sync::atomic::Ordering::Relaxed) && editor_open {
+                let noise = self.pink_noise.next_sample();
+                in_l = noise;
+                in_r = noise;
+            }
+            // What came in before anything touched it, the loudness trim compares against this
+            let (raw_l, raw_r) = (in_l, in_r);
+
+            // Apply our input gain to our incoming signal
+            in_l *= util::db_to_gain(gain);
+            in_r *= util::db_to_gain(gain);
+
+            // Calculate our amplitude for the decibel meter, only from the channels that are really
+            // there so a mono track reads its own level instead of doubled
+            in_amplitude += if num_channels > 1 { in_l + in_r } else { in_l } * in_meter_scale;
+
+            if self.match_capture.is_active() {
+                let reference = sidechain
+                    .and_then(|channels| {
+                        let left = *channels.first()?.get(sample_index)?;
+                        let right = channels.get(1).and_then(|channel| channel.get(sample_index).copied());
+                        Some((left + right.unwrap_or(left)) / 2.0)
+                    })
+                    .unwrap_or(0.0);
+                // A pair of frames the task is still busy with gets dropped like the analyzer's
+                if self.match_capture.push((in_l + in_r) / 2.0, reference) {
+                    if let Ok(mut frame) = self.match_frame.try_lock() {
+                        let first = self.match_capture.copy_frames(&mut frame);
+                        drop(frame);
+                        host.execute_background(analyzer::AnalyzerTask::MatchFrame { first });
+                    }
+                }
+                if self.match_capture.finished() {
+                    self.match_state
+                        .store(match_eq::MATCH_AVERAGING, std::sync::atomic::Ordering::Relaxed);
+                    host.execute_background(analyzer::AnalyzerTask::MatchFinish);
+                }
+            }
+
+            // In Side EQ mode the bands only see the side signal
+            let side_only = self.params.side_only.value();
+            let mid = (in_l + in_r) / 2.0;
+            let side = (in_l - in_r) / 2.0;
+            let (eq_in_l, eq_in_r) = if side_only { (side, side) } else { (in_l, in_r) };
+
+            // Resonance suppressor detection listens to what the bands get, a frame the task is
+            // still busy with gets dropped like the analyzer's
+            if self.params.suppressor.value() && self.suppressor_analyzer.push((eq_in_l + eq_in_r) / 2.0) {
+                if let Ok(mut frame) = self.suppressor_frame.try_lock() {
+                    self.suppressor_analyzer.copy_frame(&mut frame);
+                    drop(frame);
+                    host.execute_background(analyzer::AnalyzerTask::DetectResonance {
+                        low: self.params.suppressor_low.value(),
+                        high: self.params.suppressor_high.value(),
+                    });
+                }
+            }
+
+            // Set our interleaves, Auto Quality may be holding these down
+            let interleave = self.limit_interleave(self.params.interleaves.value());
+            let oversampling_step = 1000.0 / (OS_SWITCH_FADE_MS * sr);
+            if self.oversampling_target != self.oversampling_active {
+                self.oversampling_mix -= oversampling_step;
+                if self.oversampling_mix <= 0.0 {
+                    self.oversampling_mix = 0.0;
+                    self.oversampling_active = self.oversampling_target;
+                }
+            } else if self.oversampling_mix < 1.0 {
+                self.oversampling_mix = (self.oversampling_mix + oversampling_step).min(1.0);
+            }
+            let oversampling = self.oversampling_active;
+
+            let region_interleaves = [
+                self.limit_interleave(self.params.interleaves_low.value()),
+                self.limit_interleave(self.params.interleaves_mid.value()),
+                self.limit_interleave(self.params.interleaves_high.value()),
+            ];
+            (processed_sample_l, processed_sample_r) = run_bands(
+                &mut self.equalizer,
+                &mut self.multiband,
+                &self.params,
+                sr,
+                eq_in_l,
+                eq_in_r,
+                interleave,
+                region_interleaves,
+                oversampling,
+                held_bypass,
+                &band_settings,
+                false,
+            );
+            // Fading over from the copy still on the settings from before a bulk change
+            if self.bulk_fade_mix < 1.0 {
+                let (old_l, old_r) = run_bands(
+                    &mut self.bulk_fade_equalizer,
+                    &mut self.bulk_fade_multiband,
+                    &self.params,
+                    sr,
+                    eq_in_l,
+                    eq_in_r,
+                    interleave,
+                    region_interleaves,
+                    oversampling,
+                    held_bypass,
+                    &band_settings,
+                    true,
+                );
+                self.bulk_fade_mix = (self.bulk_fade_mix + 1000.0 / (BULK_FADE_MS * sr)).min(1.0);
+                processed_sample_l = old_l + (processed_sample_l - old_l) * self.bulk_fade_mix;
+                processed_sample_r = old_r + (processed_sample_r - old_r) * self.bulk_fade_mix;
+            }
+            if self.oversampling_mix < 1.0 {
+                processed_sample_l = eq_in_l + (processed_sample_l - eq_in_l) * self.oversampling_mix;
+                processed_sample_r = eq_in_r + (processed_sample_r - eq_in_r) * self.oversampling_mix;
+            }
+
+            // Put the untouched mid back with our filtered side
+            if side_only {
+                let processed_side = processed_sample_l;
+                processed_sample_l = mid + processed_side;
+                processed_sample_r = mid - processed_side;
+            }
+
+            // Calculate dry/wet mix, above 100% the dry gain goes negative which is the same as
+            // in + (processed - in) * wet
+            let wet_gain = dry_wet;
+            let dry_gain = 1.0 - dry_wet;
+            processed_sample_l = in_l * dry_gain + processed_sample_l * wet_gain;
+            processed_sample_r = in_r * dry_gain + processed_sample_r * wet_gain;
+
+            // Boost sweep hunting from the editor puts a narrow boost over everything
+            let hunt = self.hunt_freq.load(std::sync::atomic::Ordering::Relaxed);
+            if hunt > 0.0 && editor_open {
+                self.hunt_filter.update(sr, hunt, HUNT_BOOST_DB, HUNT_BOOST_Q);
+                (processed_sample_l, processed_sample_r) =
+                    self.hunt_filter.process_sample(processed_sample_l, processed_sample_r);
+            }
+
+            // Listening swaps the EQ for a bandpass on that band, crossfaded both ways so it doesn't
+            // click. This only follows the editor so closing it mid drag can't leave us stuck listening.
+            let listen_band = self.listen_band.load(std::sync::atomic::Ordering::Relaxed);
+            let listening = listen_band != NO_LISTEN && editor_open;
+            if listening {
+                self.listen_fade_band = listen_band as usize;
+            }
+            let listen_step = 1000.0 / (BAND_BYPASS_FADE_MS * sr);
+            self.listen_mix = if listening {
+                (self.listen_mix + listen_step).min(1.0)
+            } else {
+                (self.listen_mix - listen_step).max(0.0)
+            };
+            if self.listen_mix > 0.0 {
+                let (_, freq, _, q) = band_settings[self.listen_fade_band];
+                self.listen_filter.update(sr, freq, 0.0, q);
+                let (mut listen_l, mut listen_r) = self.listen_filter.process_sample(in_l, in_r);
+                // Power through the bandpass goes with its width in octaves on pink-ish material
+                if self.params.listen_level.load(std::sync::atomic::Ordering::Relaxed) {
+                    let level_db = (10.0
+                        * (LISTEN_REFERENCE_OCTAVES / biquad_filters::q_to_octaves(q)).log10())
+                    .clamp(-LISTEN_LEVEL_MAX_DB, LISTEN_LEVEL_MAX_DB);
+                    listen_l *= util::db_to_gain(level_db);
+                    listen_r *= util::db_to_gain(level_db);
+                }
+                processed_sample_l += (listen_l - processed_sample_l) * self.listen_mix;
+                processed_sample_r += (listen_r - processed_sample_r) * self.listen_mix;
+            } else if self.solo_region.load(std::sync::atomic::Ordering::Relaxed)
+                && editor_open
+            {
+                // Highpass at the low bound into lowpass at the high one, backwards bounds get swapped
+                let bound_a = self.solo_low.load(std::sync::atomic::Ordering::Relaxed);
+                let bound_b = self.solo_high.load(std::sync::atomic::Ordering::Relaxed);
+                let (low, high) = if bound_a > bound_b { (bound_b, bound_a) } else { (bound_a, bound_b) };
+                self.solo_highpass.update(sr, low, 0.0, 0.707);
+                self.solo_lowpass.update(sr, high, 0.0, 0.707);
+                let (high_l, high_r) = self.solo_highpass.process_sample(in_l, in_r);
+                (processed_sample_l, processed_sample_r) = self.solo_lowpass.process_sample(high_l, high_r);
+            }
+
+            // The trim measures before the output gain since that's what it sets
+            if self.trim_measure.is_active()
+                && self.trim_measure.push(raw_l, raw_r, processed_sample_l, processed_sample_r)
+            {
+                let new_state = match self.trim_measure.finish() {
+                    Some(gain) => {
+                        self.trim_gain.store(gain, std::sync::atomic::Ordering::Relaxed);
+                        loudness_trim::TRIM_READY
+                    }
+                    None => loudness_trim::TRIM_TOO_QUIET,
+                };
+                self.trim_state.store(new_state, std::sync::atomic::Ordering::Relaxed);
+            }
+
+            // What the output meter reads in Meter Pre Out, the output gain can't move it so it
+            // isn't scaled to the knob either
+            let pre_output = (processed_sample_l, processed_sample_r);
+
+            // Output gain
+            processed_sample_l *= output_gain;
+            processed_sample_r *= output_gain;
+
+            // Lookahead limiter on the output
+            if self.params.limiter.value() {
+                (processed_sample_l, processed_sample_r) = self.limiter.process_sample(
+                    processed_sample_l,
+                    processed_sample_r,
+                    util::db_to_gain(self.params.limiter_ceiling.value()),
+                );
+            }
 
             // Assign back so we can output our processed sounds
             *channel_samples.get_mut(0).unwrap() = processed_sample_l;
-            *channel_samples.get_mut(1).unwrap() = processed_sample_r;
+            if num_channels > 1 {
+                *channel_samples.get_mut(1).unwrap() = processed_sample_r;
+            }
 
-            out_amplitude += processed_sample_l + processed_sample_r;
+            let (meter_l, meter_r, out_meter_scale) =
+                if self.params.meter_pre_output.load(std::sync::atomic::Ordering::Relaxed) {
+                    (pre_output.0, pre_output.1, 1.0)
+                } else {
+                    (processed_sample_l, processed_sample_r, out_meter_scale)
+                };
+            out_amplitude += if num_channels > 1 { meter_l + meter_r } else { meter_l } * out_meter_scale;
 
             // To save resources, a plugin can (and probably should!) only perform expensive
             // calculations that are only displayed on the GUI while the GUI is open
-            if self.params.editor_state.is_open() {
+            if editor_open {
                 // Input gain meter
-                in_amplitude = (in_amplitude / num_samples as f32).abs();
-                let current_in_meter = self.in_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_in_meter = if in_amplitude > current_in_meter {
-                    in_amplitude
-                } else {
-                    current_in_meter * self.out_meter_decay_weight
-                        + in_amplitude * (1.0 - self.out_meter_decay_weight)
-                };
+                in_amplitude = (in_amplitude / num_channels as f32).abs();
+                self.in_meter_square = self.in_meter_square * self.meter_window_weight
+                    + in_amplitude * in_amplitude * (1.0 - self.meter_window_weight);
                 self.in_meter
-                    .store(new_in_meter, std::sync::atomic::Ordering::Relaxed);
+                    .store(self.in_meter_square.sqrt(), std::sync::atomic::Ordering::Relaxed);
+                let current_in_peak = self.in_meter_peak.load(std::sync::atomic::Ordering::Relaxed);
+                self.in_meter_peak.store(
+                    in_amplitude.max(current_in_peak * self.peak_hold_decay_weight),
+                    std::sync::atomic::Ordering::Relaxed,
+                );
 
                 // Output gain meter
-                out_amplitude = (out_amplitude / num_samples as f32).abs();
-                let current_out_meter = self.out_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_out_meter = if out_amplitude > current_out_meter {
-                    out_amplitude
-                } else {
-                    current_out_meter * self.out_meter_decay_weight
-                        + out_amplitude * (1.0 - self.out_meter_decay_weight)
-                };
+                out_amplitude = (out_amplitude / num_channels as f32).abs();
+                self.out_meter_square = self.out_meter_square * self.meter_window_weight
+                    + out_amplitude * out_amplitude * (1.0 - self.meter_window_weight);
                 self.out_meter
-                    .store(new_out_meter, std::sync::atomic::Ordering::Relaxed);
-            }
-        }
-        ProcessStatus::Normal
-    }
-
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
-
-    const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
+                    .store(self.out_meter_square.sqrt(), std::sync::atomic::Ordering::Relaxed);
+                // Peak hold uses the loudest channel so overs show up as clips
+                let out_peak = if self.params.true_peak_meter.load(std::sync::atomic::Ordering::Relaxed) {
+                    self.true_peak.process(meter_l, meter_r)
+                } else {
+                    meter_l.abs().max(meter_r.abs())
+                } * out_meter_scale;
+                let current_out_peak = self.out_meter_peak.load(std::sync::atomic::Ordering::Relaxed);
+                self.out_meter_peak.store(
+                    out_peak.max(current_out_peak * self.peak_hold_decay_weight),
+                    std::sync::atomic::Ordering::Relaxed,
+                );
 
-    const HARD_REALTIME_ONLY: bool = false;
+                // VU needles integrate the rectified level
+                let in_vu = self.in_vu.load(std::sync::atomic::Ordering::Relaxed);
+                self.in_vu.store(
+                    in_vu * self.vu_weight + in_amplitude * (1.0 - self.vu_weight),
+                    std::sync::atomic::Ordering::Relaxed,
+                );
+                let out_vu = self.out_vu.load(std::sync::atomic::Ordering::Relaxed);
+                self.out_vu.store(
+                    out_vu * self.vu_weight + out_amplitude * (1.0 - self.vu_weight),
+                    std::sync::atomic::Ordering::Relaxed,
+                );
 
-    fn task_executor(&mut self) -> TaskExecutor<Self> {
-        // In the default implementation we can simply ignore the value
-        Box::new(|_| ())
-    }
+                // Spectral balance meter from the energy in two broad detection bands
+                let (low_l, low_r) = self
+                    .balance_low_filter
+                    .process_sample(processed_sample_l, processed_sample_r);
+                let (high_l, high_r) = self
+                    .balance_high_filter
+                    .process_sample(processed_sample_l, processed_sample_r);
+                self.balance_low_energy = self.balance_low_energy * self.out_meter_decay_weight
+                    + (low_l * low_l + low_r * low_r) * (1.0 - self.out_meter_decay_weight);
+                self.balance_high_energy = self.balance_high_energy * self.out_meter_decay_weight
+                    + (high_l * high_l + high_r * high_r) * (1.0 - self.out_meter_decay_weight);
+                let silence_energy = util::db_to_gain(BALANCE_SILENCE_DB).powi(2);
+                let new_balance = if self.balance_low_energy + self.balance_high_energy
+                    < silence_energy
+                {
+                    // Nothing meaningful to compare during silence so rest at the center
+                    0.0
+                } else {
+                    10.0 * ((self.balance_high_energy + f32::EPSILON)
+                        / (self.balance_low_energy + f32::EPSILON))
+                        .log10()
+                        + BALANCE_PINK_OFFSET_DB
+                };
+                self.balance_meter
+                    .store(new_balance, std::sync::atomic::Ordering::Relaxed);
 
-    fn filter_state(_state: &mut PluginState) {}
+                // Limiter gain reduction
+                let reduction = if self.params.limiter.value() {
+                    util::gain_to_db(self.limiter.gain())
+                } else {
+                    0.0
+                };
+                self.limiter_meter
+                    .store(reduction, std::sync::atomic::Ordering::Relaxed);
 
-    fn reset(&mut self) {}
+                // Input spectrum, hand the frame over for the FFT. If the task still has the last
+                // one this frame gets dropped, the display only needs to keep up roughly.
+                // Economy mode skips it all.
+                if !self.params.economy.load(std::sync::atomic::Ordering::Relaxed) {
+                    let analyzer_mode = self.params.analyzer_mode.load(std::sync::atomic::Ordering::Relaxed);
+                    let (first, second) = match analyzer_mode {
+                        analyzer::ANALYZER_LR => (in_l, in_r),
+                        analyzer::ANALYZER_MS => ((in_l + in_r) / 2.0, (in_l - in_r) / 2.0),
+                        // The output side follows Meter Pre Out so the output gain can be left out
+                        analyzer::ANALYZER_DIFF => ((in_l + in_r) / 2.0, (meter_l + meter_r) / 2.0),
+                        _ => ((in_l + in_r) / 2.0, 0.0),
+                    };
+                    self.analyzer_second.push(second);
+                    if self.analyzer.push(first) {
+                        if let Ok(mut frame) = self.analyzer_frame.try_lock() {
+                            let (first_frame, second_frame) = frame.split_at_mut(analyzer::FFT_SIZE);
+                            self.analyzer.copy_frame(first_frame);
+                            self.analyzer_second.copy_frame(second_frame);
+                            drop(frame);
+                            host.execute_background(analyzer::AnalyzerTask::ComputeFrame {
+                                split: analyzer_mode != analyzer::ANALYZER_SUM,
+                                difference: analyzer_mode == analyzer::ANALYZER_DIFF,
+                                hold: self.params.analyzer_hold.load(std::sync::atomic::Ordering::Relaxed),
+                                spectrogram: self
+                                    .params
+                                    .analyzer_spectrogram
+                                    .load(std::sync::atomic::Ordering::Relaxed),
+                            });
+                        }
+                    }
+                }
+            }
+        }
 
-    fn deactivate(&mut self) {}
+        if buffer_seconds > 0.0 {
+            self.update_quality(process_start.elapsed().as_secs_f32() / buffer_seconds);
+        }
+        ProcessStatus::Normal
+    }
 }
 
 impl ClapPlugin for Interleaf {
@@ -968,10 +4570,805 @@ pub fn format_nothing() -> Arc<dyn Fn(f32) -> String + Send + Sync> {
 
 // This formats the interleave knob
 pub fn format_interleave() -> Arc<dyn Fn(f32) -> String + Send + Sync> {
-    Arc::new(move | input_number | if input_number < 2.0 {String::from("Off")} else {String::from(input_number.to_string())})
+    Arc::new(move | input_number | if input_number <= 1.0 {String::from("Off")} else if input_number.fract() == 0.0 {input_number.to_string()} else {format!("{input_number:.2}")})
+}
+
+// Move a frequency by a number of cents, this keeps steps log spaced across the range
+pub fn step_frequency(freq: f32, cents: f32) -> f32 {
+    freq * 2.0_f32.powf(cents / 1200.0)
+}
+
+// This formats the multiband region count knob
+pub fn format_regions() -> Arc<dyn Fn(i32) -> String + Send + Sync> {
+    Arc::new(move | regions | if regions < 2 {String::from("Off")} else {regions.to_string()})
+}
+
+// This formats the band frequency knobs, with the note name or MIDI note number when those are on
+pub fn format_frequency(
+    note_names: Arc<AtomicBool>,
+    note_numbers: Arc<AtomicBool>,
+) -> Arc<dyn Fn(f32) -> String + Send + Sync> {
+    let with_note_name = formatters::v2s_f32_hz_then_khz_with_note_name(2, false);
+    let plain = formatters::v2s_f32_hz_then_khz(2);
+    Arc::new(move |value| {
+        if note_numbers.load(std::sync::atomic::Ordering::Relaxed) {
+            let (note, cents) = note_number(value);
+            format!("{}, {note} {cents:+}c", plain(value))
+        } else if note_names.load(std::sync::atomic::Ordering::Relaxed) {
+            with_note_name(value)
+        } else {
+            plain(value)
+        }
+    })
+}
+
+// Nearest MIDI note to a frequency and how many cents off it that is, 440 Hz is 69 +0
+pub fn note_number(freq: f32) -> (i32, i32) {
+    let note = util::freq_to_midi_note(freq);
+    let nearest = note.round();
+    (nearest as i32, ((note - nearest) * 100.0).round() as i32)
 }
 
 // This formats the x2 knob - this is like this because of using the value to control looping
 pub fn format_x2() -> Arc<dyn Fn(f32) -> String + Send + Sync> {
     Arc::new(move | input_number | if input_number == 1.0 {String::from("On")} else {String::from("Off")})
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    // Stands in for the host, nothing is playing and background tasks are dropped
+    struct TestHost {
+        editor_open: bool,
+    }
+
+    impl Host for TestHost {
+        fn sample_rate(&self) -> f32 {
+            SAMPLE_RATE
+        }
+
+        fn playing_beats(&self) -> Option<f64> {
+            None
+        }
+
+        fn editor_open(&self) -> bool {
+            self.editor_open
+        }
+
+        fn execute_background(&self, _task: analyzer::AnalyzerTask) {}
+
+        fn set_latency_samples(&self, _samples: u32) {}
+    }
+
+    // Moves a param the way the host would, smoothers included
+    pub(crate) fn set_param<P: Param>(param: &P, value: P::Plain) {
+        unsafe {
+            param.as_ptr().set_normalized_value(param.preview_normalized(value));
+        }
+    }
+
+    // Stands in for the editor's side of the host, sets params straight away and counts gestures
+    #[derive(Default)]
+    struct TestGuiContext {
+        begun: AtomicU32,
+        ended: AtomicU32,
+    }
+
+    impl GuiContext for TestGuiContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Clap
+        }
+
+        fn request_resize(&self) -> bool {
+            false
+        }
+
+        unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {
+            self.begun.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+            param.set_normalized_value(normalized);
+        }
+
+        unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {
+            self.ended.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        fn get_state(&self) -> PluginState {
+            PluginState {
+                version: String::new(),
+                params: std::collections::BTreeMap::new(),
+                fields: std::collections::BTreeMap::new(),
+            }
+        }
+
+        fn set_state(&self, _state: PluginState) {}
+    }
+
+    fn prepared_plugin() -> Interleaf {
+        let mut plugin = Interleaf::default();
+        plugin.prepare(SAMPLE_RATE);
+        plugin
+    }
+
+    // A sine at a peak level
+    fn sine(freq: f32, level: f32, samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| level * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    // Runs the channels through process in place, `block_size` samples at a time
+    fn process_channels(plugin: &mut Interleaf, host: &TestHost, channels: &mut [&mut [f32]], block_size: usize) {
+        let samples = channels[0].len();
+        let mut start = 0;
+        while start < samples {
+            let end = (start + block_size).min(samples);
+            let mut buffer = Buffer::default();
+            unsafe {
+                buffer.set_slices(end - start, |slices| {
+                    *slices = channels.iter_mut().map(|channel| &mut channel[start..end]).collect();
+                });
+            }
+            let mut aux = AuxiliaryBuffers { inputs: &mut [], outputs: &mut [] };
+            plugin.process_buffer(&mut buffer, &mut aux, host);
+            start = end;
+        }
+    }
+
+    // Biggest jump between neighbouring samples
+    fn max_step(samples: &[f32]) -> f32 {
+        samples.windows(2).map(|pair| (pair[1] - pair[0]).abs()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn process_does_not_allocate() {
+        let mut plugin = prepared_plugin();
+        let params = plugin.params.clone();
+        set_param(&params.interleaves, 3.5);
+        set_param(&params.oversampling, 1.0);
+        set_param(&params.regions, 3);
+        set_param(&params.limiter, true);
+        set_param(&params.gain_band_2, 6.0);
+        let host = TestHost { editor_open: true };
+
+        let mut left = sine(1000.0, 0.5, 512);
+        let mut right = sine(500.0, 0.5, 512);
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(512, |slices| {
+                *slices = vec![left.as_mut_slice(), right.as_mut_slice()];
+            });
+        }
+        let mut aux = AuxiliaryBuffers { inputs: &mut [], outputs: &mut [] };
+        assert_no_alloc::assert_no_alloc(|| {
+            for _ in 0..16 {
+                plugin.process_buffer(&mut buffer, &mut aux, &host);
+            }
+        });
+    }
+
+    #[test]
+    fn synced_sweep_starts_from_the_manual_frequency() {
+        let params = InterleafParams::default();
+        set_param(&params.sweep_band, 1);
+        set_param(&params.freq_band_1, 1000.0);
+        set_param(&params.sweep_low, 200.0);
+        set_param(&params.sweep_high, 8000.0);
+        set_param(&params.sweep_sync, true);
+        set_param(&params.sweep, 1.0);
+        let manual = params.freq_band_1.value();
+        let high = params.sweep_high.value();
+
+        // Each cycle starts back at position 0, the band is still swept there and sits on its knob
+        let start = params.sweep_position(Some(0.0));
+        assert_eq!(start, 0.0);
+        assert_eq!(params.band_frequency(1, start), manual);
+        // Just after the start it has barely moved, not jumped to a bound
+        let early = params.band_frequency(1, params.sweep_position(Some(0.01)));
+        assert!((early / manual).log2().abs() < 0.1, "{early} Hz");
+        // Top of the cycle is the high bound
+        let top = params.band_frequency(1, params.sweep_position(Some(params.sweep_beats.value() as f64 / 2.0)));
+        assert!((top / high - 1.0).abs() < 1e-4, "{top} Hz");
+    }
+
+    #[test]
+    fn preset_load_has_no_discontinuity() {
+        use nih_plug::wrapper::state::ParamValue;
+        let mut plugin = prepared_plugin();
+        let params = plugin.params.clone();
+        set_param(&params.limiter, true);
+        set_param(&params.interleaves, 3.0);
+        let host = TestHost { editor_open: false };
+        let samples = SAMPLE_RATE as usize;
+        let load_at = samples / 2;
+        let mut left = sine(200.0, 0.1, samples);
+        let mut right = left.clone();
+
+        let (left_before, left_after) = left.split_at_mut(load_at);
+        let (right_before, right_after) = right.split_at_mut(load_at);
+        process_channels(&mut plugin, &host, &mut [left_before, right_before], 256);
+
+        // A preset with every band boosted, loaded the way the wrapper does it: filter the state,
+        // set the params, then initialize and reset
+        let mut state = PluginState {
+            version: String::new(),
+            params: std::collections::BTreeMap::new(),
+            fields: std::collections::BTreeMap::new(),
+        };
+        for index in 1..=5 {
+            state.params.insert(format!("gain_band_{index}"), ParamValue::F32(12.0));
+        }
+        state.params.insert("limiter".to_string(), ParamValue::Bool(true));
+        state.params.insert("interleaves".to_string(), ParamValue::F32(3.0));
+        Interleaf::filter_state(&mut state);
+        let by_id: std::collections::HashMap<String, ParamPtr> =
+            params.param_map().into_iter().map(|(id, ptr, _)| (id, ptr)).collect();
+        for (id, value) in &state.params {
+            let plain = match value {
+                ParamValue::F32(value) => *value,
+                ParamValue::Bool(value) => *value as u8 as f32,
+                _ => continue,
+            };
+            unsafe {
+                by_id[id].set_normalized_value(by_id[id].preview_normalized(plain));
+            }
+        }
+        plugin.prepare(SAMPLE_RATE);
+        plugin.reset();
+        process_channels(&mut plugin, &host, &mut [left_after, right_after], 256);
+
+        // Around the load it moves no faster than the louder settled signal does, dropping the
+        // limiter's lookahead or jumping the filters would show up as a step here
+        let settled = max_step(&left[samples - samples / 10..]);
+        let around_load = max_step(&left[load_at - 64..load_at + 2048]);
+        assert!(around_load <= settled * 1.1, "{around_load} against {settled}");
+    }
+
+    #[test]
+    fn linked_res_moves_every_band_in_one_gesture_each() {
+        let params = InterleafParams::default();
+        let context = TestGuiContext::default();
+        let setter = ParamSetter::new(&context);
+
+        // A drag over a few frames, band 2 leading
+        params.begin_link_res(&setter, 2);
+        for res in [0.5, 0.3, 0.25] {
+            set_param(&params.res_band_2, res);
+            params.link_res(&setter, 2, params.res_band_2.value());
+        }
+        params.end_link_res(&setter, 2);
+
+        let res = params.res_band_2.value();
+        for (_, _, _, res_param) in params.bands() {
+            assert_eq!(res_param.value(), res);
+        }
+        assert_eq!(context.begun.load(std::sync::atomic::Ordering::Relaxed), 4);
+        assert_eq!(context.ended.load(std::sync::atomic::Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn set_band_is_one_gesture() {
+        let params = InterleafParams::default();
+        let context = TestGuiContext::default();
+        let setter = ParamSetter::new(&context);
+
+        params.set_band(&setter, 3, FilterType::Peak, 2500.0, -6.0, 1.0);
+        let (type_param, freq_param, gain_param, res_param) = params.bands()[3];
+        assert!(type_param.value() == FilterType::Peak);
+        assert!((freq_param.value() - 2500.0).abs() < 0.5);
+        assert!((gain_param.value() + 6.0).abs() < 1e-4);
+        assert!((res_param.value() - 1.0).abs() < 1e-4);
+        // One begin and one end for each of the four params
+        assert_eq!(context.begun.load(std::sync::atomic::Ordering::Relaxed), 4);
+        assert_eq!(context.ended.load(std::sync::atomic::Ordering::Relaxed), 4);
+        assert!(params.bulk_change.load(std::sync::atomic::Ordering::Relaxed));
+    }
+
+    #[test]
+    fn q_scale_x2_halves_the_bandwidth() {
+        let params = InterleafParams::default();
+        set_param(&params.interleaves, 1.0);
+        set_param(&params.oversampling, 0.0);
+        set_param(&params.type_2, FilterType::BandPass);
+        set_param(&params.freq_band_2, 1000.0);
+        set_param(&params.res_band_2, DEFAULT_RES);
+        let bandwidth = || {
+            let (lower, upper) = biquad_filters::tests::minus_3db_points(|freqs| {
+                params.frequency_response(SAMPLE_RATE, freqs)
+            });
+            upper - lower
+        };
+
+        let unscaled = bandwidth();
+        set_param(&params.q_scale, 2.0);
+        // Past the Res knob's top but nowhere near the filters' limit
+        assert!((params.band_q(&params.res_band_2) - 2.0 * params.res_band_2.value()).abs() < 1e-4);
+        let scaled = bandwidth();
+        assert!((scaled / unscaled - 0.5).abs() < 0.02, "{scaled} Hz against {unscaled} Hz");
+    }
+
+    #[test]
+    fn type_level_compensation_holds_the_broadband_level() {
+        let context = TestGuiContext::default();
+        let setter = ParamSetter::new(&context);
+        // Broadband level of a Peak boost switched to a Notch with and without making it up
+        let level_change = |compensate: bool| {
+            let params = InterleafParams::default();
+            set_param(&params.freq_band_2, 1000.0);
+            set_param(&params.gain_band_2, 9.0);
+            set_param(&params.res_band_2, 0.3);
+            let overall = || {
+                params.broadband_level_db(SAMPLE_RATE) + util::gain_to_db(params.output_gain.value())
+            };
+            let before = overall();
+            let level_before = params.broadband_level_db(SAMPLE_RATE);
+            set_param(&params.type_2, FilterType::Notch);
+            if compensate {
+                params.compensate_type_change(&setter, level_before, SAMPLE_RATE);
+            }
+            overall() - before
+        };
+
+        let uncompensated = level_change(false);
+        let compensated = level_change(true);
+        assert!(uncompensated.abs() > 1.0, "{uncompensated} dB");
+        assert!(compensated.abs() < 0.1, "{compensated} dB against {uncompensated} dB");
+    }
+
+    #[test]
+    fn snapped_gain_drags_land_on_the_grid() {
+        let params = InterleafParams::default();
+        let gain_param = &params.gain_band_2;
+        for grid_db in [0.5, 1.0] {
+            // The step the editor gives the gain sliders for this grid
+            let step = gain_param.preview_normalized(grid_db) - gain_param.preview_normalized(0.0);
+            for position in 0..=1000 {
+                let dragged = position as f32 / 1000.0;
+                let snapped = CustomVerticalSlider::snap_normalized(dragged, step);
+                let gain = gain_param.preview_plain(snapped);
+                let steps = gain / grid_db;
+                assert!((steps - steps.round()).abs() < 1e-3, "{dragged} snapped to {gain} dB on a {grid_db} dB grid");
+                let unsnapped = gain_param.preview_plain(dragged);
+                assert!((gain - unsnapped).abs() <= grid_db / 2.0 + 1e-4, "{unsnapped} dB snapped to {gain} dB");
+            }
+        }
+        // No grid leaves the drag alone
+        assert_eq!(CustomVerticalSlider::snap_normalized(0.123, 0.0), 0.123);
+    }
+
+    #[test]
+    fn side_eq_leaves_the_mid_untouched() {
+        let mut plugin = prepared_plugin();
+        let params = plugin.params.clone();
+        set_param(&params.side_only, true);
+        set_param(&params.interleaves, 1.0);
+        set_param(&params.freq_band_2, 3000.0);
+        set_param(&params.gain_band_2, 12.0);
+        let host = TestHost { editor_open: false };
+        let samples = SAMPLE_RATE as usize / 4;
+
+        // No side at all comes out bit for bit what went in, boost or not
+        let input = sine(300.0, 0.5, samples);
+        let mut left = input.clone();
+        let mut right = input.clone();
+        process_channels(&mut plugin, &host, &mut [&mut left, &mut right], 256);
+        assert_eq!(left, input);
+        assert_eq!(right, input);
+
+        // With a side on top the mid only picks up the rounding of splitting it back into left
+        // and right, while the side gets the boost
+        let mid = sine(300.0, 0.5, samples);
+        let side = sine(3000.0, 0.1, samples);
+        let mut left: Vec<f32> = mid.iter().zip(&side).map(|(m, s)| m + s).collect();
+        let mut right: Vec<f32> = mid.iter().zip(&side).map(|(m, s)| m - s).collect();
+        let input_mid: Vec<f32> = left.iter().zip(&right).map(|(l, r)| (l + r) / 2.0).collect();
+        process_channels(&mut plugin, &host, &mut [&mut left, &mut right], 256);
+        for (index, ((l, r), expected)) in left.iter().zip(&right).zip(&input_mid).enumerate() {
+            let output_mid = (l + r) / 2.0;
+            assert!((output_mid - expected).abs() <= f32::EPSILON, "sample {index}: {output_mid} against {expected}");
+        }
+        let settled = samples / 2;
+        let peak = |samples: &[f32]| samples.iter().fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        let output_side: Vec<f32> = left.iter().zip(&right).map(|(l, r)| (l - r) / 2.0).collect();
+        let side_gain = peak(&output_side[settled..]) / peak(&side[settled..]);
+        assert!(side_gain > 1.5, "side came out at {side_gain}x");
+    }
+
+    #[test]
+    fn mono_and_stereo_buffers_are_processed_alike() {
+        let host = TestHost { editor_open: false };
+        let input = sine(1000.0, 0.5, 4096);
+        let boosted = || {
+            let plugin = prepared_plugin();
+            set_param(&plugin.params.gain_band_2, 6.0);
+            set_param(&plugin.params.gain_band_4, -4.0);
+            plugin
+        };
+
+        let mut stereo = boosted();
+        let mut left = input.clone();
+        let mut right = input.clone();
+        process_channels(&mut stereo, &host, &mut [&mut left, &mut right], 256);
+        assert_ne!(left, input);
+        assert_eq!(left, right);
+
+        // A mono buffer runs its one channel through both sides, so it matches either side above
+        let mut mono = boosted();
+        let mut only = input.clone();
+        process_channels(&mut mono, &host, &mut [&mut only], 256);
+        assert_eq!(only, left);
+
+        // Past the second channel everything is left as it came in
+        let mut surround = boosted();
+        let mut first = input.clone();
+        let mut second = input.clone();
+        let mut third = input.clone();
+        process_channels(&mut surround, &host, &mut [&mut first, &mut second, &mut third], 256);
+        assert_eq!(first, left);
+        assert_eq!(third, input);
+    }
+
+    #[test]
+    fn frequency_steps_grow_with_the_frequency() {
+        let params = InterleafParams::default();
+        let freq_param = &params.freq_band_2;
+        // One arrow key step up from a frequency, through the param the way the knob does it
+        let step_from = |freq: f32| {
+            let normalized = freq_param.preview_normalized(freq);
+            let stepped = freq_param.preview_normalized(step_frequency(freq_param.preview_plain(normalized), FREQ_STEP_CENTS));
+            freq_param.preview_plain(stepped) - freq_param.preview_plain(normalized)
+        };
+
+        let low = step_from(50.0);
+        let high = step_from(10000.0);
+        assert!(low > 0.0 && low < 0.5, "{low} Hz at 50 Hz");
+        assert!(high > 10.0, "{high} Hz at 10 kHz");
+        // The same few cents either way
+        let cents = |from: f32, step: f32| 1200.0 * ((from + step) / from).log2();
+        assert!((cents(50.0, low) - FREQ_STEP_CENTS).abs() < 0.5, "{} cents", cents(50.0, low));
+        assert!((cents(10000.0, high) - FREQ_STEP_CENTS).abs() < 0.5, "{} cents", cents(10000.0, high));
+    }
+
+    #[test]
+    fn x2_makeup_keeps_the_band_center_gain() {
+        let params = InterleafParams::default();
+        set_param(&params.freq_band_2, 1000.0);
+        set_param(&params.gain_band_2, 9.0);
+        let settings = params.band_settings(0.0);
+        // Steady state level in dB of a 1 kHz sine through the band chain
+        let center_gain = |oversampling: usize| {
+            let mut eq = EQ::new();
+            eq.set_bands(&params, &settings, SAMPLE_RATE, 1.0, oversampling);
+            let input = sine(1000.0, 0.5, SAMPLE_RATE as usize / 2);
+            let settled = input.len() / 2;
+            let (mut input_power, mut output_power) = (0.0, 0.0);
+            for (index, sample) in input.iter().enumerate() {
+                let (output, _) = eq.process_chain(*sample, *sample, 1.0, oversampling, false);
+                if index >= settled {
+                    input_power += sample * sample;
+                    output_power += output * output;
+                }
+            }
+            10.0 * (output_power / input_power).log10()
+        };
+
+        assert!(params.os_makeup.value());
+        let single = center_gain(0);
+        let cascaded = center_gain(1);
+        assert!(single > 1.0, "{single} dB");
+        assert!((cascaded - single).abs() < 0.05, "{cascaded} dB with x2 against {single} dB");
+
+        // Without makeup the cascade stacks the band on itself
+        set_param(&params.os_makeup, false);
+        let stacked = center_gain(1);
+        assert!((stacked - 2.0 * single).abs() < 0.1, "{stacked} dB against {single} dB");
+    }
+
+    #[test]
+    fn true_peak_mode_reads_inter_sample_overs() {
+        let host = TestHost { editor_open: true };
+        // Every sample sits 3 dB under the waveform's real peak
+        let input: Vec<f32> = (0..4800)
+            .map(|i| 0.9 * (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin())
+            .collect();
+        let output_peak = |true_peak: bool| {
+            let mut plugin = prepared_plugin();
+            plugin.params.true_peak_meter.store(true_peak, std::sync::atomic::Ordering::Relaxed);
+            let mut left = input.clone();
+            let mut right = input.clone();
+            process_channels(&mut plugin, &host, &mut [&mut left, &mut right], 256);
+            util::gain_to_db(plugin.out_meter_peak.load(std::sync::atomic::Ordering::Relaxed))
+        };
+
+        let sample_peak = output_peak(false);
+        let true_peak = output_peak(true);
+        assert!(true_peak - sample_peak > 2.0, "{true_peak} dB true peak against {sample_peak} dB");
+        assert!(true_peak < 0.0, "{true_peak} dB");
+    }
+
+    #[test]
+    fn fractional_interleave_lies_between_its_neighbours() {
+        let params = InterleafParams::default();
+        set_param(&params.freq_band_2, 2000.0);
+        set_param(&params.gain_band_2, 9.0);
+        set_param(&params.gain_band_4, -6.0);
+        let settings = params.band_settings(0.0);
+        let prepared = |interleave: f32| {
+            let mut eq = EQ::new();
+            eq.set_bands(&params, &settings, SAMPLE_RATE, interleave, 0);
+            eq
+        };
+        let (mut lower, mut upper, mut between) = (prepared(2.0), prepared(3.0), prepared(2.25));
+
+        // On the linear curve each output sample is a mix of what the two whole counts give
+        let mut moved_off_both = false;
+        for (index, sample) in sine(1500.0, 0.5, 4096).iter().enumerate() {
+            let (low, _) = lower.process(*sample, *sample, 2.0, 0, BlendCurve::Linear);
+            let (high, _) = upper.process(*sample, *sample, 3.0, 0, BlendCurve::Linear);
+            let (mixed, _) = between.process(*sample, *sample, 2.25, 0, BlendCurve::Linear);
+            assert!(
+                mixed >= low.min(high) - 1e-6 && mixed <= low.max(high) + 1e-6,
+                "sample {index}: {mixed} outside {low} and {high}"
+            );
+            assert!((mixed - (0.75 * low + 0.25 * high)).abs() < 1e-6, "sample {index}");
+            moved_off_both |= (mixed - low).abs() > 1e-3 && (mixed - high).abs() > 1e-3;
+        }
+        assert!(moved_off_both);
+    }
+
+    #[test]
+    fn wet_past_full_pushes_the_difference_further() {
+        let host = TestHost { editor_open: false };
+        let input = sine(3000.0, 0.5, 4096);
+        let run = |wet: f32| {
+            let mut plugin = prepared_plugin();
+            set_param(&plugin.params.interleaves, 1.0);
+            set_param(&plugin.params.freq_band_2, 3000.0);
+            set_param(&plugin.params.gain_band_2, 9.0);
+            set_param(&plugin.params.dry_wet, wet);
+            let mut left = input.clone();
+            let mut right = input.clone();
+            process_channels(&mut plugin, &host, &mut [&mut left, &mut right], 256);
+            left
+        };
+
+        // 150% is the dry plus one and a half times what the EQ changed
+        let full = run(1.0);
+        let past = run(1.5);
+        for (index, ((dry, wet), mixed)) in input.iter().zip(&full).zip(&past).enumerate() {
+            let expected = dry + (wet - dry) * 1.5;
+            assert!((mixed - expected).abs() < 1e-5, "sample {index}: {mixed} against {expected}");
+        }
+    }
+
+    #[test]
+    fn full_scale_mono_tone_meters_at_full_scale() {
+        let mut plugin = prepared_plugin();
+        let host = TestHost { editor_open: true };
+        let mut only = sine(1000.0, 1.0, SAMPLE_RATE as usize / 2);
+        process_channels(&mut plugin, &host, &mut [&mut only], 256);
+
+        // Summing a missing right channel in would read +6 dB, averaging over two would read -6 dB
+        let read = |meter: &Arc<AtomicF32>| util::gain_to_db(meter.load(std::sync::atomic::Ordering::Relaxed));
+        for (name, meter) in [("input peak", &plugin.in_meter_peak), ("output peak", &plugin.out_meter_peak)] {
+            let level = read(meter);
+            assert!(level.abs() < 0.5, "{name} read {level} dBFS");
+        }
+        for (name, meter) in [("input", &plugin.in_meter), ("output", &plugin.out_meter)] {
+            let level = read(meter);
+            assert!(level < 0.01, "{name} read {level} dBFS");
+        }
+    }
+
+    #[test]
+    fn shelf_drive_adds_harmonics_but_stays_clean_when_quiet() {
+        let params = InterleafParams::default();
+        set_param(&params.type_4, FilterType::HighShelf);
+        set_param(&params.freq_band_4, 2000.0);
+        set_param(&params.gain_band_4, 9.0);
+        let run = |drive: f32, level: f32| {
+            set_param(&params.drive_4, drive);
+            let mut eq = EQ::new();
+            eq.set_bands(&params, &params.band_settings(0.0), SAMPLE_RATE, 1.0, 0);
+            sine(3000.0, level, 9600)
+                .iter()
+                .map(|sample| eq.process_chain(*sample, *sample, 1.0, 0, false).0)
+                .collect::<Vec<f32>>()
+        };
+        // Amplitude of one frequency over the settled half, a whole number of cycles long
+        let amplitude = |samples: &[f32], freq: f32| {
+            let settled = &samples[samples.len() / 2..];
+            let (re, im) = settled.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, sample)| {
+                let phase = 2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE;
+                (re + sample * phase.cos(), im - sample * phase.sin())
+            });
+            2.0 * (re * re + im * im).sqrt() / settled.len() as f32
+        };
+
+        // The shelf's tanh only makes odd harmonics, the third grows with the drive
+        let thirds = [0.0, 0.25, 0.5, 1.0].map(|drive| amplitude(&run(drive, 0.5), 9000.0));
+        assert!(thirds[0] < 1e-4, "clean shelf has a third of {}", thirds[0]);
+        assert!(thirds[1] > 1e-3, "{thirds:?}");
+        assert!(thirds.windows(2).all(|pair| pair[1] > pair[0]), "{thirds:?}");
+
+        // Quiet material hardly touches the curve so it comes out like the clean shelf
+        let clean = amplitude(&run(0.0, 0.001), 3000.0);
+        let driven = amplitude(&run(1.0, 0.001), 3000.0);
+        assert!(util::gain_to_db(driven / clean).abs() < 0.05, "{clean} against {driven}");
+    }
+
+    #[test]
+    fn input_of_minus_two_is_processed_like_any_other() {
+        let params = InterleafParams::default();
+        set_param(&params.gain_band_2, 6.0);
+        let settings = params.band_settings(0.0);
+        for interleave in [1.0, 4.0] {
+            let mut unit = EQ::new();
+            let mut doubled = EQ::new();
+            unit.set_bands(&params, &settings, SAMPLE_RATE, interleave, 0);
+            doubled.set_bands(&params, &settings, SAMPLE_RATE, interleave, 0);
+            // The chain is linear so an impulse of -2 comes out as exactly -2 times the unit
+            // impulse's response, the first sample included
+            for index in 0..64 {
+                let input = if index == 0 { 1.0 } else { 0.0 };
+                let (unit_l, unit_r) = unit.process_chain(input, input, interleave, 0, false);
+                let (l, r) = doubled.process_chain(-2.0 * input, -2.0 * input, interleave, 0, false);
+                assert_eq!((l, r), (-2.0 * unit_l, -2.0 * unit_r), "sample {index} at interleave {interleave}");
+            }
+        }
+    }
+
+    #[test]
+    fn concert_a_is_note_69() {
+        assert_eq!(note_number(440.0), (69, 0));
+        assert_eq!(note_number(880.0), (81, 0));
+        // A little sharp and a little flat of A rounds to it with the cents either side
+        assert_eq!(note_number(445.0), (69, 20));
+        assert_eq!(note_number(435.0), (69, -20));
+
+        let format = format_frequency(Arc::new(AtomicBool::new(true)), Arc::new(AtomicBool::new(true)));
+        let shown = format(440.0);
+        assert!(shown.ends_with(", 69 +0c"), "{shown}");
+    }
+
+    #[test]
+    fn listen_normalisation_evens_out_narrow_and_broad_bands() {
+        let host = TestHost { editor_open: true };
+        let samples = SAMPLE_RATE as usize;
+        let mut noise = test_signal::PinkNoise::new();
+        let input: Vec<f32> = (0..samples).map(|_| noise.next_sample()).collect();
+        // RMS in dB of band 2 auditioned on pink noise once the listen fade is done
+        let audition = |res: f32, normalise: bool| {
+            let mut plugin = prepared_plugin();
+            set_param(&plugin.params.freq_band_2, 1000.0);
+            set_param(&plugin.params.res_band_2, res);
+            plugin.params.listen_level.store(normalise, std::sync::atomic::Ordering::Relaxed);
+            plugin.listen_band.store(2, std::sync::atomic::Ordering::Relaxed);
+            let mut left = input.clone();
+            let mut right = input.clone();
+            process_channels(&mut plugin, &host, &mut [&mut left, &mut right], 256);
+            let settled = &left[samples / 4..];
+            10.0 * (settled.iter().map(|sample| sample * sample).sum::<f32>() / settled.len() as f32).log10()
+        };
+
+        let raw_gap = audition(0.3, false) - audition(8.0, false);
+        let normalised_gap = audition(0.3, true) - audition(8.0, true);
+        assert!(raw_gap > 9.0, "{raw_gap} dB between broad and narrow");
+        assert!(normalised_gap.abs() < 3.0, "{normalised_gap} dB between broad and narrow");
+    }
+
+    #[test]
+    fn legacy_interleave_and_x2_values_load_as_what_they_meant() {
+        use nih_plug::wrapper::state::ParamValue;
+        let legacy = [
+            ("oversampling", ParamValue::Bool(true), 1.0),
+            ("interleaves", ParamValue::I32(3), 3.0),
+            ("interleaves_low", ParamValue::F32(f32::NAN), 4.0),
+            ("interleaves_mid", ParamValue::F32(2.9999), 3.0),
+            ("interleaves_high", ParamValue::I32(25), 10.0),
+        ];
+        let expected: Vec<(&str, f32)> = legacy.iter().map(|(id, _, expected)| (*id, *expected)).collect();
+        let mut state = PluginState {
+            version: String::new(),
+            params: legacy.into_iter().map(|(id, value, _)| (id.to_string(), value)).collect(),
+            fields: std::collections::BTreeMap::new(),
+        };
+        state.params.insert("gain_band_2".to_string(), ParamValue::F32(3.0));
+        Interleaf::filter_state(&mut state);
+
+        // Everything comes back as the float the params are now, on a value they can take
+        let params = InterleafParams::default();
+        let by_id: std::collections::HashMap<String, ParamPtr> =
+            params.param_map().into_iter().map(|(id, ptr, _)| (id, ptr)).collect();
+        for (id, expected) in expected {
+            let ParamValue::F32(repaired) = state.params[id] else {
+                panic!("{id} wasn't made a float");
+            };
+            assert_eq!(repaired, expected, "{id}");
+            // And loads the way the wrapper would set it
+            unsafe {
+                let param = by_id[id];
+                param.set_normalized_value(param.preview_normalized(repaired));
+                let loaded = param.modulated_plain_value();
+                assert!((loaded - expected).abs() < 1e-4, "{id} loaded as {loaded}");
+            }
+        }
+        assert!(matches!(state.params["gain_band_2"], ParamValue::F32(gain) if gain == 3.0));
+
+        // Off settings stay off
+        let mut state = PluginState {
+            version: String::new(),
+            params: [("oversampling".to_string(), ParamValue::F32(0.2))].into_iter().collect(),
+            fields: std::collections::BTreeMap::new(),
+        };
+        Interleaf::filter_state(&mut state);
+        assert!(matches!(state.params["oversampling"], ParamValue::F32(x2) if x2 == 0.0));
+    }
+
+    #[test]
+    fn meter_window_sets_the_reading_and_buffer_size_does_not() {
+        let host = TestHost { editor_open: true };
+        // 20 ms tone bursts every 100 ms, read at the end of the last burst
+        let period = (SAMPLE_RATE * 0.1) as usize;
+        let burst = period / 5;
+        let tone = sine(1000.0, 0.5, period * 20);
+        let input: Vec<f32> = (0..period * 19 + burst)
+            .map(|i| if i % period < burst { tone[i] } else { 0.0 })
+            .collect();
+        let readings = |window_ms: f32, block_size: usize| {
+            let mut plugin = prepared_plugin();
+            plugin.params.meter_window.store(window_ms, std::sync::atomic::Ordering::Relaxed);
+            let mut left = input.clone();
+            let mut right = input.clone();
+            process_channels(&mut plugin, &host, &mut [&mut left, &mut right], block_size);
+            (
+                plugin.in_meter.load(std::sync::atomic::Ordering::Relaxed),
+                plugin.out_meter.load(std::sync::atomic::Ordering::Relaxed),
+            )
+        };
+
+        for window_ms in [50.0, 1000.0] {
+            let (small_in, small_out) = readings(window_ms, 64);
+            let (large_in, large_out) = readings(window_ms, 1024);
+            assert!((small_in - large_in).abs() < 1e-6, "{small_in} against {large_in}");
+            assert!((small_out - large_out).abs() < 1e-6, "{small_out} against {large_out}");
+        }
+        // A short window follows the bursts, a long one settles near the average over all of them
+        let (short, _) = readings(50.0, 256);
+        let (long, _) = readings(1000.0, 256);
+        let average = (0.5f32 * 0.5 / 2.0 / 5.0).sqrt();
+        assert!(short > long * 1.2, "{short} against {long}");
+        assert!((util::gain_to_db(long) - util::gain_to_db(average)).abs() < 1.0, "{long} against {average}");
+    }
+
+    #[test]
+    fn half_band_wet_applies_half_the_band() {
+        let params = InterleafParams::default();
+        set_param(&params.freq_band_2, 2000.0);
+        set_param(&params.gain_band_2, 12.0);
+        let response = |wet: f32| {
+            set_param(&params.wet_2, wet);
+            let mut eq = EQ::new();
+            eq.set_bands(&params, &params.band_settings(0.0), SAMPLE_RATE, 1.0, 0);
+            sine(2000.0, 0.5, 4096)
+                .iter()
+                .map(|sample| eq.process_chain(*sample, *sample, 1.0, 0, false).0)
+                .collect::<Vec<f32>>()
+        };
+
+        // Half of what the band changes, sample by sample rather than half its dB
+        let full = response(1.0);
+        let half = response(0.5);
+        let input = sine(2000.0, 0.5, 4096);
+        for (index, ((dry, full), half)) in input.iter().zip(&full).zip(&half).enumerate() {
+            let expected = dry + (full - dry) * 0.5;
+            assert!((half - expected).abs() < 1e-6, "sample {index}: {half} against {expected}");
+        }
+        assert!(full.iter().zip(&input).any(|(full, dry)| (full - dry).abs() > 0.1));
+    }
+}
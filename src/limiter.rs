@@ -0,0 +1,205 @@
+// limiter.rs - Ardura 2023
+// A lookahead brickwall limiter for the output
+// The needed gain is found ahead of time through a delay line so peaks never get past the ceiling:
+//  1. Every incoming sample works out the gain it needs to sit at the ceiling
+//  2. A sliding window minimum holds that gain for the whole lookahead
+//  3. A box filter the same length as the window ramps into it (this is the attack)
+//  4. Release is a one pole that can only ever pull the gain lower, never higher
+// Because every value in the box filter's window covers the delayed sample we never overshoot
+
+// This is for my sanity
+const LEFT: usize = 0;
+const RIGHT: usize = 1;
+
+pub(crate) struct LookaheadLimiter {
+    // Lookahead in samples, this is also our latency
+    lookahead: usize,
+    // Delay lines for the audio
+    delay: [Vec<f32>; 2],
+    // Monotonic queue of (position, needed gain) used as a ring for the sliding minimum
+    min_queue: Vec<(usize, f32)>,
+    queue_head: usize,
+    queue_len: usize,
+    // Box filter history over the sliding minimum
+    smooth_history: Vec<f32>,
+    smooth_sum: f64,
+    // Running sample position
+    position: usize,
+    // Current applied gain and release coefficient
+    gain: f32,
+    release_coeff: f32,
+}
+
+impl LookaheadLimiter {
+    /// Allocates for up to `max_lookahead` samples, this should only be called outside of process
+    pub fn new(max_lookahead: usize) -> Self {
+        let mut limiter = LookaheadLimiter {
+            lookahead: 0,
+            delay: [vec![0.0; max_lookahead.max(1)], vec![0.0; max_lookahead.max(1)]],
+            min_queue: vec![(0, 1.0); max_lookahead + 2],
+            queue_head: 0,
+            queue_len: 0,
+            smooth_history: vec![1.0; max_lookahead + 1],
+            smooth_sum: 0.0,
+            position: 0,
+            gain: 1.0,
+            release_coeff: 0.0,
+        };
+        limiter.set_lookahead(max_lookahead);
+        limiter
+    }
+
+    /// Changing the lookahead clears the limiter since the delay line changes length
+    pub fn set_lookahead(&mut self, lookahead: usize) {
+        self.lookahead = lookahead.min(self.delay[LEFT].len());
+        self.reset();
+    }
+
+    pub fn lookahead(&self) -> usize {
+        self.lookahead
+    }
+
+    /// The longest lookahead the delay line was allocated for
+    pub fn max_lookahead(&self) -> usize {
+        self.delay[LEFT].len()
+    }
+
+    /// Release time in milliseconds
+    pub fn set_release(&mut self, release_ms: f32, sample_rate: f32) {
+        self.release_coeff = (-1.0 / (release_ms / 1000.0 * sample_rate)).exp();
+    }
+
+    /// Current gain reduction as a linear gain, 1.0 means nothing is being limited
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    pub fn reset(&mut self) {
+        for channel in self.delay.iter_mut() {
+            channel.iter_mut().for_each(|sample| *sample = 0.0);
+        }
+        self.queue_head = 0;
+        self.queue_len = 0;
+        self.smooth_history.iter_mut().for_each(|gain| *gain = 1.0);
+        self.smooth_sum = self.window() as f64;
+        self.position = 0;
+        self.gain = 1.0;
+    }
+
+    // The window covers the delayed sample and everything we can see ahead of it
+    fn window(&self) -> usize {
+        self.lookahead + 1
+    }
+
+    /// Takes the ceiling as a linear gain and returns the delayed, limited samples
+    pub fn process_sample(&mut self, input_l: f32, input_r: f32, ceiling: f32) -> (f32, f32) {
+        let window = self.window();
+        let capacity = self.min_queue.len();
+
+        // Gain this sample needs to sit at the ceiling, linked between channels
+        let peak = input_l.abs().max(input_r.abs());
+        let needed = if peak > ceiling { ceiling / peak } else { 1.0 };
+
+        // Sliding window minimum - anything behind us that needs less reduction can never be the min
+        while self.queue_len > 0 {
+            let back = (self.queue_head + self.queue_len - 1) % capacity;
+            if self.min_queue[back].1 >= needed {
+                self.queue_len -= 1;
+            } else {
+                break;
+            }
+        }
+        self.min_queue[(self.queue_head + self.queue_len) % capacity] = (self.position, needed);
+        self.queue_len += 1;
+        if self.position - self.min_queue[self.queue_head].0 >= window {
+            self.queue_head = (self.queue_head + 1) % capacity;
+            self.queue_len -= 1;
+        }
+        let window_min = self.min_queue[self.queue_head].1;
+
+        // Box filter the minimum so we ramp into the reduction over the lookahead
+        let slot = self.position % window;
+        self.smooth_sum += (window_min - self.smooth_history[slot]) as f64;
+        self.smooth_history[slot] = window_min;
+        let smoothed = ((self.smooth_sum / window as f64) as f32).min(1.0);
+
+        // Release can only hold the gain down, attack is instant since the box filter already ramped
+        self.gain = if smoothed < self.gain {
+            smoothed
+        } else {
+            smoothed + (self.gain - smoothed) * self.release_coeff
+        };
+
+        // Delay the audio by the lookahead so the gain lines up with it
+        let (output_l, output_r) = if self.lookahead == 0 {
+            (input_l, input_r)
+        } else {
+            let delay_slot = self.position % self.lookahead;
+            let delayed = (self.delay[LEFT][delay_slot], self.delay[RIGHT][delay_slot]);
+            self.delay[LEFT][delay_slot] = input_l;
+            self.delay[RIGHT][delay_slot] = input_r;
+            delayed
+        };
+        self.position += 1;
+
+        (output_l * self.gain, output_r * self.gain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    // Everything that comes out for the input pairs, with the lookahead flushed out after
+    fn run(limiter: &mut LookaheadLimiter, input: &[(f32, f32)], ceiling: f32) -> Vec<(f32, f32)> {
+        let flush = vec![(0.0, 0.0); limiter.lookahead()];
+        input
+            .iter()
+            .chain(flush.iter())
+            .map(|(left, right)| limiter.process_sample(*left, *right, ceiling))
+            .collect()
+    }
+
+    #[test]
+    fn full_scale_step_never_passes_the_ceiling() {
+        let mut limiter = LookaheadLimiter::new(240);
+        limiter.set_release(50.0, SAMPLE_RATE);
+        let ceiling = 0.5;
+        // Silence then straight to full scale on both channels, then one channel alone
+        let mut input = vec![(0.0, 0.0); 1000];
+        input.extend(vec![(1.0, -1.0); 2000]);
+        input.extend(vec![(0.0, 1.0); 2000]);
+        let output = run(&mut limiter, &input, ceiling);
+        let peak = output.iter().fold(0.0_f32, |peak, (left, right)| peak.max(left.abs()).max(right.abs()));
+        assert!(peak <= ceiling * (1.0 + 1e-6), "{peak}");
+        // It limits rather than just turning down, the held part sits at the ceiling
+        assert!((output[2500].0.abs() - ceiling).abs() < 1e-3);
+    }
+
+    #[test]
+    fn transients_never_pass_the_ceiling() {
+        let ceiling = 10.0_f32.powf(-1.0 / 20.0);
+        for lookahead in [0, 1, 64, 240] {
+            let mut limiter = LookaheadLimiter::new(240);
+            limiter.set_lookahead(lookahead);
+            limiter.set_release(20.0, SAMPLE_RATE);
+            // A resonant tone with clicks over it, well over the ceiling
+            let input: Vec<(f32, f32)> = (0..20000)
+                .map(|index| {
+                    let tone = 1.5 * (index as f32 * 0.07).sin();
+                    let click = if index % 997 == 0 { 4.0 } else { 0.0 };
+                    (tone + click, tone - click)
+                })
+                .collect();
+            let output = run(&mut limiter, &input, ceiling);
+            for (index, (left, right)) in output.iter().enumerate() {
+                assert!(
+                    left.abs() <= ceiling * (1.0 + 1e-6) && right.abs() <= ceiling * (1.0 + 1e-6),
+                    "sample {index} with {lookahead} lookahead: {left} {right}"
+                );
+            }
+        }
+    }
+}
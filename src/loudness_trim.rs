@@ -0,0 +1,72 @@
+// loudness_trim.rs - Ardura 2023
+// One shot output trim so the EQ'd signal is as loud as what came in, for honest A/B against bypass
+// Measures the RMS of the raw input and of the output before the output gain over a short window,
+// the GUI then sets the output gain to the ratio as one undoable gesture.
+
+/// How long the measurement listens for
+pub(crate) const TRIM_SECONDS: f32 = 2.0;
+/// Either side quieter than this over the window gives no result
+const TRIM_MIN_RMS_DB: f32 = -50.0;
+
+// Measurement states shared with the GUI
+pub(crate) const TRIM_IDLE: u32 = 0;
+pub(crate) const TRIM_MEASURING: u32 = 1;
+pub(crate) const TRIM_READY: u32 = 2;
+pub(crate) const TRIM_TOO_QUIET: u32 = 3;
+
+pub(crate) struct TrimMeasure {
+    input_energy: f64,
+    output_energy: f64,
+    samples: usize,
+    samples_left: usize,
+    active: bool,
+}
+
+impl TrimMeasure {
+    pub fn new() -> Self {
+        TrimMeasure {
+            input_energy: 0.0,
+            output_energy: 0.0,
+            samples: 0,
+            samples_left: 0,
+            active: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn start(&mut self, sample_rate: f32) {
+        self.input_energy = 0.0;
+        self.output_energy = 0.0;
+        self.samples = 0;
+        self.samples_left = (TRIM_SECONDS * sample_rate) as usize;
+        self.active = true;
+    }
+
+    /// Feed one stereo sample of each, returns true once the window is full
+    pub fn push(&mut self, input_l: f32, input_r: f32, output_l: f32, output_r: f32) -> bool {
+        if !self.active {
+            return false;
+        }
+        self.input_energy += (input_l * input_l + input_r * input_r) as f64;
+        self.output_energy += (output_l * output_l + output_r * output_r) as f64;
+        self.samples += 1;
+        self.samples_left = self.samples_left.saturating_sub(1);
+        self.samples_left == 0
+    }
+
+    /// Output gain that brings the output RMS to the input RMS, None if either was too quiet
+    pub fn finish(&mut self) -> Option<f32> {
+        self.active = false;
+        let count = (self.samples * 2).max(1) as f64;
+        let input_rms = (self.input_energy / count).sqrt() as f32;
+        let output_rms = (self.output_energy / count).sqrt() as f32;
+        let min_rms = 10.0_f32.powf(TRIM_MIN_RMS_DB / 20.0);
+        if input_rms < min_rms || output_rms < min_rms {
+            return None;
+        }
+        Some(input_rms / output_rms)
+    }
+}
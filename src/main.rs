@@ -0,0 +1,9 @@
+// main.rs - Ardura 2023
+// Standalone entry, the same plugin through nih_plug's JACK/CPAL wrapper on the default audio I/O
+
+use nih_plug::prelude::*;
+use Interleaf::Interleaf;
+
+fn main() {
+    nih_export_standalone::<Interleaf>();
+}
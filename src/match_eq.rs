@@ -0,0 +1,328 @@
+// match_eq.rs - Ardura 2023
+// Match EQ from a reference on the sidechain input
+//  1. Capture averages the power spectrum of the main input and the sidechain for a few seconds
+//  2. The difference between them (reference - input) is what the bands need to make up
+//  3. Apply lays the bands out as shelves and peaks and fits their gains with least squares
+// Process only fills the rings, the background task does the FFTs and the averaging and hands
+// the result over once, fitting happens in the GUI
+
+use crate::analyzer::{self, Analyzer};
+use crate::biquad_filters::{Biquad, FilterType};
+use crate::InterleafParams;
+use nih_plug::prelude::{Param, ParamSetter};
+use std::sync::atomic::Ordering;
+
+/// How long the capture listens for
+pub(crate) const CAPTURE_SECONDS: f32 = 4.0;
+/// A capture whose reference stays under this is treated as a missing sidechain
+const REFERENCE_SILENCE_DB: f32 = -70.0;
+
+// Capture states shared with the GUI
+pub(crate) const MATCH_IDLE: u32 = 0;
+pub(crate) const MATCH_CAPTURING: u32 = 1;
+pub(crate) const MATCH_READY: u32 = 2;
+pub(crate) const MATCH_NO_SIDECHAIN: u32 = 3;
+/// Heard enough, waiting on the background task to finish the average
+pub(crate) const MATCH_AVERAGING: u32 = 4;
+
+// Where the fitted bands go in order, (type, center, Q)
+const FIT_LAYOUT: [(FilterType, f32, f32); 5] = [
+    (FilterType::LowShelf, 90.0, 0.707),
+    (FilterType::Peak, 300.0, 0.9),
+    (FilterType::Peak, 1000.0, 0.9),
+    (FilterType::Peak, 3500.0, 0.9),
+    (FilterType::HighShelf, 9000.0, 0.707),
+];
+// The fit compares the curves at this many log spaced points over this range
+const FIT_POINTS: usize = 64;
+const FIT_MIN_FREQ: f32 = 30.0;
+const FIT_MAX_FREQ: f32 = 16000.0;
+// Each fit point averages the difference over this many octaves so single bins don't dominate
+const FIT_SMOOTHING_OCTAVES: f32 = 1.0 / 3.0;
+
+/// Audio thread half of a capture: fills the rings and hands each pair of frames to the background
+/// task, which does the FFTs and the averaging in `MatchAverage`
+pub(crate) struct MatchCapture {
+    input: Analyzer,
+    reference: Analyzer,
+    frames_sent: usize,
+    samples_left: usize,
+    active: bool,
+}
+
+impl MatchCapture {
+    pub fn new() -> Self {
+        MatchCapture {
+            input: Analyzer::new(),
+            reference: Analyzer::new(),
+            frames_sent: 0,
+            samples_left: 0,
+            active: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn start(&mut self, sample_rate: f32) {
+        self.frames_sent = 0;
+        self.samples_left = (CAPTURE_SECONDS * sample_rate) as usize;
+        self.active = true;
+    }
+
+    /// Feed one mono sample of each, returns true when there's a new pair of frames to copy out
+    pub fn push(&mut self, input: f32, reference: f32) -> bool {
+        if !self.active {
+            return false;
+        }
+        let input_frame = self.input.push(input);
+        let reference_frame = self.reference.push(reference);
+        self.samples_left = self.samples_left.saturating_sub(1);
+        input_frame && reference_frame
+    }
+
+    /// Copy the input then the reference frame into `frame`, 2 * FFT_SIZE long. Returns true for
+    /// the capture's first frame so the average starts over.
+    pub fn copy_frames(&mut self, frame: &mut [f32]) -> bool {
+        let (input, reference) = frame.split_at_mut(analyzer::FFT_SIZE);
+        self.input.copy_frame(input);
+        self.reference.copy_frame(reference);
+        self.frames_sent += 1;
+        self.frames_sent == 1
+    }
+
+    /// True once, when the capture has heard enough
+    pub fn finished(&mut self) -> bool {
+        if self.active && self.samples_left == 0 {
+            self.active = false;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Background half of a capture, averages the power spectrum of each pair of frames
+pub(crate) struct MatchAverage {
+    input: Analyzer,
+    reference: Analyzer,
+    input_power: Vec<f32>,
+    reference_power: Vec<f32>,
+    frames: usize,
+}
+
+impl MatchAverage {
+    pub fn new() -> Self {
+        MatchAverage {
+            input: Analyzer::new(),
+            reference: Analyzer::new(),
+            input_power: vec![0.0; analyzer::NUM_BINS],
+            reference_power: vec![0.0; analyzer::NUM_BINS],
+            frames: 0,
+        }
+    }
+
+    /// Add a pair of frames from `MatchCapture::copy_frames`, `first` clears the last capture
+    pub fn add_frames(&mut self, frame: &[f32], first: bool) {
+        if first {
+            self.input_power.iter_mut().for_each(|power| *power = 0.0);
+            self.reference_power.iter_mut().for_each(|power| *power = 0.0);
+            self.frames = 0;
+        }
+        let (input, reference) = frame.split_at(analyzer::FFT_SIZE);
+        self.input.compute_frame(input);
+        self.reference.compute_frame(reference);
+        for (sum, db) in self.input_power.iter_mut().zip(self.input.frame()) {
+            *sum += 10.0_f32.powf(db / 10.0);
+        }
+        for (sum, db) in self.reference_power.iter_mut().zip(self.reference.frame()) {
+            *sum += 10.0_f32.powf(db / 10.0);
+        }
+        self.frames += 1;
+    }
+
+    /// Writes reference - input in dB per bin, or returns false when the reference was silent
+    pub fn finish(&mut self, difference_db: &mut [f32]) -> bool {
+        if self.frames == 0 {
+            return false;
+        }
+        let frames = self.frames as f32;
+        self.frames = 0;
+        let reference_peak = self.reference_power.iter().fold(0.0_f32, |peak, power| peak.max(*power));
+        if 10.0 * (reference_peak / frames).max(1e-12).log10() < REFERENCE_SILENCE_DB {
+            return false;
+        }
+        for ((difference, input), reference) in difference_db
+            .iter_mut()
+            .zip(self.input_power.iter())
+            .zip(self.reference_power.iter())
+        {
+            *difference = 10.0 * ((reference + 1e-12) / (input + 1e-12)).log10();
+        }
+        true
+    }
+}
+
+// Solves the square system in place with Gaussian elimination, rows that can't pivot get 0
+fn solve(matrix: &mut [[f32; 5]; 5], rhs: &mut [f32; 5]) -> [f32; 5] {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))
+            .unwrap_or(col);
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+        if matrix[col][col].abs() < 1e-9 {
+            continue;
+        }
+        let pivot_row = matrix[col];
+        let pivot_rhs = rhs[col];
+        for (row_values, rhs_value) in matrix.iter_mut().zip(rhs.iter_mut()).skip(col + 1) {
+            let factor = row_values[col] / pivot_row[col];
+            for (value, pivot_value) in row_values.iter_mut().zip(pivot_row.iter()).skip(col) {
+                *value -= factor * pivot_value;
+            }
+            *rhs_value -= factor * pivot_rhs;
+        }
+    }
+    let mut solution = [0.0; 5];
+    for row in (0..n).rev() {
+        if matrix[row][row].abs() < 1e-9 {
+            continue;
+        }
+        let known: f32 = matrix[row]
+            .iter()
+            .zip(solution.iter())
+            .skip(row + 1)
+            .map(|(value, solved)| value * solved)
+            .sum();
+        solution[row] = (rhs[row] - known) / matrix[row][row];
+    }
+    solution
+}
+
+/// Sets the bands to the fitted layout through the setter as one gesture.
+/// The fit uses each band's response at 1 dB as its basis, the same way the response graph works
+/// out interleaving and x2, so the gains line up with what the plugin actually does.
+pub(crate) fn apply_match(
+    params: &InterleafParams,
+    setter: &ParamSetter,
+    difference_db: &[f32],
+    sample_rate: f32,
+) {
+    // A fractional interleave fits against the nearest whole count
+    let interleave = params.interleaves.value().round();
+    let interleave_factor = if interleave >= 2.0 { interleave } else { 1.0 };
+    let oversampling = params.effective_oversampling(&params.band_settings(params.sweep_position(None)));
+    let passes = (oversampling + 1) as f32;
+
+    // Interleaving moves a band down by the interleave count so the params sit that much higher
+    let layout = FIT_LAYOUT.map(|(filter_type, center, q)| {
+        (filter_type, (center * interleave_factor).min(20000.0), q)
+    });
+
+    let fit_freqs: Vec<f32> = (0..FIT_POINTS)
+        .map(|i| FIT_MIN_FREQ * (FIT_MAX_FREQ / FIT_MIN_FREQ).powf(i as f32 / (FIT_POINTS - 1) as f32))
+        .collect();
+    let targets: Vec<f32> = fit_freqs
+        .iter()
+        .map(|freq| {
+            let spread = 2.0_f32.powf(FIT_SMOOTHING_OCTAVES / 2.0);
+            let low_bin = ((freq / spread) * analyzer::FFT_SIZE as f32 / sample_rate) as usize;
+            let high_bin = ((freq * spread) * analyzer::FFT_SIZE as f32 / sample_rate).ceil() as usize;
+            let last_bin = difference_db.len() - 1;
+            let bins = &difference_db[low_bin.clamp(1, last_bin)..=high_bin.min(last_bin)];
+            bins.iter().sum::<f32>() / bins.len().max(1) as f32
+        })
+        .collect();
+    let basis: Vec<[f32; 5]> = fit_freqs
+        .iter()
+        .map(|freq| {
+            layout.map(|(filter_type, center, q)| {
+                let gain = params.pass_gain(1.0, filter_type, oversampling);
+                Biquad::new(sample_rate, center, gain, q, filter_type)
+                    .frequency_response(freq * interleave_factor)
+                    * passes
+            })
+        })
+        .collect();
+
+    // Normal equations
+    let mut normal = [[0.0; 5]; 5];
+    let mut rhs = [0.0; 5];
+    for (row, target) in basis.iter().zip(targets.iter()) {
+        for ((normal_row, rhs_value), row_i) in normal.iter_mut().zip(rhs.iter_mut()).zip(row.iter()) {
+            for (cell, row_j) in normal_row.iter_mut().zip(row.iter()) {
+                *cell += row_i * row_j;
+            }
+            *rhs_value += row_i * target;
+        }
+    }
+    let gains = solve(&mut normal, &mut rhs);
+
+    let bands = params.bands();
+    for (type_param, freq_param, gain_param, res_param) in bands.iter() {
+        setter.begin_set_parameter(*type_param);
+        setter.begin_set_parameter(*freq_param);
+        setter.begin_set_parameter(*gain_param);
+        setter.begin_set_parameter(*res_param);
+    }
+    for ((type_param, freq_param, gain_param, res_param), ((filter_type, center, q), gain)) in
+        bands.iter().zip(layout.iter().zip(gains.iter()))
+    {
+        setter.set_parameter(*type_param, *filter_type);
+        setter.set_parameter(*freq_param, *center);
+        setter.set_parameter(*res_param, *q);
+        // Past the gain range the fit is out of reach so clamp through the param's own range
+        setter.set_parameter(*gain_param, gain_param.preview_plain(gain_param.preview_normalized(*gain)));
+    }
+    for (type_param, freq_param, gain_param, res_param) in bands.iter() {
+        setter.end_set_parameter(*type_param);
+        setter.end_set_parameter(*freq_param);
+        setter.end_set_parameter(*gain_param);
+        setter.end_set_parameter(*res_param);
+    }
+    params.bulk_change.store(true, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs a capture the way process and the background task split it
+    fn capture(input: impl Fn(usize) -> f32, reference: impl Fn(usize) -> f32, difference: &mut [f32]) -> bool {
+        let sample_rate = 48000.0;
+        let mut capture = MatchCapture::new();
+        let mut average = MatchAverage::new();
+        let mut frame = vec![0.0; 2 * analyzer::FFT_SIZE];
+        capture.start(sample_rate);
+        let mut index = 0;
+        while capture.is_active() {
+            if capture.push(input(index), reference(index)) {
+                let first = capture.copy_frames(&mut frame);
+                average.add_frames(&frame, first);
+            }
+            if capture.finished() {
+                return average.finish(difference);
+            }
+            index += 1;
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn same_signal_on_both_sides_matches_flat() {
+        let tone = |index: usize| 0.5 * (index as f32 * 0.05).sin() + 0.1 * (index as f32 * 0.31).sin();
+        let mut difference = vec![f32::NAN; analyzer::NUM_BINS];
+        assert!(capture(tone, tone, &mut difference));
+        assert!(difference.iter().all(|db| db.abs() < 1e-3));
+    }
+
+    #[test]
+    fn silent_reference_is_a_missing_sidechain() {
+        let tone = |index: usize| 0.5 * (index as f32 * 0.05).sin();
+        let mut difference = vec![0.0; analyzer::NUM_BINS];
+        assert!(!capture(tone, |_| 0.0, &mut difference));
+    }
+}
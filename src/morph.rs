@@ -0,0 +1,130 @@
+// morph.rs - Ardura 2023
+// Two stored band setups, A and B, and the bands part way between them
+// Each snapshot is every band's type, frequency, gain and Res in band order. Frequencies move
+// evenly in octaves, gain and Res move linearly and the type flips over halfway.
+
+use crate::biquad_filters::FilterType;
+use crate::InterleafParams;
+use atomic_float::AtomicF32;
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::Enum;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Type index, frequency, gain and Res for each of the 5 bands
+const BAND_VALUES: usize = 4;
+pub(crate) const SNAPSHOT_LEN: usize = 5 * BAND_VALUES;
+pub(crate) type Snapshot = [f32; SNAPSHOT_LEN];
+
+/// A stored snapshot the editor writes and process reads every buffer without locking. A store
+/// landing while process reads can hand it one buffer of old and new values mixed.
+pub(crate) struct SnapshotSlot {
+    stored: AtomicBool,
+    values: [AtomicF32; SNAPSHOT_LEN],
+}
+
+impl SnapshotSlot {
+    pub fn new() -> Self {
+        SnapshotSlot {
+            stored: AtomicBool::new(false),
+            values: std::array::from_fn(|_| AtomicF32::new(0.0)),
+        }
+    }
+
+    pub fn is_stored(&self) -> bool {
+        self.stored.load(Ordering::Acquire)
+    }
+
+    pub fn store(&self, snapshot: &Snapshot) {
+        for (value, new_value) in self.values.iter().zip(snapshot) {
+            value.store(*new_value, Ordering::Relaxed);
+        }
+        self.stored.store(true, Ordering::Release);
+    }
+
+    pub fn load(&self) -> Option<Snapshot> {
+        if self.is_stored() {
+            Some(std::array::from_fn(|index| self.values[index].load(Ordering::Relaxed)))
+        } else {
+            None
+        }
+    }
+}
+
+// Saved with the state like the Mutex it replaced, None until stored
+impl<'a> PersistentField<'a, Option<Snapshot>> for SnapshotSlot {
+    fn set(&self, new_value: Option<Snapshot>) {
+        match new_value {
+            Some(snapshot) => self.store(&snapshot),
+            None => self.stored.store(false, Ordering::Release),
+        }
+    }
+
+    fn map<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&Option<Snapshot>) -> R,
+    {
+        f(&self.load())
+    }
+}
+
+/// The bands as the knobs have them now
+pub(crate) fn capture(params: &InterleafParams) -> Snapshot {
+    let mut snapshot = [0.0; SNAPSHOT_LEN];
+    for (values, (type_param, freq_param, gain_param, res_param)) in
+        snapshot.chunks_exact_mut(BAND_VALUES).zip(params.bands())
+    {
+        values.copy_from_slice(&[
+            type_param.value().to_index() as f32,
+            freq_param.value(),
+            gain_param.value(),
+            res_param.value(),
+        ]);
+    }
+    snapshot
+}
+
+/// One band's type, frequency, gain and Res at `position` from A (0) to B (1)
+pub(crate) fn morph_band(a: &Snapshot, b: &Snapshot, index: usize, position: f32) -> (FilterType, f32, f32, f32) {
+    let a = &a[index * BAND_VALUES..(index + 1) * BAND_VALUES];
+    let b = &b[index * BAND_VALUES..(index + 1) * BAND_VALUES];
+    let type_index = if position < 0.5 { a[0] } else { b[0] };
+    (
+        FilterType::from_index((type_index as usize).min(FilterType::variants().len() - 1)),
+        a[1] * (b[1] / a[1]).powf(position),
+        a[2] + (b[2] - a[2]) * position,
+        a[3] + (b[3] - a[3]) * position,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn midpoint_frequency_is_the_geometric_mean() {
+        let mut a = [0.0; SNAPSHOT_LEN];
+        let mut b = [0.0; SNAPSHOT_LEN];
+        for (index, (a_freq, b_freq)) in [(100.0, 400.0), (1000.0, 250.0), (20.0, 20000.0)].into_iter().enumerate() {
+            a[index * BAND_VALUES + 1] = a_freq;
+            b[index * BAND_VALUES + 1] = b_freq;
+            let (_, freq, _, _) = morph_band(&a, &b, index, 0.5);
+            let mean = (a_freq * b_freq).sqrt();
+            assert!((freq - mean).abs() / mean < 1e-5, "{freq} Hz between {a_freq} and {b_freq} Hz");
+        }
+    }
+
+    #[test]
+    fn slot_round_trips_through_persistence() {
+        let slot = SnapshotSlot::new();
+        assert!(!slot.is_stored());
+        assert_eq!(slot.map(|snapshot| *snapshot), None);
+
+        let snapshot: Snapshot = std::array::from_fn(|index| index as f32 * 1.5);
+        slot.set(Some(snapshot));
+        assert!(slot.is_stored());
+        assert_eq!(slot.load(), Some(snapshot));
+
+        slot.set(None);
+        assert_eq!(slot.load(), None);
+    }
+}
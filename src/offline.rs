@@ -0,0 +1,108 @@
+// offline.rs - Ardura 2023
+// Renders the current settings away from the audio thread for debug and QA tools
+// Everything starts from fresh filters so the live EQ is never touched
+
+use crate::{crossover, randomize::Xorshift, run_bands, wav, InterleafParams, EQ};
+use nih_plug::util;
+use std::path::Path;
+
+/// Level of the generated test noise
+const TEST_SIGNAL_DB: f32 = -12.0;
+/// Length of the generated test noise
+const TEST_SIGNAL_SECONDS: f32 = 2.0;
+
+/// Render the wet path for a block of stereo input with the current (unsmoothed) settings.
+/// This covers input gain, Side EQ, the bands (including multiband), dry/wet and output gain.
+/// The output limiter is left out since its lookahead would shift the result in time.
+pub(crate) fn render(
+    params: &InterleafParams,
+    sample_rate: f32,
+    input: &[(f32, f32)],
+) -> Vec<(f32, f32)> {
+    let mut eq = [EQ::new(); crossover::MAX_REGIONS];
+    let mut multiband = crossover::MultibandSplit::new(
+        sample_rate,
+        params.crossover_low.value(),
+        params.crossover_high.value(),
+    );
+    let input_gain = params.input_gain.value();
+    let output_gain = params.output_gain.value();
+    let dry_wet = params.dry_wet.value();
+    let side_only = params.side_only.value();
+    let interleave = params.interleaves.value();
+    let region_interleaves = [
+        params.interleaves_low.value(),
+        params.interleaves_mid.value(),
+        params.interleaves_high.value(),
+    ];
+    // There's no transport here so a synced sweep renders at the macro position
+    let band_settings = params.band_settings(params.sweep_position(None));
+    let oversampling = params.effective_oversampling(&band_settings);
+
+    input
+        .iter()
+        .map(|(left, right)| {
+            let in_l = left * input_gain;
+            let in_r = right * input_gain;
+            let mid = (in_l + in_r) / 2.0;
+            let side = (in_l - in_r) / 2.0;
+            let (eq_in_l, eq_in_r) = if side_only { (side, side) } else { (in_l, in_r) };
+
+            let (mut processed_l, mut processed_r) = run_bands(
+                &mut eq,
+                &mut multiband,
+                params,
+                sample_rate,
+                eq_in_l,
+                eq_in_r,
+                interleave,
+                region_interleaves,
+                oversampling,
+                0,
+                &band_settings,
+                false,
+            );
+            if side_only {
+                let processed_side = processed_l;
+                processed_l = mid + processed_side;
+                processed_r = mid - processed_side;
+            }
+
+            processed_l = in_l * (1.0 - dry_wet) + processed_l * dry_wet;
+            processed_r = in_r * (1.0 - dry_wet) + processed_r * dry_wet;
+            (processed_l * output_gain, processed_r * output_gain)
+        })
+        .collect()
+}
+
+/// Reproducible stereo white noise for test renders
+pub(crate) fn test_noise(sample_rate: f32, seconds: f32) -> Vec<(f32, f32)> {
+    let mut rng = Xorshift::new(1);
+    let level = util::db_to_gain(TEST_SIGNAL_DB);
+    (0..(sample_rate * seconds) as usize)
+        .map(|_| {
+            (
+                (rng.next_f32() * 2.0 - 1.0) * level,
+                (rng.next_f32() * 2.0 - 1.0) * level,
+            )
+        })
+        .collect()
+}
+
+/// Renders test noise through the current settings, writes wet minus dry to a WAV at `path`
+/// and returns the peak difference in dB. A transparent setting nulls to -inf.
+pub(crate) fn null_test(params: &InterleafParams, sample_rate: f32, path: &Path) -> std::io::Result<f32> {
+    let dry = test_noise(sample_rate, TEST_SIGNAL_SECONDS);
+    let wet = render(params, sample_rate, &dry);
+    let difference: Vec<(f32, f32)> = wet
+        .iter()
+        .zip(dry.iter())
+        .map(|(wet, dry)| (wet.0 - dry.0, wet.1 - dry.1))
+        .collect();
+    wav::write_wav(path, sample_rate as u32, &difference)?;
+
+    let peak = difference
+        .iter()
+        .fold(0.0f32, |peak, (left, right)| peak.max(left.abs()).max(right.abs()));
+    Ok(util::gain_to_db(peak))
+}
@@ -0,0 +1,321 @@
+// phase_compensation.rs - Ardura 2023
+// Per band allpass phase compensation, a middle ground between minimum and linear phase
+// A minimum phase boost bunches its group delay up at its center with dips either side, which is
+// the phase distortion you hear. Two allpasses just outside the band's -3 dB points have their own
+// delay peaks there, so with the right Qs they fill the dips in and the delay through the band
+// comes out flatter. The magnitude is untouched and it adds no latency, the band just comes out a
+// little later as a whole. The Qs are picked by trying a small set against the band's own delay
+// and keeping the flattest, nothing is kept when none of them beat the band alone.
+
+use crate::biquad_filters::{self, Biquad, FilterType};
+
+/// How far outside the band's -3 dB points the allpasses can sit, as a ratio
+const EDGE_SPREADS: [f32; 3] = [1.0, 1.15, 1.3];
+/// Q of the allpass below the band
+const LOW_QS: [f32; 8] = [0.25, 0.35, 0.5, 0.7, 1.0, 1.4, 2.0, 2.8];
+/// The one above has its delay peak spread over more Hz so it gets this much more Q
+const HIGH_Q_RATIOS: [f32; 3] = [2.0, 3.0, 4.0];
+/// How many log spaced points across the band the flatness is judged on
+const SCORE_POINTS: usize = 24;
+/// A band that keeps moving, like under the sweep or the suppressor, is searched for again at most
+/// this often. The last design keeps running in between.
+const REDESIGN_INTERVAL_MS: f32 = 30.0;
+
+// Cookbook second order allpass, normalized so b = [c0, c1, 1] and a = [1, c1, c0]
+#[derive(Clone, Copy)]
+struct Allpass {
+    c0: f32,
+    c1: f32,
+    // Last two inputs and outputs per channel
+    input_history: [[f32; 2]; 2],
+    output_history: [[f32; 2]; 2],
+}
+
+impl Allpass {
+    fn new(sample_rate: f32, center_freq: f32, q_factor: f32) -> Self {
+        let omega = 2.0 * std::f32::consts::PI
+            * center_freq.min(sample_rate * biquad_filters::MAX_FREQ_RATIO)
+            / sample_rate;
+        let alpha = omega.sin() / (2.0 * q_factor);
+        Allpass {
+            c0: (1.0 - alpha) / (1.0 + alpha),
+            c1: -2.0 * omega.cos() / (1.0 + alpha),
+            input_history: [[0.0; 2]; 2],
+            output_history: [[0.0; 2]; 2],
+        }
+    }
+
+    // Keeps the history so a redesign while running carries on from where the filter was
+    fn set_coeffs(&mut self, other: &Allpass) {
+        self.c0 = other.c0;
+        self.c1 = other.c1;
+    }
+
+    fn group_delay(&self, omega: f32) -> f32 {
+        biquad_filters::polynomial_delay([self.c0, self.c1, 1.0], omega)
+            - biquad_filters::polynomial_delay([1.0, self.c1, self.c0], omega)
+    }
+
+    // The numerator is the denominator backwards so the phase is -2 omega - 2 arg(denominator)
+    fn phase(&self, omega: f32) -> f32 {
+        let (sin_1, cos_1) = omega.sin_cos();
+        let (sin_2, cos_2) = (2.0 * omega).sin_cos();
+        let re = 1.0 + self.c1 * cos_1 + self.c0 * cos_2;
+        let im = -(self.c1 * sin_1 + self.c0 * sin_2);
+        -2.0 * omega - 2.0 * im.atan2(re)
+    }
+
+    fn process_sample(&mut self, input: [f32; 2]) -> [f32; 2] {
+        let mut output = [0.0; 2];
+        for (((sample, output), inputs), outputs) in input
+            .iter()
+            .zip(output.iter_mut())
+            .zip(self.input_history.iter_mut())
+            .zip(self.output_history.iter_mut())
+        {
+            *output = self.c0 * sample + self.c1 * inputs[0] + inputs[1] - self.c1 * outputs[0] - self.c0 * outputs[1];
+            *inputs = [*sample, inputs[0]];
+            *outputs = [*output, outputs[0]];
+        }
+        output
+    }
+}
+
+/// What a band's compensation depends on: whether it's on, the band's type, frequency, gain, Q
+/// and soft Q, the sample rate, its interleave count and how many x2 passes it runs
+#[derive(Clone, Copy, PartialEq)]
+struct Design(bool, FilterType, f32, f32, f32, bool, f32, f32, f32);
+
+#[derive(Clone, Copy)]
+pub(crate) struct PhaseCompensator {
+    sections: [Allpass; 2],
+    active: bool,
+    sample_rate: f32,
+    designed_for: Option<Design>,
+    // Updates left before the next search is allowed, update is called once a sample
+    holdoff: u32,
+}
+
+impl PhaseCompensator {
+    pub fn new() -> Self {
+        PhaseCompensator {
+            sections: [Allpass::new(44100.0, 1000.0, 0.707); 2],
+            active: false,
+            sample_rate: 44100.0,
+            designed_for: None,
+            holdoff: 0,
+        }
+    }
+
+    /// Redesign for a band if anything it depends on changed. Only bands with a gain are
+    /// compensated. An interleave of N responds like H(z^N), so the band shows up at 1/N of its
+    /// frequency with N times the delay, and each x2 pass stacks another copy of it. Searching is
+    /// held off for `REDESIGN_INTERVAL_MS` after each one.
+    pub fn update(
+        &mut self,
+        enabled: bool,
+        band: (FilterType, f32, f32, f32),
+        soft_q: bool,
+        sample_rate: f32,
+        interleave: f32,
+        passes: f32,
+    ) {
+        let (filter_type, freq, gain, q) = band;
+        let design = Design(enabled, filter_type, freq, gain, q, soft_q, sample_rate, interleave, passes);
+        self.holdoff = self.holdoff.saturating_sub(1);
+        if self.designed_for == Some(design) {
+            return;
+        }
+        // Turning it off is free so that's never held off
+        if !enabled || !filter_type.uses_gain() || gain == 0.0 {
+            self.designed_for = Some(design);
+            self.active = false;
+            return;
+        }
+        if self.holdoff > 0 {
+            return;
+        }
+        self.holdoff = (REDESIGN_INTERVAL_MS / 1000.0 * sample_rate) as u32;
+        self.designed_for = Some(design);
+        self.sample_rate = sample_rate;
+        let was_active = self.active;
+        self.active = false;
+
+        let mut filter = Biquad::new(sample_rate, freq, gain, q, filter_type);
+        filter.set_soft_q(soft_q);
+        // Around the -3 dB points of a band pass at the same Q, shelves get the same span around
+        // their corner
+        let center = freq / interleave;
+        let half_width = 2.0_f32.powf(biquad_filters::q_to_octaves(q) / 2.0);
+        let top = sample_rate * biquad_filters::MAX_FREQ_RATIO;
+        let low = center / half_width;
+        let high = (center * half_width).min(top);
+        if high <= low {
+            return;
+        }
+        let mut band_delay = [0.0; SCORE_POINTS];
+        let mut omegas = [0.0; SCORE_POINTS];
+        for (point, (delay, omega)) in band_delay.iter_mut().zip(omegas.iter_mut()).enumerate() {
+            let point_freq = low * (high / low).powf(point as f32 / (SCORE_POINTS - 1) as f32);
+            *delay = filter.group_delay(point_freq * interleave) * interleave * passes;
+            *omega = 2.0 * std::f32::consts::PI * point_freq / sample_rate;
+        }
+        // Spread of the delay around its mean across the band, lower is flatter
+        let spread = |sections: Option<&[Allpass; 2]>| -> f32 {
+            let mut total = [0.0; SCORE_POINTS];
+            for ((total, delay), omega) in total.iter_mut().zip(band_delay).zip(omegas) {
+                *total = delay
+                    + sections.map_or(0.0, |sections| sections.iter().map(|section| section.group_delay(omega)).sum::<f32>());
+            }
+            let mean = total.iter().sum::<f32>() / SCORE_POINTS as f32;
+            total.iter().map(|delay| (delay - mean) * (delay - mean)).sum()
+        };
+
+        let mut best = spread(None);
+        let mut best_sections = None;
+        for edge_spread in EDGE_SPREADS {
+            for low_q in LOW_QS {
+                for high_q_ratio in HIGH_Q_RATIOS {
+                    let sections = [
+                        Allpass::new(sample_rate, low / edge_spread, low_q),
+                        Allpass::new(sample_rate, (high * edge_spread).min(top), low_q * high_q_ratio),
+                    ];
+                    let score = spread(Some(&sections));
+                    if score < best {
+                        best = score;
+                        best_sections = Some(sections);
+                    }
+                }
+            }
+        }
+        if let Some(sections) = best_sections {
+            if was_active {
+                for (section, designed) in self.sections.iter_mut().zip(sections.iter()) {
+                    section.set_coeffs(designed);
+                }
+            } else {
+                self.sections = sections;
+            }
+            self.active = true;
+        }
+    }
+
+    /// Phase shift in radians the compensation adds at a frequency, not wrapped
+    pub fn phase_response(&self, freq: f32) -> f32 {
+        if !self.active {
+            return 0.0;
+        }
+        let omega = 2.0 * std::f32::consts::PI * freq / self.sample_rate;
+        self.sections.iter().map(|section| section.phase(omega)).sum()
+    }
+
+    pub fn process_sample(&mut self, input_l: f32, input_r: f32) -> (f32, f32) {
+        if !self.active {
+            return (input_l, input_r);
+        }
+        let [output_l, output_r] = self
+            .sections
+            .iter_mut()
+            .fold([input_l, input_r], |samples, section| section.process_sample(samples));
+        (output_l, output_r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    // Phase of the band with and without compensation unwrapped from near DC, then how far it
+    // strays from the best straight line (a delay plus a constant shift) across the band
+    fn linear_phase_error(band: &Biquad, compensator: &PhaseCompensator, low: f32, high: f32) -> f32 {
+        let mut phases = Vec::new();
+        let mut freqs = Vec::new();
+        let mut last: Option<f32> = None;
+        let steps = 2000;
+        for step in 1..=steps {
+            let freq = high * step as f32 / steps as f32;
+            let mut phase = band.phase_response(freq) + compensator.phase_response(freq);
+            if let Some(last) = last {
+                phase -= 2.0 * std::f32::consts::PI * ((phase - last) / (2.0 * std::f32::consts::PI)).round();
+            }
+            last = Some(phase);
+            if freq >= low {
+                phases.push(phase);
+                freqs.push(freq);
+            }
+        }
+        let n = phases.len() as f32;
+        let mean_x = freqs.iter().sum::<f32>() / n;
+        let mean_y = phases.iter().sum::<f32>() / n;
+        let slope = freqs.iter().zip(&phases).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f32>()
+            / freqs.iter().map(|x| (x - mean_x) * (x - mean_x)).sum::<f32>();
+        let error = freqs
+            .iter()
+            .zip(&phases)
+            .map(|(x, y)| {
+                let residual = y - mean_y - slope * (x - mean_x);
+                residual * residual
+            })
+            .sum::<f32>();
+        (error / n).sqrt()
+    }
+
+    #[test]
+    fn boost_has_less_phase_deviation_than_minimum_phase() {
+        let band = Biquad::new(SAMPLE_RATE, 1000.0, 6.0, 2.0, FilterType::Peak);
+        let mut compensator = PhaseCompensator::new();
+        let plain = linear_phase_error(&band, &compensator, 700.0, 1400.0);
+        compensator.update(true, (FilterType::Peak, 1000.0, 6.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+        let compensated = linear_phase_error(&band, &compensator, 700.0, 1400.0);
+        assert!(compensated < plain * 0.8, "{compensated} vs {plain}");
+    }
+
+    #[test]
+    fn compensation_is_allpass() {
+        let mut compensator = PhaseCompensator::new();
+        compensator.update(true, (FilterType::Peak, 1000.0, 12.0, 1.0), false, SAMPLE_RATE, 1.0, 1.0);
+        assert!(compensator.active);
+        // A windowed sine comes out at the same level once the filters have settled
+        let freq = 3000.0;
+        let mut peak: f32 = 0.0;
+        for index in 0..48000 {
+            let input = (2.0 * std::f32::consts::PI * freq * index as f32 / SAMPLE_RATE).sin();
+            let (output, _) = compensator.process_sample(input, input);
+            if index > 24000 {
+                peak = peak.max(output.abs());
+            }
+        }
+        assert!((peak - 1.0).abs() < 1e-3, "{peak}");
+    }
+
+    #[test]
+    fn moving_bands_are_redesigned_at_most_once_per_interval() {
+        let mut compensator = PhaseCompensator::new();
+        compensator.update(true, (FilterType::Peak, 1000.0, 6.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+        let first = compensator.phase_response(1000.0);
+        let mut moved = PhaseCompensator::new();
+        moved.update(true, (FilterType::Peak, 2000.0, 6.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+
+        // Moving straight after keeps the design it has until the interval is up
+        let interval = (REDESIGN_INTERVAL_MS / 1000.0 * SAMPLE_RATE) as usize;
+        for _ in 1..interval {
+            compensator.update(true, (FilterType::Peak, 2000.0, 6.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+        }
+        assert_eq!(compensator.phase_response(1000.0), first);
+        compensator.update(true, (FilterType::Peak, 2000.0, 6.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+        assert_eq!(compensator.phase_response(1000.0), moved.phase_response(1000.0));
+    }
+
+    #[test]
+    fn off_or_flat_bands_pass_through() {
+        let mut compensator = PhaseCompensator::new();
+        compensator.update(false, (FilterType::Peak, 1000.0, 6.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+        assert_eq!(compensator.process_sample(0.5, -0.25), (0.5, -0.25));
+        compensator.update(true, (FilterType::Peak, 1000.0, 0.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+        assert_eq!(compensator.process_sample(0.5, -0.25), (0.5, -0.25));
+        compensator.update(true, (FilterType::LowPass, 1000.0, 6.0, 2.0), false, SAMPLE_RATE, 1.0, 1.0);
+        assert_eq!(compensator.process_sample(0.5, -0.25), (0.5, -0.25));
+    }
+}
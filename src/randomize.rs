@@ -0,0 +1,98 @@
+// randomize.rs - Ardura 2023
+// Seeded randomizing of the bands for sound design starting points
+// Everything happens in normalized parameter space so ranges and skews are respected
+
+use crate::biquad_filters::FilterType;
+use crate::InterleafParams;
+use nih_plug::prelude::{Enum, Param, ParamSetter};
+use std::sync::atomic::Ordering;
+
+/// How far "slight variation" can nudge a normalized value either way
+const SLIGHT_AMOUNT: f32 = 0.05;
+
+// Small xorshift so results are reproducible from a shown seed without pulling in a crate
+pub(crate) struct Xorshift {
+    state: u32,
+}
+
+impl Xorshift {
+    pub fn new(seed: u32) -> Self {
+        // Xorshift gets stuck on 0 forever
+        Xorshift {
+            state: if seed == 0 { 0x9E37_79B9 } else { seed },
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// Random value in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+/// Randomizes every band through the setter. All params get their begin gesture first and their
+/// end gesture last so hosts can treat this as one undo step.
+/// `slight` nudges the current values instead of replacing them, `musical` keeps the outer bands as
+/// shelves and the inner ones as peaks.
+pub(crate) fn randomize_bands(
+    params: &InterleafParams,
+    setter: &ParamSetter,
+    seed: u32,
+    slight: bool,
+    musical: bool,
+) {
+    let mut rng = Xorshift::new(seed);
+    let bands = params.bands();
+
+    for (type_param, freq_param, gain_param, res_param) in bands.iter() {
+        setter.begin_set_parameter(*type_param);
+        setter.begin_set_parameter(*freq_param);
+        setter.begin_set_parameter(*gain_param);
+        setter.begin_set_parameter(*res_param);
+    }
+
+    let band_count = bands.len();
+    for (index, (type_param, freq_param, gain_param, res_param)) in bands.iter().enumerate() {
+        for param in [*freq_param, *gain_param, *res_param] {
+            let normalized = if slight {
+                param.unmodulated_normalized_value() + (rng.next_f32() * 2.0 - 1.0) * SLIGHT_AMOUNT
+            } else {
+                rng.next_f32()
+            };
+            setter.set_parameter_normalized(param, normalized.clamp(0.0, 1.0));
+        }
+
+        let new_type = if musical {
+            if index == 0 {
+                FilterType::LowShelf
+            } else if index == band_count - 1 {
+                FilterType::HighShelf
+            } else {
+                FilterType::Peak
+            }
+        } else if slight {
+            type_param.value()
+        } else {
+            // Skip Off so every band does something
+            let variants = FilterType::variants().len();
+            FilterType::from_index(1 + (rng.next_u32() as usize % (variants - 1)))
+        };
+        setter.set_parameter(*type_param, new_type);
+    }
+
+    for (type_param, freq_param, gain_param, res_param) in bands.iter() {
+        setter.end_set_parameter(*type_param);
+        setter.end_set_parameter(*freq_param);
+        setter.end_set_parameter(*gain_param);
+        setter.end_set_parameter(*res_param);
+    }
+    params.bulk_change.store(true, Ordering::Relaxed);
+}
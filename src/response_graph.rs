@@ -0,0 +1,781 @@
+// response_graph.rs - Ardura 2023
+// Draws the combined EQ response on a log frequency axis with a node per band
+// Dragging a node moves its band's frequency (and gain for the types that use it)
+// The input spectrum from the analyzer can be drawn behind the curve on its own dBFS scale
+// The analyzer's held peaks go under it as a dashed line so they don't read as the live spectrum
+// Right dragging across the graph can pick a region for the analyzer zoom view
+// The combined phase shift can be overlaid on its own +-180 degree scale labelled on the right
+// Bands that are on get their frequency and gain written next to the node
+// A draggable threshold line on the analyzer's scale marks the bins that go over it
+// The frequency grid can follow notes instead, a labelled line on every C and fainter semitones
+// The analyzer's output minus input difference goes on the EQ's dB scale to compare with the curve
+// A node's right click menu can reset its band or set its gain from the analyzer, see `band_menu`
+// A crosshair follows the pointer with the frequency, the EQ's gain and the analyzer level under it
+
+use crate::analyzer::{bin_to_freq, level_at, ANALYZER_MIN_DB};
+use crate::biquad_filters::FilterType;
+use crate::{InterleafParams, NO_LISTEN, PEAK_FREQ_GATE_DB};
+use nih_plug::prelude::{formatters, util, Param, ParamSetter};
+use std::cell::Cell;
+use std::ops::RangeInclusive;
+use atomic_float::AtomicF32;
+use std::sync::atomic::{AtomicI32, Ordering};
+use nih_plug_egui::egui::{
+    vec2, Align2, Button, Color32, DragValue, FontId, PointerButton, Pos2, Rect, Response, Sense, Shape, Stroke, Ui, Vec2, Widget,
+};
+
+/// Default frequency range, the editor can widen or narrow it
+pub(crate) const GRAPH_MIN_FREQ: f32 = 20.0;
+pub(crate) const GRAPH_MAX_FREQ: f32 = 20000.0;
+/// Furthest the range can be set out to
+pub(crate) const GRAPH_LOWEST_FREQ: f32 = 10.0;
+pub(crate) const GRAPH_HIGHEST_FREQ: f32 = 40000.0;
+/// The graph shows plus and minus this many dB unless set otherwise
+pub(crate) const GRAPH_DB_RANGE: f32 = 24.0;
+/// Limits for the manual range and what auto scaling can pick
+pub(crate) const GRAPH_MIN_DB_RANGE: f32 = 6.0;
+pub(crate) const GRAPH_MAX_DB_RANGE: f32 = 96.0;
+// Auto scaling leaves this much room past the curve's extremes then rounds up to the grid step
+const AUTO_DB_HEADROOM: f32 = 1.2;
+const AUTO_DB_STEP: f32 = 6.0;
+// How many points make up the drawn curve
+const CURVE_POINTS: usize = 200;
+const NODE_RADIUS: f32 = 5.0;
+// Gap between a node and its value label
+const NODE_LABEL_GAP: f32 = 2.0;
+// How far either side of the threshold line it can be grabbed from
+const THRESHOLD_GRAB: f32 = 3.0;
+/// Flatter than this everywhere and there's nothing to highlight
+const HIGHLIGHT_MIN_DB: f32 = 0.5;
+// Phase scale top and bottom, and the gridline labels on the right edge
+const PHASE_RANGE_DEGREES: f32 = 180.0;
+const PHASE_LABELS: [f32; 3] = [180.0, 0.0, -180.0];
+// Gap between the pointer and the cursor readout
+const CURSOR_LABEL_GAP: f32 = 8.0;
+// Dash and gap lengths of the held analyzer peaks
+const HELD_DASH: f32 = 4.0;
+const HELD_GAP: f32 = 3.0;
+// Gain to Target reads the analyzer over this much of an octave either side of the band
+const TARGET_OCTAVES: f32 = 1.0 / 6.0;
+
+// Gridlines go at 1, 2 and 5 of every decade in range, only the 1s get labels
+const GRID_DECADES: [(f32, &str); 5] = [
+    (10.0, "10"),
+    (100.0, "100"),
+    (1000.0, "1k"),
+    (10000.0, "10k"),
+    (100000.0, "100k"),
+];
+const GRID_STEPS: [f32; 3] = [1.0, 2.0, 5.0];
+// Note grid runs over the MIDI notes that cover the widest graph range, semitone lines only show
+// once they're at least this far apart
+const NOTE_GRID_NOTES: std::ops::Range<i32> = 0..144;
+const NOTE_GRID_MIN_SEMITONE_PX: f32 = 4.0;
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub(crate) struct ResponseGraph<'a> {
+    params: &'a InterleafParams,
+    setter: &'a ParamSetter<'a>,
+    sample_rate: f32,
+    size: Vec2,
+    freq_range: RangeInclusive<f32>,
+    db_range: f32,
+    auto_db_range: bool,
+    spectrum: Option<&'a [f32]>,
+    spectrum_color: Color32,
+    second_spectrum: Option<&'a [f32]>,
+    second_spectrum_color: Color32,
+    held_spectrum: Option<&'a [f32]>,
+    held_color: Color32,
+    difference: Option<&'a [f32]>,
+    difference_color: Color32,
+    gain_target: Option<&'a [f32]>,
+    highlight_color: Color32,
+    auto_listen: Option<&'a AtomicI32>,
+    held_bypass: Option<&'a Cell<u32>>,
+    band_focus: Option<&'a Cell<Option<usize>>>,
+    zoom_select: Option<(&'a AtomicF32, &'a AtomicF32)>,
+    threshold: Option<&'a AtomicF32>,
+    note_grid: bool,
+    show_phase: bool,
+    phase_color: Color32,
+    line_color: Color32,
+    node_color: Color32,
+    grid_color: Color32,
+    background_color: Color32,
+}
+
+impl<'a> ResponseGraph<'a> {
+    pub fn new(params: &'a InterleafParams, setter: &'a ParamSetter<'a>, sample_rate: f32, size: Vec2) -> Self {
+        Self {
+            params,
+            setter,
+            sample_rate,
+            size,
+            freq_range: GRAPH_MIN_FREQ..=GRAPH_MAX_FREQ,
+            db_range: GRAPH_DB_RANGE,
+            auto_db_range: false,
+            spectrum: None,
+            spectrum_color: Color32::DARK_GRAY,
+            second_spectrum: None,
+            second_spectrum_color: Color32::LIGHT_GRAY,
+            held_spectrum: None,
+            held_color: Color32::GRAY,
+            difference: None,
+            difference_color: Color32::LIGHT_GRAY,
+            gain_target: None,
+            highlight_color: Color32::from_white_alpha(24),
+            auto_listen: None,
+            held_bypass: None,
+            band_focus: None,
+            zoom_select: None,
+            threshold: None,
+            note_grid: false,
+            show_phase: false,
+            phase_color: Color32::LIGHT_BLUE,
+            line_color: Color32::WHITE,
+            node_color: Color32::GREEN,
+            grid_color: Color32::GRAY,
+            background_color: Color32::BLACK,
+        }
+    }
+
+    /// Show this range across instead of the default 20 Hz to 20 kHz
+    pub fn with_freq_range(mut self, freq_range: RangeInclusive<f32>) -> Self {
+        self.freq_range = freq_range;
+        self
+    }
+
+    /// Show plus and minus this many dB instead of the default
+    pub fn with_db_range(mut self, db_range: f32) -> Self {
+        self.db_range = db_range.clamp(GRAPH_MIN_DB_RANGE, GRAPH_MAX_DB_RANGE);
+        self
+    }
+
+    /// Fit the dB range to the curve and nodes every frame, this takes over from `with_db_range`
+    pub fn with_auto_db_range(mut self) -> Self {
+        self.auto_db_range = true;
+        self
+    }
+
+    /// Analyzer bins in dBFS to draw behind the response
+    pub fn with_spectrum(mut self, spectrum: &'a [f32]) -> Self {
+        self.spectrum = Some(spectrum);
+        self
+    }
+
+    /// Another spectrum over the first, right or side when the analyzer is split
+    pub fn with_second_spectrum(mut self, spectrum: &'a [f32]) -> Self {
+        self.second_spectrum = Some(spectrum);
+        self
+    }
+
+    /// The analyzer's held peaks in dBFS
+    pub fn with_held_spectrum(mut self, held: &'a [f32]) -> Self {
+        self.held_spectrum = Some(held);
+        self
+    }
+
+    /// How far the output's bins sit above the input's in dB, drawn on the response's scale
+    pub fn with_difference(mut self, difference: &'a [f32]) -> Self {
+        self.difference = Some(difference);
+        self
+    }
+
+    /// Analyzer bins in dBFS the nodes' Gain to Target reads from, it's unavailable without them
+    pub fn with_gain_target(mut self, bins: &'a [f32]) -> Self {
+        self.gain_target = Some(bins);
+        self
+    }
+
+    /// Listen to whichever band is being dragged by writing its index here, NO_LISTEN otherwise
+    pub fn with_auto_listen(mut self, listen_band: &'a AtomicI32) -> Self {
+        self.auto_listen = Some(listen_band);
+        self
+    }
+
+    /// Ctrl/Cmd pressing a node sets its band's bit here for as long as it's held
+    pub fn with_held_bypass(mut self, held_bands: &'a Cell<u32>) -> Self {
+        self.held_bypass = Some(held_bands);
+        self
+    }
+
+    /// Hovering or dragging a node writes its band here
+    pub fn with_band_focus(mut self, band: &'a Cell<Option<usize>>) -> Self {
+        self.band_focus = Some(band);
+        self
+    }
+
+    /// Show the zoom region and let right dragging across the graph set it
+    pub fn with_zoom_select(mut self, low: &'a AtomicF32, high: &'a AtomicF32) -> Self {
+        self.zoom_select = Some((low, high));
+        self
+    }
+
+    /// Draw a threshold line at this analyzer level in dBFS, dragging it writes the new level back
+    pub fn with_threshold(mut self, level: &'a AtomicF32) -> Self {
+        self.threshold = Some(level);
+        self
+    }
+
+    /// Put the frequency gridlines on notes instead of decades
+    pub fn with_note_grid(mut self) -> Self {
+        self.note_grid = true;
+        self
+    }
+
+    /// Overlay the combined phase shift of the bands
+    pub fn with_phase(mut self) -> Self {
+        self.show_phase = true;
+        self
+    }
+
+    /// Set the color of the analyzer spectrum
+    pub fn set_spectrum_color(&mut self, new_color: Color32) {
+        self.spectrum_color = new_color;
+    }
+
+    /// Set the color of the second analyzer spectrum
+    pub fn set_second_spectrum_color(&mut self, new_color: Color32) {
+        self.second_spectrum_color = new_color;
+    }
+
+    /// Set the color of the held analyzer peaks
+    pub fn set_held_color(&mut self, new_color: Color32) {
+        self.held_color = new_color;
+    }
+
+    /// Set the color of the analyzer difference
+    pub fn set_difference_color(&mut self, new_color: Color32) {
+        self.difference_color = new_color;
+    }
+
+    /// Set the color of the phase curve and its scale
+    pub fn set_phase_color(&mut self, new_color: Color32) {
+        self.phase_color = new_color;
+    }
+
+    /// Set the color of the response curve
+    pub fn set_line_color(&mut self, new_color: Color32) {
+        self.line_color = new_color;
+    }
+
+    /// Set the color of the band nodes
+    pub fn set_node_color(&mut self, new_color: Color32) {
+        self.node_color = new_color;
+    }
+
+    /// Set the color of the grid and its labels
+    pub fn set_grid_color(&mut self, new_color: Color32) {
+        self.grid_color = new_color;
+    }
+
+    /// Set the background color
+    pub fn set_background_color(&mut self, new_color: Color32) {
+        self.background_color = new_color;
+    }
+}
+
+// Axis mapping, log frequency across over `freq_range` and linear dB up
+pub(crate) fn freq_to_x(rect: Rect, freq_range: &RangeInclusive<f32>, freq: f32) -> f32 {
+    let (min, max) = (*freq_range.start(), *freq_range.end());
+    rect.left() + rect.width() * (freq / min).ln() / (max / min).ln()
+}
+
+pub(crate) fn x_to_freq(rect: Rect, freq_range: &RangeInclusive<f32>, x: f32) -> f32 {
+    let (min, max) = (*freq_range.start(), *freq_range.end());
+    min * (max / min).powf((x - rect.left()) / rect.width())
+}
+
+pub(crate) fn db_to_y(rect: Rect, db_range: f32, db: f32) -> f32 {
+    rect.center().y - db / db_range * rect.height() / 2.0
+}
+
+pub(crate) fn y_to_db(rect: Rect, db_range: f32, y: f32) -> f32 {
+    (rect.center().y - y) / (rect.height() / 2.0) * db_range
+}
+
+/// dB between gridlines, 12 at the default range and always a multiple of 3
+pub(crate) fn db_grid_step(db_range: f32) -> f32 {
+    (db_range / 6.0).ceil() * 3.0
+}
+
+// The analyzer runs from ANALYZER_MIN_DB at the bottom to 0 dBFS at the top
+pub(crate) fn analyzer_db_to_y(rect: Rect, db: f32) -> f32 {
+    rect.bottom() - (db - ANALYZER_MIN_DB) / -ANALYZER_MIN_DB * rect.height()
+}
+
+pub(crate) fn y_to_analyzer_db(rect: Rect, y: f32) -> f32 {
+    ANALYZER_MIN_DB + (rect.bottom() - y) / rect.height() * -ANALYZER_MIN_DB
+}
+
+/// Right click menu for a band's node and header: reset it, or set its gain so the input at its
+/// frequency comes out at the target level. Greyed out when there's nothing to read there.
+pub(crate) fn band_menu(
+    ui: &mut Ui,
+    params: &InterleafParams,
+    setter: &ParamSetter<'_>,
+    index: usize,
+    bins: Option<&[f32]>,
+    sample_rate: f32,
+) {
+    if ui.button("Reset Band").clicked() {
+        params.reset_band(setter, index);
+        ui.close_menu();
+    }
+    let (type_param, freq_param, _, _) = params.bands()[index];
+    if !type_param.value().uses_gain() {
+        return;
+    }
+
+    ui.separator();
+    let mut target = params.band_target_level.load(Ordering::Relaxed);
+    ui.add(
+        DragValue::new(&mut target)
+            .clamp_range(ANALYZER_MIN_DB..=0.0)
+            .speed(0.1)
+            .max_decimals(1)
+            .prefix("Target ")
+            .suffix(" dBFS"),
+    );
+    params.band_target_level.store(target, Ordering::Relaxed);
+    let level = bins
+        .map(|bins| level_at(bins, sample_rate, freq_param.value(), TARGET_OCTAVES))
+        .filter(|level| *level >= PEAK_FREQ_GATE_DB);
+    match level {
+        Some(level) => {
+            if ui
+                .button("Gain to Target")
+                .on_hover_text(format!("The input is at {level:.1} dBFS around this band"))
+                .clicked()
+            {
+                params.gain_to_target(setter, index, level, target);
+                ui.close_menu();
+            }
+        }
+        None => {
+            ui.add_enabled(false, Button::new("Gain to Target"));
+            ui.label(if bins.is_some() {
+                "No signal at this band's frequency"
+            } else {
+                "The analyzer is off in economy mode"
+            });
+        }
+    }
+}
+
+impl<'a> Widget for ResponseGraph<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let sense = if self.zoom_select.is_some() { Sense::drag() } else { Sense::hover() };
+        let (rect, response) = ui.allocate_exact_size(self.size, sense);
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+        let painter = ui.painter_at(rect);
+        painter.rect(rect, 0.0, self.background_color, Stroke::new(1.0, self.grid_color));
+
+        // Combined response curve, worked out first so auto scaling can fit it
+        let freqs: Vec<f32> = (0..CURVE_POINTS)
+            .map(|i| x_to_freq(rect, &self.freq_range, rect.left() + rect.width() * i as f32 / (CURVE_POINTS - 1) as f32))
+            .collect();
+        let responses = self.params.frequency_response(self.sample_rate, &freqs);
+        let db_range = if self.auto_db_range {
+            let node_extreme = self
+                .params
+                .bands()
+                .iter()
+                .filter(|(type_param, _, _, _)| type_param.value().uses_gain())
+                .fold(0.0_f32, |extreme, (_, _, gain_param, _)| extreme.max(gain_param.value().abs()));
+            let extreme = responses.iter().fold(node_extreme, |extreme, db| extreme.max(db.abs()));
+            ((extreme * AUTO_DB_HEADROOM / AUTO_DB_STEP).ceil() * AUTO_DB_STEP)
+                .clamp(GRAPH_MIN_DB_RANGE, GRAPH_MAX_DB_RANGE)
+        } else {
+            self.db_range
+        };
+
+        // Frequency grid, on notes or on decades
+        let faint_grid = self.grid_color.gamma_multiply(0.4);
+        if self.note_grid {
+            let semitone_px = rect.width() / (*self.freq_range.end() / *self.freq_range.start()).log2() / 12.0;
+            for note in NOTE_GRID_NOTES {
+                let octave_line = note % 12 == 0;
+                if !octave_line && semitone_px < NOTE_GRID_MIN_SEMITONE_PX {
+                    continue;
+                }
+                let freq = util::f32_midi_note_to_freq(note as f32);
+                if !self.freq_range.contains(&freq) {
+                    continue;
+                }
+                let x = freq_to_x(rect, &self.freq_range, freq);
+                painter.line_segment(
+                    [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                    Stroke::new(1.0, if octave_line { self.grid_color } else { faint_grid }),
+                );
+                if octave_line {
+                    painter.text(
+                        Pos2::new(x + 2.0, rect.bottom() - 2.0),
+                        Align2::LEFT_BOTTOM,
+                        format!("C{}", note / 12 - 1),
+                        FontId::monospace(8.0),
+                        self.grid_color,
+                    );
+                }
+            }
+        }
+        for (decade, label) in GRID_DECADES.into_iter().filter(|_| !self.note_grid) {
+            for step in GRID_STEPS {
+                let freq = decade * step;
+                if !self.freq_range.contains(&freq) {
+                    continue;
+                }
+                let x = freq_to_x(rect, &self.freq_range, freq);
+                let labelled = step == 1.0;
+                painter.line_segment(
+                    [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                    Stroke::new(1.0, if labelled { self.grid_color } else { faint_grid }),
+                );
+                if labelled {
+                    painter.text(
+                        Pos2::new(x + 2.0, rect.bottom() - 2.0),
+                        Align2::LEFT_BOTTOM,
+                        label,
+                        FontId::monospace(8.0),
+                        self.grid_color,
+                    );
+                }
+            }
+        }
+
+        // dB grid, every 12 dB at the default range
+        let db_step = db_grid_step(db_range);
+        let mut db = -(db_range / db_step).floor() * db_step;
+        while db <= db_range {
+            let y = db_to_y(rect, db_range, db);
+            painter.line_segment(
+                [Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                Stroke::new(1.0, if db == 0.0 { self.grid_color } else { faint_grid }),
+            );
+            painter.text(
+                Pos2::new(rect.left() + 2.0, y),
+                Align2::LEFT_BOTTOM,
+                format!("{db:+.0}"),
+                FontId::monospace(8.0),
+                self.grid_color,
+            );
+            db += db_step;
+        }
+
+        // Held peaks, dashed so they stand apart from the live spectrum over them
+        if let Some(held) = self.held_spectrum {
+            let points: Vec<Pos2> = held
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(bin, db)| (bin_to_freq(bin as f32, self.sample_rate), *db))
+                .filter(|(freq, _)| self.freq_range.contains(freq))
+                .map(|(freq, db)| Pos2::new(freq_to_x(rect, &self.freq_range, freq), analyzer_db_to_y(rect, db)))
+                .collect();
+            painter.extend(Shape::dashed_line(&points, Stroke::new(1.0, self.held_color), HELD_DASH, HELD_GAP));
+        }
+
+        // Input spectrum, or both halves of a split one
+        for (spectrum, color) in [
+            (self.spectrum, self.spectrum_color),
+            (self.second_spectrum, self.second_spectrum_color),
+        ] {
+            let Some(spectrum) = spectrum else {
+                continue;
+            };
+            let points: Vec<Pos2> = spectrum
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(bin, db)| (bin_to_freq(bin as f32, self.sample_rate), *db))
+                .filter(|(freq, _)| self.freq_range.contains(freq))
+                .map(|(freq, db)| Pos2::new(freq_to_x(rect, &self.freq_range, freq), analyzer_db_to_y(rect, db)))
+                .collect();
+            painter.add(Shape::line(points, Stroke::new(1.0, color)));
+        }
+
+        // Output minus input, should land on the curve for steady signals
+        if let Some(difference) = self.difference {
+            let points: Vec<Pos2> = difference
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(bin, db)| (bin_to_freq(bin as f32, self.sample_rate), *db))
+                .filter(|(freq, _)| self.freq_range.contains(freq))
+                .map(|(freq, db)| {
+                    Pos2::new(
+                        freq_to_x(rect, &self.freq_range, freq),
+                        db_to_y(rect, db_range, db).clamp(rect.top(), rect.bottom()),
+                    )
+                })
+                .collect();
+            painter.add(Shape::line(points, Stroke::new(1.0, self.difference_color)));
+        }
+
+        // Threshold line on the analyzer's scale, the bins over it get a bar up from the line
+        if let Some(threshold) = self.threshold {
+            let level = threshold.load(Ordering::Relaxed);
+            let y = analyzer_db_to_y(rect, level);
+            let line_response = ui
+                .interact(
+                    Rect::from_x_y_ranges(rect.x_range(), (y - THRESHOLD_GRAB)..=(y + THRESHOLD_GRAB)),
+                    response.id.with("threshold"),
+                    Sense::drag(),
+                )
+                .on_hover_text(format!("Analyzer threshold {level:.1} dBFS, drag to move"));
+            if line_response.dragged() {
+                if let Some(pointer) = line_response.interact_pointer_pos() {
+                    threshold.store(
+                        y_to_analyzer_db(rect, pointer.y).clamp(ANALYZER_MIN_DB, 0.0),
+                        Ordering::Relaxed,
+                    );
+                }
+            }
+            if let Some(spectrum) = self.spectrum {
+                for (bin, db) in spectrum.iter().enumerate().skip(1).filter(|(_, db)| **db > level) {
+                    let freq = bin_to_freq(bin as f32, self.sample_rate);
+                    if !self.freq_range.contains(&freq) {
+                        continue;
+                    }
+                    let x = freq_to_x(rect, &self.freq_range, freq);
+                    painter.line_segment(
+                        [Pos2::new(x, y), Pos2::new(x, analyzer_db_to_y(rect, *db))],
+                        Stroke::new(1.0, self.highlight_color.gamma_multiply(3.0)),
+                    );
+                }
+            }
+            painter.line_segment(
+                [Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                Stroke::new(if line_response.hovered() || line_response.dragged() { 2.0 } else { 1.0 }, self.spectrum_color),
+            );
+        }
+
+        // Zoom region, a right drag replaces it with what's under the drag
+        if let Some((zoom_low, zoom_high)) = self.zoom_select {
+            if response.dragged_by(PointerButton::Secondary) {
+                if let (Some(origin), Some(pointer)) =
+                    (ui.input(|i| i.pointer.press_origin()), response.interact_pointer_pos())
+                {
+                    let start = x_to_freq(rect, &self.freq_range, origin.x.clamp(rect.left(), rect.right()));
+                    let end = x_to_freq(rect, &self.freq_range, pointer.x.clamp(rect.left(), rect.right()));
+                    // Ignore a click without much of a drag
+                    if start.max(end) / start.min(end) > 1.01 {
+                        zoom_low.store(start.min(end), Ordering::Relaxed);
+                        zoom_high.store(start.max(end), Ordering::Relaxed);
+                    }
+                }
+            }
+            let zoom_rect = Rect::from_x_y_ranges(
+                freq_to_x(rect, &self.freq_range, zoom_low.load(Ordering::Relaxed))
+                    ..=freq_to_x(rect, &self.freq_range, zoom_high.load(Ordering::Relaxed)),
+                rect.y_range(),
+            );
+            painter.rect_stroke(zoom_rect, 0.0, Stroke::new(1.0, self.spectrum_color.gamma_multiply(0.5)));
+        }
+
+        // Highlight where the EQ is doing the most: out from the biggest change until the curve
+        // falls under half of it
+        let (peak_index, peak_db) = responses
+            .iter()
+            .enumerate()
+            .fold((0, 0.0_f32), |best, (index, db)| if db.abs() > best.1.abs() { (index, *db) } else { best });
+        if peak_db.abs() >= HIGHLIGHT_MIN_DB {
+            let in_region = |db: &f32| db.abs() >= peak_db.abs() / 2.0 && db.signum() == peak_db.signum();
+            let start = responses[..peak_index]
+                .iter()
+                .rposition(|db| !in_region(db))
+                .map_or(0, |index| index + 1);
+            let end = responses[peak_index..]
+                .iter()
+                .position(|db| !in_region(db))
+                .map_or(responses.len() - 1, |index| peak_index + index - 1);
+            painter.rect_filled(
+                Rect::from_x_y_ranges(
+                    freq_to_x(rect, &self.freq_range, freqs[start])
+                        ..=freq_to_x(rect, &self.freq_range, freqs[end]),
+                    rect.y_range(),
+                ),
+                0.0,
+                self.highlight_color,
+            );
+            let peak_x = freq_to_x(rect, &self.freq_range, freqs[peak_index]);
+            painter.line_segment(
+                [Pos2::new(peak_x, rect.top()), Pos2::new(peak_x, rect.bottom())],
+                Stroke::new(1.0, self.highlight_color.gamma_multiply(3.0)),
+            );
+        }
+        // Phase under the magnitude curve, broken where it wraps so it doesn't draw across the graph
+        if self.show_phase {
+            let phase_to_y = |degrees: f32| rect.center().y - degrees / PHASE_RANGE_DEGREES * rect.height() / 2.0;
+            for degrees in PHASE_LABELS {
+                painter.text(
+                    Pos2::new(rect.right() - 2.0, phase_to_y(degrees).clamp(rect.top() + 8.0, rect.bottom()),
+                    Align2::RIGHT_BOTTOM,
+                    format!("{degrees:+.0}°"),
+                    FontId::monospace(8.0),
+                    self.phase_color,
+                );
+            }
+            let phases = self.params.phase_response(self.sample_rate, &freqs);
+            let mut segment: Vec<Pos2> = Vec::new();
+            for (index, (freq, degrees)) in freqs.iter().zip(phases.iter()).enumerate() {
+                if index > 0 && (degrees - phases[index - 1]).abs() > PHASE_RANGE_DEGREES {
+                    painter.add(Shape::line(std::mem::take(&mut segment), Stroke::new(1.0, self.phase_color)));
+                }
+                segment.push(Pos2::new(freq_to_x(rect, &self.freq_range, *freq), phase_to_y(*degrees)));
+            }
+            painter.add(Shape::line(segment, Stroke::new(1.0, self.phase_color)));
+        }
+
+        let points: Vec<Pos2> = freqs
+            .iter()
+            .zip(responses.iter())
+            .map(|(freq, db)| Pos2::new(freq_to_x(rect, &self.freq_range, *freq), db_to_y(rect, db_range, *db)))
+            .collect();
+        painter.add(Shape::line(points, Stroke::new(1.5, self.line_color)));
+
+        // Band nodes
+        let mut dragged_band = None;
+        let mut node_labels = Vec::new();
+        for (index, (type_param, freq_param, gain_param, _)) in self.params.bands().iter().enumerate() {
+            let uses_gain = type_param.value().uses_gain();
+            let node_db = if uses_gain { gain_param.value() } else { 0.0 };
+            let center = Pos2::new(freq_to_x(rect, &self.freq_range, freq_param.value()), db_to_y(rect, db_range, node_db));
+            let node_response = ui.interact(
+                Rect::from_center_size(center, vec2(NODE_RADIUS * 3.0, NODE_RADIUS * 3.0)),
+                response.id.with(index),
+                Sense::click_and_drag(),
+            )
+            .on_hover_text(format!(
+                "Band {index}\n{}\nRight click to reset or set the gain from the analyzer\n{}",
+                type_param.value().description(),
+                if uses_gain { "Shift click to flip boost and cut" } else { "No gain to flip with Shift click" }
+            ))
+            .context_menu(|ui| {
+                band_menu(ui, self.params, self.setter, index, self.gain_target, self.sample_rate);
+            });
+
+            if node_response.hovered() || node_response.dragged() {
+                if let Some(band_focus) = self.band_focus {
+                    band_focus.set(Some(index));
+                }
+            }
+            // Shift clicking flips the gain between boost and cut, the types without gain stay put
+            if node_response.clicked() && ui.input(|i| i.modifiers.shift) && uses_gain {
+                self.setter.begin_set_parameter(*gain_param);
+                self.setter.set_parameter(*gain_param, -gain_param.value());
+                self.setter.end_set_parameter(*gain_param);
+            }
+            if node_response.drag_started() {
+                self.setter.begin_set_parameter(*freq_param);
+                self.setter.begin_set_parameter(*gain_param);
+            }
+            // Momentary bypass leaves the band where it is
+            let holding_bypass = ui.input(|i| i.modifiers.command) && node_response.is_pointer_button_down_on();
+            if holding_bypass {
+                if let Some(held_bands) = self.held_bypass {
+                    held_bands.set(held_bands.get() | 1 << index);
+                }
+            } else if node_response.dragged() {
+                dragged_band = Some(index);
+                if let Some(pointer) = node_response.interact_pointer_pos() {
+                    // The band's own range limits it further when the graph goes wider
+                    let freq = x_to_freq(rect, &self.freq_range, pointer.x);
+                    self.setter.set_parameter(
+                        *freq_param,
+                        freq_param.preview_plain(freq_param.preview_normalized(freq)),
+                    );
+                    if uses_gain {
+                        let gain = y_to_db(rect, db_range, pointer.y);
+                        self.setter.set_parameter(
+                            *gain_param,
+                            gain_param.preview_plain(gain_param.preview_normalized(gain)),
+                        );
+                    }
+                }
+            }
+            if node_response.drag_released() {
+                self.setter.end_set_parameter(*freq_param);
+                self.setter.end_set_parameter(*gain_param);
+            }
+
+            let fill = if node_response.hovered() || node_response.dragged() {
+                self.line_color
+            } else {
+                self.node_color
+            };
+            painter.circle_filled(center, NODE_RADIUS, fill);
+            if type_param.value() != FilterType::Off {
+                let freq_text = formatters::v2s_f32_hz_then_khz(0)(freq_param.value());
+                node_labels.push((
+                    center,
+                    if uses_gain { format!("{freq_text} {:+.1}", gain_param.value()) } else { freq_text },
+                ));
+            }
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                index.to_string(),
+                FontId::monospace(8.0),
+                self.background_color,
+            );
+        }
+
+        // Value labels go above their node, or below when that's taken, and are left out when
+        // neither spot is free so close bands don't write over each other
+        let mut placed_labels: Vec<Rect> = Vec::new();
+        for (center, text) in node_labels {
+            let galley = painter.layout_no_wrap(text, FontId::monospace(8.0), self.grid_color);
+            let offset = NODE_RADIUS + NODE_LABEL_GAP;
+            let above = Rect::from_center_size(
+                Pos2::new(center.x, center.y - offset - galley.size().y / 2.0),
+                galley.size(),
+            );
+            let below = above.translate(vec2(0.0, 2.0 * offset + galley.size().y));
+            let free = [above, below].into_iter().find(|candidate| {
+                rect.contains_rect(*candidate) && !placed_labels.iter().any(|placed| placed.intersects(*candidate))
+            });
+            if let Some(label_rect) = free {
+                painter.galley(label_rect.min, galley);
+                placed_labels.push(label_rect);
+            }
+        }
+
+        // Cursor readout, only while the pointer is over the plot. It goes to the other side of
+        // the pointer when it would run off the edge.
+        if let Some(pointer) = ui.input(|i| i.pointer.hover_pos()).filter(|pointer| rect.contains(*pointer)) {
+            let freq = x_to_freq(rect, &self.freq_range, pointer.x);
+            let gain = self.params.frequency_response(self.sample_rate, &[freq])[0];
+            let mut text = format!("{} {gain:+.1} dB", formatters::v2s_f32_hz_then_khz(0)(freq));
+            if let Some(spectrum) = self.spectrum {
+                text += &format!(" {:.0} dBFS", level_at(spectrum, self.sample_rate, freq, 0.0));
+            }
+            let faint = self.grid_color.gamma_multiply(0.6);
+            painter.line_segment([Pos2::new(pointer.x, rect.top()), Pos2::new(pointer.x, rect.bottom())], Stroke::new(1.0, faint));
+            painter.line_segment([Pos2::new(rect.left(), pointer.y), Pos2::new(rect.right(), pointer.y)], Stroke::new(1.0, faint));
+            let galley = painter.layout_no_wrap(text, FontId::monospace(8.0), self.line_color);
+            let mut label_rect = Rect::from_min_size(
+                Pos2::new(pointer.x + CURSOR_LABEL_GAP, pointer.y - CURSOR_LABEL_GAP - galley.size().y),
+                galley.size(),
+            );
+            if label_rect.right() > rect.right() {
+                label_rect = label_rect.translate(vec2(-2.0 * CURSOR_LABEL_GAP - galley.size().x, 0.0));
+            }
+            if label_rect.top() < rect.top() {
+                label_rect = label_rect.translate(vec2(0.0, 2.0 * CURSOR_LABEL_GAP + galley.size().y));
+            }
+            painter.rect_filled(label_rect.expand(1.0), 0.0, self.background_color);
+            painter.galley(label_rect.min, galley);
+        }
+
+        // Set every frame so letting go anywhere always goes back to normal
+        if let Some(listen_band) = self.auto_listen {
+            listen_band.store(dragged_band.map_or(NO_LISTEN, |band| band as i32), Ordering::Relaxed);
+        }
+
+        response
+    }
+}
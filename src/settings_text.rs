@@ -0,0 +1,63 @@
+// settings_text.rs - Ardura 2023
+// Settings as one line of text for sharing in chats and forums
+// Looks like `Interleaf1 gain_band_0=3.5 type_0=6 ...` with plain values so people can read it
+
+use crate::InterleafParams;
+use nih_plug::prelude::{ParamPtr, ParamSetter, Params};
+use std::sync::atomic::Ordering;
+
+// Starts every settings line, bump the number if the format ever changes
+const HEADER: &str = "Interleaf1";
+
+/// Every parameter's current plain value after the header
+pub(crate) fn to_text(params: &InterleafParams) -> String {
+    let mut text = String::from(HEADER);
+    for (id, ptr, _) in params.param_map() {
+        // SAFETY: the pointers from param_map live as long as the params
+        let value = unsafe { ptr.unmodulated_plain_value() };
+        text.push_str(&format!(" {id}={value}"));
+    }
+    text
+}
+
+/// Parses the whole text before touching anything so bad text leaves the settings alone.
+/// Params missing from the text keep their current values. Returns how many were set.
+pub(crate) fn apply_text(params: &InterleafParams, setter: &ParamSetter, text: &str) -> Result<usize, String> {
+    let mut parts = text.split_whitespace();
+    if parts.next() != Some(HEADER) {
+        return Err(String::from("Not Interleaf settings"));
+    }
+
+    let param_map = params.param_map();
+    let mut values: Vec<(ParamPtr, f32)> = Vec::new();
+    for part in parts {
+        let (id, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("Can't read '{part}'"))?;
+        let (_, ptr, _) = param_map
+            .iter()
+            .find(|(param_id, _, _)| param_id == id)
+            .ok_or_else(|| format!("Unknown setting '{id}'"))?;
+        let value: f32 = value
+            .parse()
+            .ok()
+            .filter(|value: &f32| value.is_finite())
+            .ok_or_else(|| format!("Bad value for '{id}'"))?;
+        // SAFETY: the pointers from param_map live as long as the params
+        values.push((*ptr, unsafe { ptr.preview_normalized(value) }));
+    }
+
+    // Same gesture layout as randomizing so it's one undo step where hosts allow it
+    for (ptr, _) in values.iter() {
+        // SAFETY: as above
+        unsafe { setter.raw_context.raw_begin_set_parameter(*ptr) };
+    }
+    for (ptr, normalized) in values.iter() {
+        unsafe { setter.raw_context.raw_set_parameter_normalized(*ptr, *normalized) };
+    }
+    for (ptr, _) in values.iter() {
+        unsafe { setter.raw_context.raw_end_set_parameter(*ptr) };
+    }
+    params.bulk_change.store(true, Ordering::Relaxed);
+    Ok(values.len())
+}
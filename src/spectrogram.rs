@@ -0,0 +1,160 @@
+// spectrogram.rs - Ardura 2023
+// Scrolling waterfall of the analyzer frames for content that changes over time
+// The background task keeps the last few seconds of unsmoothed frames in a ring, the editor turns
+// them into a texture each frame with the newest row at the top. Columns follow the graph's log
+// frequency range and take the loudest bin they cover so high frequencies don't alias away.
+
+use crate::analyzer::{ANALYZER_MIN_DB, FFT_SIZE, NUM_BINS};
+use crate::response_graph::x_to_freq;
+use nih_plug_egui::egui::{
+    pos2, Color32, ColorImage, Rect, Response, Sense, Stroke, TextureHandle, TextureOptions, Ui, Vec2, Widget,
+};
+use std::ops::RangeInclusive;
+
+/// Frames kept, this bounds the memory to ROWS * NUM_BINS floats
+pub(crate) const SPECTROGRAM_ROWS: usize = 160;
+// Texture width, egui stretches it over the view
+const SPECTROGRAM_COLUMNS: usize = 256;
+
+pub(crate) struct Spectrogram {
+    // Rows of NUM_BINS in dBFS, written as a ring
+    rows: Vec<f32>,
+    newest: usize,
+}
+
+impl Spectrogram {
+    pub fn new() -> Self {
+        Spectrogram {
+            rows: vec![ANALYZER_MIN_DB; SPECTROGRAM_ROWS * NUM_BINS],
+            newest: 0,
+        }
+    }
+
+    /// Add a frame of bins in dBFS, dropping the oldest
+    pub fn push(&mut self, frame_db: &[f32]) {
+        self.newest = (self.newest + 1) % SPECTROGRAM_ROWS;
+        self.rows[self.newest * NUM_BINS..(self.newest + 1) * NUM_BINS].copy_from_slice(&frame_db[..NUM_BINS]);
+    }
+
+    /// A frame by age, 0 is the newest
+    pub fn row(&self, age: usize) -> &[f32] {
+        let index = (self.newest + SPECTROGRAM_ROWS - age % SPECTROGRAM_ROWS) % SPECTROGRAM_ROWS;
+        &self.rows[index * NUM_BINS..(index + 1) * NUM_BINS]
+    }
+}
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub(crate) struct SpectrogramView<'a> {
+    spectrogram: &'a Spectrogram,
+    // Kept by the editor so the texture is updated instead of made again every frame
+    texture: &'a mut Option<TextureHandle>,
+    sample_rate: f32,
+    freq_range: RangeInclusive<f32>,
+    size: Vec2,
+    background_color: Color32,
+    heat_color: Color32,
+    hot_color: Color32,
+    grid_color: Color32,
+}
+
+impl<'a> SpectrogramView<'a> {
+    pub fn new(
+        spectrogram: &'a Spectrogram,
+        texture: &'a mut Option<TextureHandle>,
+        sample_rate: f32,
+        freq_range: RangeInclusive<f32>,
+        size: Vec2,
+    ) -> Self {
+        Self {
+            spectrogram,
+            texture,
+            sample_rate,
+            freq_range,
+            size,
+            background_color: Color32::BLACK,
+            heat_color: Color32::RED,
+            hot_color: Color32::WHITE,
+            grid_color: Color32::GRAY,
+        }
+    }
+
+    /// Set the color of quiet bins
+    pub fn set_background_color(&mut self, new_color: Color32) {
+        self.background_color = new_color;
+    }
+
+    /// Set the colors halfway up and at full scale
+    pub fn set_heat_colors(&mut self, heat_color: Color32, hot_color: Color32) {
+        self.heat_color = heat_color;
+        self.hot_color = hot_color;
+    }
+
+    /// Set the color of the border
+    pub fn set_grid_color(&mut self, new_color: Color32) {
+        self.grid_color = new_color;
+    }
+
+    // Background to heat over the bottom half of the dB range, heat to hot over the top half
+    fn heat(&self, db: f32) -> Color32 {
+        let position = (1.0 - db / ANALYZER_MIN_DB).clamp(0.0, 1.0) * 2.0;
+        let (from, to, t) = if position < 1.0 {
+            (self.background_color, self.heat_color, position)
+        } else {
+            (self.heat_color, self.hot_color, position - 1.0)
+        };
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+        Color32::from_rgb(mix(from.r(), to.r()), mix(from.g(), to.g()), mix(from.b(), to.b()))
+    }
+}
+
+impl<'a> Widget for SpectrogramView<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(self.size, Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        // The bins each column covers, at least one
+        let column_rect = Rect::from_min_max(pos2(0.0, 0.0), pos2(SPECTROGRAM_COLUMNS as f32, 1.0));
+        let bin_of = |x: f32| {
+            let freq = x_to_freq(column_rect, &self.freq_range, x);
+            ((freq * FFT_SIZE as f32 / self.sample_rate).round() as usize).min(NUM_BINS - 1)
+        };
+        let columns: Vec<(usize, usize)> = (0..SPECTROGRAM_COLUMNS)
+            .map(|column| {
+                let low = bin_of(column as f32);
+                (low, bin_of(column as f32 + 1.0).max(low + 1).min(NUM_BINS))
+            })
+            .collect();
+
+        let mut image = ColorImage::new([SPECTROGRAM_COLUMNS, SPECTROGRAM_ROWS], self.background_color);
+        for age in 0..SPECTROGRAM_ROWS {
+            let row = self.spectrogram.row(age);
+            for (column, (low, high)) in columns.iter().enumerate() {
+                let db = row[*low..*high].iter().fold(ANALYZER_MIN_DB, |loudest, db| loudest.max(*db));
+                image.pixels[age * SPECTROGRAM_COLUMNS + column] = self.heat(db);
+            }
+        }
+        let texture_id = match self.texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::LINEAR);
+                texture.id()
+            }
+            None => self
+                .texture
+                .insert(ui.ctx().load_texture("spectrogram", image, TextureOptions::LINEAR))
+                .id(),
+        };
+
+        let painter = ui.painter_at(rect);
+        painter.image(
+            texture_id,
+            rect,
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        painter.rect_stroke(rect, 0.0, Stroke::new(1.0, self.grid_color));
+
+        response
+    }
+}
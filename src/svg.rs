@@ -0,0 +1,126 @@
+// svg.rs - Ardura 2023
+// Writes the current EQ response to an SVG for sharing settings visually
+// Same axes as the response graph: log frequency across, linear dB up, a marker per band
+
+use crate::response_graph::{db_to_y, freq_to_x, x_to_freq, GRAPH_DB_RANGE, GRAPH_MAX_FREQ, GRAPH_MIN_FREQ};
+use crate::InterleafParams;
+use nih_plug_egui::egui::{pos2, vec2, Rect};
+use std::{fmt::Write as _, fs, path::Path};
+
+const SVG_WIDTH: f32 = 800.0;
+const SVG_HEIGHT: f32 = 400.0;
+// Room around the plot for the axis labels
+const MARGIN: f32 = 40.0;
+const CURVE_POINTS: usize = 400;
+
+const GRID_FREQS: [(f32, &str); 10] = [
+    (20.0, "20"),
+    (50.0, "50"),
+    (100.0, "100"),
+    (200.0, "200"),
+    (500.0, "500"),
+    (1000.0, "1k"),
+    (2000.0, "2k"),
+    (5000.0, "5k"),
+    (10000.0, "10k"),
+    (20000.0, "20k"),
+];
+
+pub(crate) fn write_response_svg(params: &InterleafParams, sample_rate: f32, path: &Path) -> std::io::Result<()> {
+    let plot = Rect::from_min_size(
+        pos2(MARGIN, MARGIN / 2.0),
+        vec2(SVG_WIDTH - MARGIN * 1.5, SVG_HEIGHT - MARGIN * 1.5),
+    );
+    // Always the default range so shared pictures line up with each other
+    let freq_range = GRAPH_MIN_FREQ..=GRAPH_MAX_FREQ;
+    // Writing to a String can't fail so the results are ignored
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SVG_WIDTH}" height="{SVG_HEIGHT}" font-family="monospace" font-size="10">"#
+    );
+    let _ = writeln!(svg, r##"<rect width="100%" height="100%" fill="#04070e"/>"##);
+
+    // Frequency grid and labels
+    for (freq, label) in GRID_FREQS {
+        let x = freq_to_x(plot, &freq_range, freq);
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{x:.1}" y1="{:.1}" x2="{x:.1}" y2="{:.1}" stroke="#73937e" stroke-opacity="0.5"/>"##,
+            plot.top(),
+            plot.bottom()
+        );
+        let _ = writeln!(
+            svg,
+            r##"<text x="{x:.1}" y="{:.1}" fill="#73937e" text-anchor="middle">{label}</text>"##,
+            plot.bottom() + 14.0
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r##"<text x="{:.1}" y="{:.1}" fill="#73937e" text-anchor="middle">Frequency (Hz)</text>"##,
+        plot.center().x,
+        SVG_HEIGHT - 4.0
+    );
+
+    // dB grid and labels every 6 dB
+    let mut db = -GRAPH_DB_RANGE;
+    while db <= GRAPH_DB_RANGE {
+        let y = db_to_y(plot, GRAPH_DB_RANGE, db);
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="#73937e" stroke-opacity="{}"/>"##,
+            plot.left(),
+            plot.right(),
+            if db == 0.0 { "1" } else { "0.5" }
+        );
+        let _ = writeln!(
+            svg,
+            r##"<text x="{:.1}" y="{:.1}" fill="#73937e" text-anchor="end">{db:+.0}</text>"##,
+            plot.left() - 4.0,
+            y + 3.0
+        );
+        db += 6.0;
+    }
+    let _ = writeln!(
+        svg,
+        r##"<text x="10" y="{:.1}" fill="#73937e" transform="rotate(-90 10 {:.1})" text-anchor="middle">Gain (dB)</text>"##,
+        plot.center().y,
+        plot.center().y
+    );
+
+    // Response curve, clipped to the plot like the graph
+    let freqs: Vec<f32> = (0..CURVE_POINTS)
+        .map(|i| x_to_freq(plot, &freq_range, plot.left() + plot.width() * i as f32 / (CURVE_POINTS - 1) as f32))
+        .collect();
+    let responses = params.frequency_response(sample_rate, &freqs);
+    let points: Vec<String> = freqs
+        .iter()
+        .zip(responses.iter())
+        .map(|(freq, db)| {
+            let y = db_to_y(plot, GRAPH_DB_RANGE, db.clamp(-GRAPH_DB_RANGE, GRAPH_DB_RANGE));
+            format!("{:.1},{y:.1}", freq_to_x(plot, &freq_range, *freq))
+        })
+        .collect();
+    let _ = writeln!(
+        svg,
+        r##"<polyline points="{}" fill="none" stroke="#ceb992" stroke-width="2"/>"##,
+        points.join(" ")
+    );
+
+    // Band markers
+    for (index, (type_param, freq_param, gain_param, _)) in params.bands().iter().enumerate() {
+        let node_db = if type_param.value().uses_gain() { gain_param.value() } else { 0.0 };
+        let x = freq_to_x(plot, &freq_range, freq_param.value());
+        let y = db_to_y(plot, GRAPH_DB_RANGE, node_db);
+        let _ = writeln!(svg, r##"<circle cx="{x:.1}" cy="{y:.1}" r="6" fill="#30638e"/>"##);
+        let _ = writeln!(
+            svg,
+            r##"<text x="{x:.1}" y="{:.1}" fill="#04070e" text-anchor="middle">{index}</text>"##,
+            y + 3.5
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    fs::write(path, svg)
+}
@@ -0,0 +1,36 @@
+// test_signal.rs - Ardura 2023
+// Pink noise to feed the bands when the track is silent, for setting up and showing the EQ off
+// White noise from the xorshift goes through Paul Kellet's economy pink filter, which is within
+// about half a dB of -3 dB per octave across the audible range.
+
+use crate::randomize::Xorshift;
+
+/// Roughly where the noise sits, loud enough to see on the analyzer without being a shock
+const PINK_LEVEL: f32 = 0.05;
+
+pub(crate) struct PinkNoise {
+    white: Xorshift,
+    b0: f32,
+    b1: f32,
+    b2: f32,
+}
+
+impl PinkNoise {
+    pub fn new() -> Self {
+        PinkNoise {
+            white: Xorshift::new(0x5EED),
+            b0: 0.0,
+            b1: 0.0,
+            b2: 0.0,
+        }
+    }
+
+    /// Next sample, the same goes to both channels
+    pub fn next_sample(&mut self) -> f32 {
+        let white = self.white.next_f32() * 2.0 - 1.0;
+        self.b0 = 0.99765 * self.b0 + white * 0.0990460;
+        self.b1 = 0.96300 * self.b1 + white * 0.2965164;
+        self.b2 = 0.57000 * self.b2 + white * 1.0526913;
+        (self.b0 + self.b1 + self.b2 + white * 0.1848) * PINK_LEVEL
+    }
+}
@@ -0,0 +1,105 @@
+// true_peak.rs - Ardura 2023
+// True peak detection for the output meter
+// The waveform between samples can go higher than any sample, so we interpolate 3 points between
+// each pair with a short windowed sinc (4x oversampling) and take the largest of all of them.
+// Short enough to be cheap, it's for metering and not for audio so a little ripple is fine.
+
+// Taps each side of the interpolated point
+const HALF_TAPS: usize = 6;
+const TAPS: usize = HALF_TAPS * 2;
+const OVERSAMPLE: usize = 4;
+
+pub(crate) struct TruePeakDetector {
+    // Last TAPS samples per channel, written as a ring
+    history: [[f32; TAPS]; 2],
+    write_pos: usize,
+    // One set of taps per in between point, oldest sample first
+    coefficients: [[f32; TAPS]; OVERSAMPLE - 1],
+}
+
+impl TruePeakDetector {
+    pub fn new() -> Self {
+        let mut coefficients = [[0.0; TAPS]; OVERSAMPLE - 1];
+        for (phase, taps) in coefficients.iter_mut().enumerate() {
+            let fraction = (phase + 1) as f32 / OVERSAMPLE as f32;
+            for (tap, coefficient) in taps.iter_mut().enumerate() {
+                // Distance from the point we want to this tap's sample
+                let x = (HALF_TAPS - 1) as f32 + fraction - tap as f32;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (std::f32::consts::PI * x).sin() / (std::f32::consts::PI * x)
+                };
+                let window = 0.5 + 0.5 * (std::f32::consts::PI * x / HALF_TAPS as f32).cos();
+                *coefficient = sinc * window;
+            }
+            // Unity at DC so a constant signal reads its own level
+            let sum: f32 = taps.iter().sum();
+            taps.iter_mut().for_each(|coefficient| *coefficient /= sum);
+        }
+        TruePeakDetector {
+            history: [[0.0; TAPS]; 2],
+            write_pos: 0,
+            coefficients,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.history = [[0.0; TAPS]; 2];
+    }
+
+    /// Add a stereo sample, returns the true peak of both channels around the sample
+    /// `HALF_TAPS` back. That latency doesn't matter for a meter.
+    pub fn process(&mut self, left: f32, right: f32) -> f32 {
+        self.history[0][self.write_pos] = left;
+        self.history[1][self.write_pos] = right;
+        self.write_pos = (self.write_pos + 1) % TAPS;
+
+        let mut peak: f32 = 0.0;
+        for channel in self.history.iter() {
+            peak = peak.max(channel[(self.write_pos + HALF_TAPS - 1) % TAPS].abs());
+            for taps in self.coefficients.iter() {
+                let interpolated: f32 = taps
+                    .iter()
+                    .enumerate()
+                    .map(|(tap, coefficient)| channel[(self.write_pos + tap) % TAPS] * coefficient)
+                    .sum();
+                peak = peak.max(interpolated.abs());
+            }
+        }
+        peak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inter_sample_peaks_read_higher_than_the_samples() {
+        // A quarter of the sample rate 45 degrees off lands every sample at 0.707 while the
+        // waveform between them reaches 1
+        let signal: Vec<f32> = (0..480)
+            .map(|i| (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin())
+            .collect();
+        let sample_peak = signal.iter().fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+
+        let mut detector = TruePeakDetector::new();
+        let true_peak = signal
+            .iter()
+            .map(|sample| detector.process(*sample, -*sample))
+            .fold(0.0_f32, f32::max);
+        assert!(sample_peak < 0.71, "{sample_peak}");
+        assert!(true_peak > 0.95 && true_peak < 1.05, "{true_peak}");
+    }
+
+    #[test]
+    fn slow_signals_read_their_sample_peak() {
+        let mut detector = TruePeakDetector::new();
+        let true_peak = (0..4800)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 100.0 * i as f32 / 48000.0).sin())
+            .map(|sample| detector.process(sample, sample))
+            .fold(0.0_f32, f32::max);
+        assert!((true_peak - 0.5).abs() < 0.005, "{true_peak}");
+    }
+}
@@ -3,6 +3,7 @@
 // https://github.com/a2aaron/nyasynth/blob/canon/src/ui_knob.rs
 
 use std::{
+    cell::Cell,
     f32::consts::TAU,
     ops::{Add, Mul, Sub},
 };
@@ -31,6 +32,11 @@ lazy_static! {
 struct SliderRegion<'a, P: Param> {
     param: &'a P,
     param_setter: &'a ParamSetter<'a>,
+    // Optional custom stepping for the arrow keys, takes the normalized value and a step count
+    // and gives back the new normalized value
+    step_fn: Option<Box<dyn Fn(f32, i32) -> f32 + 'a>>,
+    // Set while a gesture is in progress, from pressing the knob until letting go
+    touch_flag: Option<&'a Cell<bool>>,
 }
 
 impl<'a, P: Param> SliderRegion<'a, P> {
@@ -38,6 +44,8 @@ impl<'a, P: Param> SliderRegion<'a, P> {
         SliderRegion {
             param,
             param_setter,
+            step_fn: None,
+            touch_flag: None,
         }
     }
 
@@ -45,12 +53,18 @@ impl<'a, P: Param> SliderRegion<'a, P> {
     // the parameter.
     fn handle_response(&self, ui: &Ui, response: &Response) -> f32 {
         let value = self.param.unmodulated_normalized_value();
+        if let Some(touch_flag) = self.touch_flag {
+            if response.is_pointer_button_down_on() {
+                touch_flag.set(true);
+            }
+        }
         if response.drag_started() {
             self.param_setter.begin_set_parameter(self.param);
             ui.memory_mut(|i| i.data.insert_temp(*DRAG_AMOUNT_MEMORY_ID, value))
         }
 
-        if response.dragged() {
+        // Ctrl/Cmd holds the value still so the knob can be pressed as a momentary button
+        if response.dragged() && !ui.input(|i| i.modifiers.command) {
             let delta: f32;
             // Invert the y axis, since we want dragging up to increase the value and down to
             // decrease it, but drag_delta() has the y-axis increasing downwards.
@@ -68,6 +82,21 @@ impl<'a, P: Param> SliderRegion<'a, P> {
             });
         }
 
+        // Arrow keys step the value while hovered
+        if let Some(step_fn) = &self.step_fn {
+            if response.hovered() {
+                let steps = ui.input(|i| {
+                    i.key_pressed(egui::Key::ArrowUp) as i32 - i.key_pressed(egui::Key::ArrowDown) as i32
+                });
+                if steps != 0 {
+                    self.param_setter.begin_set_parameter(self.param);
+                    self.param_setter
+                        .set_parameter_normalized(self.param, step_fn(value, steps).clamp(0.0, 1.0));
+                    self.param_setter.end_set_parameter(self.param);
+                }
+            }
+        }
+
         // Reset on doubleclick
         if response.double_clicked() {
             self.param_setter
@@ -102,6 +131,7 @@ pub struct ArcKnob<'a, P: Param> {
     padding: f32,
     show_label: bool,
     swap_label_and_value: bool,
+    usable_limit: Option<f32>,
 }
 
 #[allow(dead_code)]
@@ -138,9 +168,30 @@ impl<'a, P: Param> ArcKnob<'a, P> {
             padding: 10.0,
             show_label: true,
             swap_label_and_value: true,
+            usable_limit: None,
         }
     }
 
+    // Normalized value past which the param doesn't do anything more, the arc is faded after it
+    pub fn set_usable_limit(&mut self, limit: f32) -> &Self {
+        self.usable_limit = Some(limit.clamp(0.0, 1.0));
+        self
+    }
+
+    // Custom arrow key stepping, gets the normalized value and step count and returns the new
+    // normalized value
+    pub fn set_step_fn(&mut self, step_fn: impl Fn(f32, i32) -> f32 + 'a) -> &Self {
+        self.slider_region.step_fn = Some(Box::new(step_fn));
+        self
+    }
+
+    // Sets the flag for as long as the knob is held, left alone otherwise so several knobs can
+    // share one
+    pub fn set_touch_flag(&mut self, touch_flag: &'a Cell<bool>) -> &Self {
+        self.slider_region.touch_flag = Some(touch_flag);
+        self
+    }
+
     // Undo newer swap label and value
     pub fn set_swap_label_and_value(&mut self, use_old: bool) -> &Self {
         self.swap_label_and_value = use_old;
@@ -294,8 +345,21 @@ impl<'a, P: Param> Widget for ArcKnob<'a, P> {
             // Draw the arc
             let arc_radius = self.center_size + self.center_to_line_space;
             let arc_stroke = Stroke::new(self.line_width, self.line_color);
+            let usable_value = match self.usable_limit {
+                Some(limit) if value > limit => {
+                    // Faded arc for the part past the limit with the usable part drawn over it
+                    painter.add(Shape::Path(PathShape {
+                        points: get_arc_points(center, arc_radius, value, 0.03),
+                        closed: false,
+                        fill: Color32::TRANSPARENT,
+                        stroke: Stroke::new(self.line_width, self.line_color.gamma_multiply(0.3)),
+                    }));
+                    limit
+                }
+                _ => value,
+            };
             let shape = Shape::Path(PathShape {
-                points: get_arc_points(center, arc_radius, value, 0.03),
+                points: get_arc_points(center, arc_radius, usable_value, 0.03),
                 closed: false,
                 fill: Color32::TRANSPARENT,
                 stroke: arc_stroke,
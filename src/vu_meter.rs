@@ -0,0 +1,143 @@
+// vu_meter.rs - Ardura 2023
+// An analog style VU needle meter, the ballistics are done in process so this only draws
+
+use nih_plug_egui::egui::{
+    vec2, Align2, Color32, FontId, NumExt, Pos2, Response, Sense, Stroke, Ui, Widget, WidgetText,
+};
+
+/// Lowest and highest marks on the scale in VU
+pub const VU_MIN: f32 = -20.0;
+pub const VU_MAX: f32 = 3.0;
+// Labelled scale marks
+const VU_MARKS: [f32; 8] = [-20.0, -10.0, -7.0, -5.0, -3.0, -1.0, 0.0, 3.0];
+// The scale sweeps this far either side of straight up, in radians
+const SWEEP: f32 = 0.8;
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct VuMeter {
+    vu: f32,
+    desired_width: Option<f32>,
+    text: Option<WidgetText>,
+    border_color: Color32,
+    needle_color: Color32,
+    over_color: Color32,
+    background_color: Color32,
+}
+
+impl VuMeter {
+    /// Reading in VU, where 0 is the reference level
+    pub fn new(vu: f32) -> Self {
+        Self {
+            vu,
+            desired_width: None,
+            text: None,
+            border_color: Color32::BLACK,
+            needle_color: Color32::WHITE,
+            over_color: Color32::RED,
+            background_color: Color32::GRAY,
+        }
+    }
+
+    /// The desired width of the meter, the height follows at half of it. Will use all horizontal
+    /// space if not set.
+    pub fn desired_width(mut self, desired_width: f32) -> Self {
+        self.desired_width = Some(desired_width);
+        self
+    }
+
+    /// A custom text to display under the needle.
+    pub fn text(mut self, text: impl Into<WidgetText>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Set the color of the outline, scale and text
+    pub fn set_border_color(&mut self, new_color: Color32) {
+        self.border_color = new_color;
+    }
+
+    /// Set the needle color
+    pub fn set_needle_color(&mut self, new_color: Color32) {
+        self.needle_color = new_color;
+    }
+
+    /// Set the background color
+    pub fn set_background_color(&mut self, new_color: Color32) {
+        self.background_color = new_color;
+    }
+}
+
+// Real VU scales are close to linear in voltage so the marks bunch up at the low end
+fn vu_to_position(vu: f32) -> f32 {
+    let min = 10.0_f32.powf(VU_MIN / 20.0);
+    let max = 10.0_f32.powf(VU_MAX / 20.0);
+    ((10.0_f32.powf(vu / 20.0) - min) / (max - min)).clamp(0.0, 1.0)
+}
+
+// Point on the scale arc for a `[0, 1]` position
+fn arc_point(pivot: Pos2, radius: f32, position: f32) -> Pos2 {
+    let angle = -SWEEP + position * SWEEP * 2.0;
+    Pos2::new(pivot.x + radius * angle.sin(), pivot.y - radius * angle.cos())
+}
+
+impl Widget for VuMeter {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let desired_width = self
+            .desired_width
+            .unwrap_or_else(|| ui.available_size_before_wrap().x.at_least(96.0));
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(desired_width, desired_width / 2.0), Sense::hover());
+
+        if ui.is_rect_visible(rect) {
+            let painter = ui.painter_at(rect);
+            painter.rect(rect, 2.0, self.background_color, Stroke::new(1.0, self.border_color));
+
+            // The needle pivots just under the bottom edge like a real meter
+            let pivot = Pos2::new(rect.center().x, rect.bottom() + rect.height() * 0.15);
+            let radius = rect.height() * 0.95;
+
+            // Scale arc, red past 0 VU
+            let zero = vu_to_position(0.0);
+            let steps = 32;
+            for step in 0..steps {
+                let start = step as f32 / steps as f32;
+                let end = (step + 1) as f32 / steps as f32;
+                let color = if start >= zero { self.over_color } else { self.border_color };
+                painter.line_segment(
+                    [arc_point(pivot, radius * 0.85, start), arc_point(pivot, radius * 0.85, end)],
+                    Stroke::new(2.0, color),
+                );
+            }
+            for mark in VU_MARKS {
+                let position = vu_to_position(mark);
+                let color = if mark > 0.0 { self.over_color } else { self.border_color };
+                painter.line_segment(
+                    [arc_point(pivot, radius * 0.85, position), arc_point(pivot, radius * 0.92, position)],
+                    Stroke::new(1.0, color),
+                );
+                painter.text(
+                    arc_point(pivot, radius * 0.98, position),
+                    Align2::CENTER_CENTER,
+                    format!("{}", mark.abs()),
+                    FontId::monospace(7.0),
+                    color,
+                );
+            }
+
+            // Needle
+            let needle_end = arc_point(pivot, radius * 0.9, vu_to_position(self.vu));
+            painter.line_segment([pivot, needle_end], Stroke::new(1.5, self.needle_color));
+
+            if let Some(text) = self.text {
+                let galley = text.into_galley(ui, Some(false), f32::INFINITY, FontId::monospace(8.0));
+                let text_pos = Pos2::new(
+                    rect.center().x - galley.size().x / 2.0,
+                    rect.bottom() - galley.size().y - 2.0,
+                );
+                galley.paint_with_fallback_color(&painter, text_pos, self.border_color);
+            }
+        }
+
+        response
+    }
+}
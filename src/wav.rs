@@ -0,0 +1,39 @@
+// wav.rs - Ardura 2023
+// Bare minimum WAV writer for debug renders, 32 bit float stereo so nothing gets truncated
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+pub(crate) fn write_wav(path: &Path, sample_rate: u32, samples: &[(f32, f32)]) -> std::io::Result<()> {
+    let channels: u16 = 2;
+    let bits_per_sample: u16 = 32;
+    let block_align = channels * bits_per_sample / 8;
+    let byte_rate = sample_rate * block_align as u32;
+    let data_size = samples.len() as u32 * block_align as u32;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    // RIFF header
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    // Format chunk, 3 is IEEE float
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&3u16.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&bits_per_sample.to_le_bytes())?;
+    // Interleaved sample data
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for (left, right) in samples {
+        writer.write_all(&left.to_le_bytes())?;
+        writer.write_all(&right.to_le_bytes())?;
+    }
+    writer.flush()
+}
@@ -0,0 +1,135 @@
+// zoom_view.rs - Ardura 2023
+// A close up of the analyzer over a narrow range for hunting down resonances
+// The bins come from the same FFT as the main analyzer, drawn with Catmull-Rom interpolation
+// between them so a narrow peak reads as a peak instead of a few straight segments.
+// The dB scale follows the loudest bin in range so small bumps are easy to see.
+
+use crate::analyzer::{ANALYZER_MIN_DB, FFT_SIZE};
+use nih_plug::prelude::formatters;
+use nih_plug_egui::egui::{Align2, Color32, FontId, Pos2, Rect, Response, Sense, Shape, Stroke, Ui, Vec2, Widget};
+
+// How many points make up the drawn curve
+const ZOOM_POINTS: usize = 300;
+/// dB shown top to bottom, headroom above the loudest bin
+const ZOOM_DB_RANGE: f32 = 36.0;
+const ZOOM_HEADROOM_DB: f32 = 3.0;
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub(crate) struct ZoomView<'a> {
+    bins: &'a [f32],
+    sample_rate: f32,
+    low_freq: f32,
+    high_freq: f32,
+    size: Vec2,
+    line_color: Color32,
+    grid_color: Color32,
+    background_color: Color32,
+}
+
+impl<'a> ZoomView<'a> {
+    /// Bins in dBFS from the analyzer, shown between the two frequencies
+    pub fn new(bins: &'a [f32], sample_rate: f32, low_freq: f32, high_freq: f32, size: Vec2) -> Self {
+        Self {
+            bins,
+            sample_rate,
+            low_freq,
+            high_freq,
+            size,
+            line_color: Color32::WHITE,
+            grid_color: Color32::GRAY,
+            background_color: Color32::BLACK,
+        }
+    }
+
+    /// Set the color of the spectrum
+    pub fn set_line_color(&mut self, new_color: Color32) {
+        self.line_color = new_color;
+    }
+
+    /// Set the color of the grid and its labels
+    pub fn set_grid_color(&mut self, new_color: Color32) {
+        self.grid_color = new_color;
+    }
+
+    /// Set the background color
+    pub fn set_background_color(&mut self, new_color: Color32) {
+        self.background_color = new_color;
+    }
+
+    // Catmull-Rom through the bins around a fractional bin position
+    fn interpolated_db(&self, bin: f32) -> f32 {
+        let last = self.bins.len() as isize - 1;
+        let index = bin.floor() as isize;
+        let t = bin - bin.floor();
+        let at = |offset: isize| self.bins[(index + offset).clamp(0, last) as usize];
+        let (p0, p1, p2, p3) = (at(-1), at(0), at(1), at(2));
+        0.5 * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t)
+    }
+}
+
+impl<'a> Widget for ZoomView<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(self.size, Sense::hover());
+        if !ui.is_rect_visible(rect) || self.bins.is_empty() || self.high_freq <= self.low_freq {
+            return response;
+        }
+        let painter = ui.painter_at(rect);
+        painter.rect(rect, 0.0, self.background_color, Stroke::new(1.0, self.grid_color));
+
+        let points: Vec<(f32, f32)> = (0..ZOOM_POINTS)
+            .map(|i| {
+                let position = i as f32 / (ZOOM_POINTS - 1) as f32;
+                let freq = self.low_freq * (self.high_freq / self.low_freq).powf(position);
+                let db = self.interpolated_db(freq * FFT_SIZE as f32 / self.sample_rate);
+                (rect.left() + rect.width() * position, db.max(ANALYZER_MIN_DB))
+            })
+            .collect();
+        let top_db = points.iter().fold(ANALYZER_MIN_DB, |top, (_, db)| top.max(*db)) + ZOOM_HEADROOM_DB;
+        let db_to_y = |db: f32| rect.top() + (top_db - db) / ZOOM_DB_RANGE * rect.height();
+
+        // dB grid every 12 dB down from the top
+        let mut db = top_db.floor();
+        while db > top_db - ZOOM_DB_RANGE {
+            let y = db_to_y(db);
+            painter.line_segment(
+                [Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                Stroke::new(1.0, self.grid_color.gamma_multiply(0.4)),
+            );
+            painter.text(
+                Pos2::new(rect.left() + 2.0, y),
+                Align2::LEFT_TOP,
+                format!("{db:.0}"),
+                FontId::monospace(8.0),
+                self.grid_color,
+            );
+            db -= 12.0;
+        }
+
+        let to_string = formatters::v2s_f32_hz_then_khz(1);
+        painter.text(
+            Pos2::new(rect.left() + 2.0, rect.bottom() - 2.0),
+            Align2::LEFT_BOTTOM,
+            to_string(self.low_freq),
+            FontId::monospace(8.0),
+            self.grid_color,
+        );
+        painter.text(
+            Pos2::new(rect.right() - 2.0, rect.bottom() - 2.0),
+            Align2::RIGHT_BOTTOM,
+            to_string(self.high_freq),
+            FontId::monospace(8.0),
+            self.grid_color,
+        );
+
+        let line: Vec<Pos2> = points
+            .iter()
+            .map(|(x, db)| Pos2::new(*x, db_to_y(*db).clamp(rect.top(), rect.bottom())))
+            .collect();
+        painter.add(Shape::line(line, Stroke::new(1.0, self.line_color)));
+
+        response
+    }
+}